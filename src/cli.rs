@@ -0,0 +1,229 @@
+use crate::project::*;
+
+const USAGE: &str = "Usage:
+    OreMapper                                                  Open the editor
+    OreMapper export <project-dir> [-o <data-file>] [-w]       Export project data without opening the editor, -w re-exports on changes
+    OreMapper validate <project-dir>                           List the problems that would fail or change the export
+    OreMapper export-tiled <project-dir> [-o <directory>]      Export maps as Tiled TMX and atlases as TSX
+    OreMapper import-tiled <project-dir> <tmx> [-o <map-file>] Import a Tiled map into the project
+    OreMapper export-ldtk <project-dir> [-o <ldtk-file>]       Export the project as an LDtk project
+    OreMapper import-ldtk <project-dir> <ldtk>                 Import an LDtk project into the project
+    OreMapper render <project-dir> <map> [-o <png>] [-s <n>]   Render a map to a PNG, every pixel n times (1 by default)";
+
+pub fn run(args: &[String]) -> Result<()> {
+    let (command, args) = args.split_first().context(USAGE)?;
+    match command.as_str() {
+        "export" => export(args),
+        "validate" => validate(args),
+        "export-tiled" => export_tiled(args),
+        "import-tiled" => import_tiled(args),
+        "export-ldtk" => export_ldtk(args),
+        "import-ldtk" => import_ldtk(args),
+        "render" => render(args),
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => bail!("Unknown command {:?}!\n{}", command, USAGE),
+    }
+}
+
+// * --------------------------------------------------------------------------------- EXPORT --------------------------------------------------------------------------------- * //
+/// Positional paths, described by `names`, and the optional `-o` path
+fn paths_and_output(args: &[String], names: &[&str]) -> Result<(Vec<PathBuf>, Option<PathBuf>)> {
+    let mut paths = Vec::new();
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(
+                    args.next().context("Expected output path after -o!")?,
+                ));
+            }
+            _ if paths.len() < names.len() => paths.push(PathBuf::from(arg)),
+            _ => bail!("Unexpected argument {:?}!\n{}", arg, USAGE),
+        }
+    }
+
+    if let Some(name) = names.get(paths.len()) {
+        bail!("No {} given!\n{}", name, USAGE);
+    }
+    Ok((paths, output))
+}
+
+fn export(args: &[String]) -> Result<()> {
+    let watch = args.iter().any(|arg| arg == "--watch" || arg == "-w");
+    let args = args
+        .iter()
+        .filter(|arg| *arg != "--watch" && *arg != "-w")
+        .cloned()
+        .collect::<Vec<_>>();
+    let (paths, output) = paths_and_output(&args, &["project directory"])?;
+    let project = &paths[0];
+    if watch {
+        return export_watch(project, output);
+    }
+    let mut assets =
+        Assets::load(project).context(format!("Failed to load project {:?}!", project))?;
+    let output = output.unwrap_or_else(|| assets.path.join("data.dat"));
+    let result = assets.export_to(&output);
+    for problem in &assets.export_problems {
+        println!("{}", problem);
+    }
+    result.context(format!("Failed to export project {:?}!", project))?;
+    print!("{}", assets.export_sizes.text(&assets));
+    println!("Exported {:?} to {:?}", project, output);
+    Ok(())
+}
+
+/// Exports once, then on every change until killed, errors are printed instead of stopping
+fn export_watch(project: &Path, output: Option<PathBuf>) -> Result<()> {
+    use crate::export::watch::*;
+
+    let export = || {
+        let (problems, result) = reexport(project, output.as_deref());
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        match result {
+            Ok(output) => println!("Exported {:?} to {:?}", project, output),
+            Err(err) => eprintln!("Error: {:?}", err),
+        }
+    };
+
+    export();
+    println!("Watching {:?} for changes, press Ctrl+C to stop", project);
+    let mut watcher = Watcher::new(project);
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let changed = watcher.poll();
+        if !changed.is_empty() {
+            println!("{} changed", describe(project, &changed));
+            export();
+        }
+    }
+}
+
+fn validate(args: &[String]) -> Result<()> {
+    let (paths, output) = paths_and_output(args, &["project directory"])?;
+    if output.is_some() {
+        bail!("validate writes no files, -o is not supported!");
+    }
+    let project = &paths[0];
+    let assets = Assets::load(project).context(format!("Failed to load project {:?}!", project))?;
+    let problems = crate::export::validate::validate(&assets);
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    crate::export::validate::check(&problems)?;
+    println!("{:?} can be exported, {} warnings", project, problems.len());
+    Ok(())
+}
+
+fn export_tiled(args: &[String]) -> Result<()> {
+    let (paths, output) = paths_and_output(args, &["project directory"])?;
+    let project = &paths[0];
+    let assets = Assets::load(project).context(format!("Failed to load project {:?}!", project))?;
+    let output = output.unwrap_or_else(|| assets.path.join("tiled"));
+    crate::export::tiled::export(&assets, &output)
+        .context(format!("Failed to export project {:?} to Tiled!", project))?;
+    println!("Exported {:?} to {:?}", project, output);
+    Ok(())
+}
+
+fn export_ldtk(args: &[String]) -> Result<()> {
+    let (paths, output) = paths_and_output(args, &["project directory"])?;
+    let project = &paths[0];
+    let assets = Assets::load(project).context(format!("Failed to load project {:?}!", project))?;
+    let output = output.unwrap_or_else(|| assets.path.join(crate::export::ldtk::PROJECT_FILE));
+    crate::export::ldtk::export(&assets, &output)
+        .context(format!("Failed to export project {:?} to LDtk!", project))?;
+    println!("Exported {:?} to {:?}", project, output);
+    Ok(())
+}
+
+fn render(args: &[String]) -> Result<()> {
+    let mut scale = 1;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-s" | "--scale" => {
+                let value = args.next().context("Expected scale after -s!")?;
+                scale = value
+                    .parse()
+                    .ok()
+                    .filter(|scale| *scale > 0)
+                    .context(format!(
+                        "Scale must be a positive integer, got {:?}!",
+                        value
+                    ))?;
+            }
+            _ => rest.push(arg.clone()),
+        }
+    }
+
+    let (paths, output) = paths_and_output(&rest, &["project directory", "map file"])?;
+    let (project, map_path) = (&paths[0], &paths[1]);
+    let assets = Assets::load(project).context(format!("Failed to load project {:?}!", project))?;
+    let canonical =
+        std::fs::canonicalize(map_path).context(format!("Failed to find map {:?}!", map_path))?;
+    let map = assets
+        .maps
+        .values()
+        .find(|map| std::fs::canonicalize(&map.path).is_ok_and(|path| path == canonical))
+        .context(format!(
+            "Map {:?} is not in project {:?}!",
+            map_path, project
+        ))?;
+    let output = output.unwrap_or_else(|| map.path.with_extension("png"));
+    crate::export::render::render_to(&assets, map, scale, &output)
+        .context(format!("Failed to render map {:?}!", map_path))?;
+    println!("Rendered {:?} to {:?}", map_path, output);
+    Ok(())
+}
+
+// * --------------------------------------------------------------------------------- IMPORT --------------------------------------------------------------------------------- * //
+fn import_tiled(args: &[String]) -> Result<()> {
+    let (paths, output) = paths_and_output(args, &["project directory", "TMX file"])?;
+    let (project, tmx) = (&paths[0], &paths[1]);
+    let mut assets =
+        Assets::load(project).context(format!("Failed to load project {:?}!", project))?;
+    let output = output.unwrap_or_else(|| {
+        assets
+            .path
+            .join(tmx.file_name().unwrap_or_default())
+            .with_extension("map")
+    });
+    let (_, warnings) = crate::import::tiled::import(&mut assets, tmx, &output)
+        .context(format!("Failed to import Tiled map {:?}!", tmx))?;
+    assets
+        .save()
+        .context(format!("Failed to save project {:?}!", project))?;
+    for warning in warnings {
+        println!("Warning: {}", warning);
+    }
+    println!("Imported {:?} to {:?}", tmx, output);
+    Ok(())
+}
+
+fn import_ldtk(args: &[String]) -> Result<()> {
+    let (paths, output) = paths_and_output(args, &["project directory", "LDtk file"])?;
+    if output.is_some() {
+        bail!("LDtk levels are imported into the project directory, -o is not supported!");
+    }
+    let (project, ldtk) = (&paths[0], &paths[1]);
+    let mut assets =
+        Assets::load(project).context(format!("Failed to load project {:?}!", project))?;
+    let (maps, warnings) = crate::import::ldtk::import(&mut assets, ldtk)
+        .context(format!("Failed to import LDtk project {:?}!", ldtk))?;
+    assets
+        .save()
+        .context(format!("Failed to save project {:?}!", project))?;
+    for warning in warnings {
+        println!("Warning: {}", warning);
+    }
+    println!("Imported {} levels from {:?}", maps.len(), ldtk);
+    Ok(())
+}
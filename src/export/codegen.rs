@@ -0,0 +1,723 @@
+use crate::export::header::FORMAT_VERSION;
+use crate::export::layout::*;
+use crate::project::*;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// * ---------------------------------------------------------------------------------- NAMES --------------------------------------------------------------------------------- * //
+/// Path relative to the project without extension, or name of a builtin component
+pub fn asset_name(assets: &Assets, path: &Path) -> String {
+    if path.starts_with("/\nbuiltin/") {
+        let stem = path.file_stem().unwrap().to_string_lossy();
+        stem.split_once(" - ")
+            .map_or(stem.to_string(), |(_, name)| name.to_owned())
+    } else {
+        path.strip_prefix(&assets.path)
+            .unwrap_or(path)
+            .with_extension("")
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Lowercase words, split on non-alphanumeric characters and camel case
+pub fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous_lowercase = false;
+    for char in name.chars() {
+        if (!char.is_ascii_alphanumeric() || (char.is_ascii_uppercase() && previous_lowercase))
+            && !word.is_empty()
+        {
+            words.push(std::mem::take(&mut word));
+        }
+        if char.is_ascii_alphanumeric() {
+            word.push(char.to_ascii_lowercase());
+        }
+        previous_lowercase = char.is_ascii_lowercase() || char.is_ascii_digit();
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn upper_snake(words: &[String]) -> String {
+    words.iter().map(|word| word.to_ascii_uppercase()).join("_")
+}
+
+pub fn camel(words: &[String]) -> String {
+    words
+        .iter()
+        .map(|word| word[..1].to_ascii_uppercase() + &word[1..])
+        .join("")
+}
+
+fn field_name(name: &str) -> String {
+    let name = split_words(name).join("_");
+    if name.is_empty() || name.starts_with(|char: char| char.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+struct Asset<'a> {
+    index: u16,
+    words: Vec<String>,
+    /// Component properties, or tile properties of an atlas that has any
+    properties: Option<&'a IndexMap<String, Property>>,
+}
+
+/// Number of records in data.dat, IDs may leave gaps
+fn count(list: &[Asset]) -> u32 {
+    list.last().map_or(0, |asset| asset.index as u32 + 1)
+}
+
+/// Maps, atlases and components sorted by index
+fn asset_lists(assets: &Assets) -> Result<[(&'static str, Vec<Asset<'_>>); 3]> {
+    fn list<'a, T>(
+        assets: &Assets,
+        kind: &str,
+        items: &'a HashMap<Uuid, T>,
+        indices: &HashMap<Uuid, u16>,
+        path: impl Fn(&T) -> &PathBuf,
+        properties: impl Fn(&'a T) -> Option<&'a IndexMap<String, Property>>,
+    ) -> Result<Vec<Asset<'a>>> {
+        let list = items
+            .iter()
+            .map(|(uuid, item)| Asset {
+                index: indices[uuid],
+                words: split_words(&asset_name(assets, path(item))),
+                properties: properties(item),
+            })
+            .sorted_by_key(|asset| asset.index)
+            .collect::<Vec<_>>();
+        if let Some(asset) = list.iter().find(|asset| asset.words.is_empty()) {
+            bail!(
+                "The name of {} {} has no letters or digits to make an identifier from!",
+                kind,
+                asset.index
+            );
+        }
+        if let Some(duplicate) = list.iter().map(|asset| &asset.words).duplicates().next() {
+            bail!(
+                "Several {}s are named {}, rename one of them!",
+                kind,
+                upper_snake(duplicate)
+            );
+        }
+        // Escaping keywords can make a name collide too
+        for asset in &list {
+            for escape in [c_field_name as fn(&str) -> String, rust_field_name] {
+                let fields = asset.properties.into_iter().flat_map(IndexMap::keys);
+                if let Some(field) = fields.map(|name| escape(name)).duplicates().next() {
+                    bail!(
+                        "Several properties of {} {} are named {}, rename one of them!",
+                        kind,
+                        upper_snake(&asset.words),
+                        field
+                    );
+                }
+            }
+        }
+        Ok(list)
+    }
+
+    Ok([
+        (
+            "ATLAS",
+            list(
+                assets,
+                "atlas",
+                &assets.atlases,
+                &assets.atlas_indices,
+                |atlas| &atlas.path,
+                |atlas| (!atlas.tile_properties.is_empty()).then_some(&atlas.tile_properties),
+            )?,
+        ),
+        (
+            "MAP",
+            list(
+                assets,
+                "map",
+                &assets.maps,
+                &assets.map_indices(),
+                |map| &map.path,
+                |_| None,
+            )?,
+        ),
+        (
+            "COMPONENT",
+            list(
+                assets,
+                "component",
+                &assets.components,
+                &assets.component_indices(),
+                |component| &component.path,
+                |component| Some(&component.properties),
+            )?,
+        ),
+    ])
+}
+
+// * ------------------------------------------------------------------------------------ C ----------------------------------------------------------------------------------- * //
+pub fn c_header(assets: &Assets) -> Result<String> {
+    let mut code = String::new();
+    writeln!(
+        code,
+        "/* Generated by OreMapper from the project, do not edit! */"
+    )?;
+    writeln!(code, "#ifndef ORE_DATA_H")?;
+    writeln!(code, "#define ORE_DATA_H")?;
+    writeln!(code)?;
+    writeln!(code, "#include <stdint.h>")?;
+    writeln!(code, "#include <string.h>")?;
+    writeln!(code)?;
+    writeln!(code, "#define ORE_FORMAT_VERSION {}", FORMAT_VERSION)?;
+    let alignment = assets.export_settings.alignment;
+    writeln!(code, "#define ORE_ALIGNMENT {}", alignment.bytes())?;
+    writeln!(code)?;
+    writeln!(
+        code,
+        "/* Collision shape header: kind in the low bits, one-way flag in the top bit */"
+    )?;
+    writeln!(code, "#define ORE_SHAPE_RECT 0")?;
+    writeln!(code, "#define ORE_SHAPE_POLYGON 1")?;
+    writeln!(code, "#define ORE_SHAPE_ONE_WAY 0x80")?;
+
+    let lists = asset_lists(assets)?;
+    for (kind, list) in &lists {
+        writeln!(code)?;
+        for asset in list {
+            writeln!(
+                code,
+                "#define ORE_{}_{} {}",
+                kind,
+                upper_snake(&asset.words),
+                asset.index
+            )?;
+        }
+        writeln!(code, "#define ORE_{}_COUNT {}", kind, count(list))?;
+    }
+
+    let (read_u16, read_u32) = match assets.export_settings.byte_order {
+        ByteOrder::LittleEndian => (
+            "data[0] | data[1] << 8",
+            "(uint32_t)data[0] | (uint32_t)data[1] << 8 | (uint32_t)data[2] << 16 | (uint32_t)data[3] << 24",
+        ),
+        ByteOrder::BigEndian => (
+            "data[0] << 8 | data[1]",
+            "(uint32_t)data[0] << 24 | (uint32_t)data[1] << 16 | (uint32_t)data[2] << 8 | (uint32_t)data[3]",
+        ),
+    };
+    writeln!(code)?;
+    writeln!(
+        code,
+        "typedef struct {{
+    uint16_t length;
+    const uint8_t *data;
+}} OreString;
+
+/* Values are aligned in memory as long as data.dat is loaded at a multiple of ORE_ALIGNMENT */
+static inline const uint8_t *ore_align(const uint8_t *data, uintptr_t alignment) {{
+    return data + (alignment - (uintptr_t)data % alignment) % alignment;
+}}
+
+static inline uint16_t ore_read_u16(const uint8_t *data) {{
+    return (uint16_t)({});
+}}
+
+static inline uint32_t ore_read_u32(const uint8_t *data) {{
+    return {};
+}}
+
+static inline float ore_read_f32(const uint8_t *data) {{
+    uint32_t bits = ore_read_u32(data);
+    float value;
+    memcpy(&value, &bits, sizeof(value));
+    return value;
+}}",
+        read_u16, read_u32
+    )?;
+
+    for asset in &lists[0].1 {
+        if let Some(properties) = asset.properties {
+            c_record(
+                &mut code,
+                &format!("{}Tile", camel(&asset.words)),
+                &format!("{}_tile", asset.words.join("_")),
+                "Reads a record of the tile property table, returns pointer past it",
+                properties,
+                alignment,
+            )?;
+        }
+    }
+    for asset in &lists[2].1 {
+        let properties = asset.properties.unwrap();
+        if !properties.is_empty() {
+            c_record(
+                &mut code,
+                &camel(&asset.words),
+                &asset.words.join("_"),
+                "Reads component values, returns pointer past them",
+                properties,
+                alignment,
+            )?;
+        }
+    }
+
+    writeln!(code)?;
+    writeln!(code, "#endif")?;
+    Ok(code)
+}
+
+/// Struct and reader of a record of property values
+fn c_record(
+    code: &mut String,
+    name: &str,
+    function: &str,
+    doc: &str,
+    properties: &IndexMap<String, Property>,
+    alignment: Alignment,
+) -> Result<()> {
+    writeln!(code)?;
+    writeln!(code, "typedef struct {{")?;
+    for (property, property_type) in properties {
+        let c_type = match property_type {
+            Property::I8 => "int8_t",
+            Property::U8 => "uint8_t",
+            Property::I16 => "int16_t",
+            Property::U16 | Property::Atlas | Property::Map => "uint16_t",
+            Property::I32 => "int32_t",
+            Property::U32 => "uint32_t",
+            Property::F32 => "float",
+            Property::String => "OreString",
+        };
+        let comment = match property_type {
+            Property::Atlas => " /* ORE_ATLAS_* */",
+            Property::Map => " /* ORE_MAP_* */",
+            _ => "",
+        };
+        writeln!(
+            code,
+            "    {} {};{}",
+            c_type,
+            c_field_name(property),
+            comment
+        )?;
+    }
+    writeln!(code, "}} Ore{};", name)?;
+    writeln!(code)?;
+    writeln!(code, "/* {} */", doc)?;
+    writeln!(
+        code,
+        "static inline const uint8_t *ore_read_{}(const uint8_t *data, Ore{} *out) {{",
+        function, name
+    )?;
+    for (property, property_type) in properties {
+        let field = c_field_name(property);
+        let align = alignment.of(value_size(property_type));
+        if align > 1 {
+            writeln!(code, "    data = ore_align(data, {});", align)?;
+        }
+        match property_type {
+            Property::I8 => writeln!(code, "    out->{} = (int8_t)data[0];", field)?,
+            Property::U8 => writeln!(code, "    out->{} = data[0];", field)?,
+            Property::I16 => writeln!(code, "    out->{} = (int16_t)ore_read_u16(data);", field)?,
+            Property::U16 | Property::Atlas | Property::Map => {
+                writeln!(code, "    out->{} = ore_read_u16(data);", field)?
+            }
+            Property::I32 => writeln!(code, "    out->{} = (int32_t)ore_read_u32(data);", field)?,
+            Property::U32 => writeln!(code, "    out->{} = ore_read_u32(data);", field)?,
+            Property::F32 => writeln!(code, "    out->{} = ore_read_f32(data);", field)?,
+            Property::String => {
+                writeln!(code, "    out->{}.length = ore_read_u16(data);", field)?;
+                writeln!(code, "    out->{}.data = data + 2;", field)?;
+            }
+        }
+        match property_type {
+            Property::String => writeln!(code, "    data += 2 + out->{}.length;", field)?,
+            property_type => writeln!(code, "    data += {};", value_size(property_type))?,
+        }
+    }
+    writeln!(code, "    return data;")?;
+    writeln!(code, "}}")?;
+    Ok(())
+}
+
+const C_KEYWORDS: [&str; 37] = [
+    "auto", "bool", "break", "case", "char", "const", "continue", "default", "do", "double",
+    "else", "enum", "extern", "false", "float", "for", "goto", "if", "inline", "int", "long",
+    "register", "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch",
+    "true", "typedef", "union", "unsigned", "void", "volatile", "while",
+];
+
+fn c_field_name(name: &str) -> String {
+    let name = field_name(name);
+    if C_KEYWORDS.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+fn value_size(property_type: &Property) -> usize {
+    match property_type {
+        Property::I8 | Property::U8 => 1,
+        Property::I16 | Property::U16 | Property::Atlas | Property::Map => 2,
+        Property::I32 | Property::U32 | Property::F32 => 4,
+        Property::String => 2,
+    }
+}
+
+// * ---------------------------------------------------------------------------------- RUST ---------------------------------------------------------------------------------- * //
+/// Strict and reserved keywords up to the 2024 edition
+const RUST_KEYWORDS: [&str; 51] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+pub fn rust_module(assets: &Assets) -> Result<String> {
+    let mut code = String::new();
+    writeln!(
+        code,
+        "// Generated by OreMapper from the project, do not edit!"
+    )?;
+    writeln!(code)?;
+    writeln!(code, "pub const FORMAT_VERSION: u16 = {};", FORMAT_VERSION)?;
+    let alignment = assets.export_settings.alignment;
+    writeln!(code, "pub const ALIGNMENT: usize = {};", alignment.bytes())?;
+    writeln!(code)?;
+    writeln!(
+        code,
+        "/// Collision shape header: kind in the low bits, one-way flag in the top bit"
+    )?;
+    writeln!(code, "pub const SHAPE_RECT: u8 = 0;")?;
+    writeln!(code, "pub const SHAPE_POLYGON: u8 = 1;")?;
+    writeln!(code, "pub const SHAPE_ONE_WAY: u8 = 0x80;")?;
+    let from_bytes = match assets.export_settings.byte_order {
+        ByteOrder::LittleEndian => "from_le_bytes",
+        ByteOrder::BigEndian => "from_be_bytes",
+    };
+
+    let lists = asset_lists(assets)?;
+    for (kind, list) in &lists {
+        writeln!(code)?;
+        for asset in list {
+            writeln!(
+                code,
+                "pub const {}_{}: u16 = {};",
+                kind,
+                upper_snake(&asset.words),
+                asset.index
+            )?;
+        }
+        writeln!(code, "pub const {}_COUNT: u16 = {};", kind, count(list))?;
+    }
+
+    writeln!(code)?;
+    writeln!(
+        code,
+        "#[allow(dead_code)]
+fn take<const N: usize>(data: &mut &[u8]) -> Option<[u8; N]> {{
+    let (bytes, rest) = data.split_first_chunk::<N>()?;
+    *data = rest;
+    Some(*bytes)
+}}
+
+/// Values are aligned in memory as long as data.dat is loaded at a multiple of ALIGNMENT
+#[allow(dead_code)]
+fn align(data: &mut &[u8], alignment: usize) -> Option<()> {{
+    let padding = (alignment - data.as_ptr() as usize % alignment) % alignment;
+    *data = data.get(padding..)?;
+    Some(())
+}}
+
+#[allow(dead_code)]
+fn take_str<'a>(data: &mut &'a [u8]) -> Option<&'a str> {{
+    let length = u16::{}(take(data)?) as usize;
+    if data.len() < length {{
+        return None;
+    }}
+    let (bytes, rest) = data.split_at(length);
+    *data = rest;
+    core::str::from_utf8(bytes).ok()
+}}",
+        from_bytes
+    )?;
+
+    for asset in &lists[0].1 {
+        if let Some(properties) = asset.properties {
+            let mut name = camel(&asset.words);
+            if name.starts_with(|char: char| char.is_ascii_digit()) {
+                name = format!("Atlas{}", name);
+            }
+            rust_record(
+                &mut code,
+                &format!("{}Tile", name),
+                ("ATLAS", asset.index),
+                "Reads a record of the tile property table and advances data past it",
+                properties,
+                alignment,
+                from_bytes,
+            )?;
+        }
+    }
+    for asset in &lists[2].1 {
+        let mut name = camel(&asset.words);
+        if name.starts_with(|char: char| char.is_ascii_digit()) {
+            name = format!("Component{}", name);
+        }
+        rust_record(
+            &mut code,
+            &name,
+            ("INDEX", asset.index),
+            "Reads component values and advances data past them",
+            asset.properties.unwrap(),
+            alignment,
+            from_bytes,
+        )?;
+    }
+    Ok(code)
+}
+
+/// Struct of a record of property values with an index constant and a reader
+fn rust_record(
+    code: &mut String,
+    name: &str,
+    (constant, index): (&str, u16),
+    doc: &str,
+    properties: &IndexMap<String, Property>,
+    alignment: Alignment,
+    from_bytes: &str,
+) -> Result<()> {
+    let lifetime = if properties.values().contains(&Property::String) {
+        "<'a>"
+    } else {
+        ""
+    };
+
+    writeln!(code)?;
+    if properties.is_empty() {
+        writeln!(code, "pub struct {};", name)?;
+    } else {
+        writeln!(code, "pub struct {}{} {{", name, lifetime)?;
+        for (property, property_type) in properties {
+            match property_type {
+                Property::Atlas => writeln!(code, "    /// ATLAS_*")?,
+                Property::Map => writeln!(code, "    /// MAP_*")?,
+                _ => (),
+            }
+            writeln!(
+                code,
+                "    pub {}: {},",
+                rust_field_name(property),
+                rust_type(property_type)
+            )?;
+        }
+        writeln!(code, "}}")?;
+    }
+
+    writeln!(code)?;
+    writeln!(code, "impl{} {}{} {{", lifetime, name, lifetime)?;
+    writeln!(code, "    pub const {}: u16 = {};", constant, index)?;
+    writeln!(code)?;
+    writeln!(code, "    /// {}", doc)?;
+    let data_lifetime = if lifetime.is_empty() { "" } else { "'a " };
+    writeln!(
+        code,
+        "    pub fn read(data: &mut &{}[u8]) -> Option<Self> {{",
+        data_lifetime
+    )?;
+    if properties.is_empty() {
+        writeln!(code, "        let _ = data;")?;
+        writeln!(code, "        Some(Self)")?;
+    } else {
+        writeln!(code, "        Some(Self {{")?;
+        for (property, property_type) in properties {
+            let mut value = match property_type {
+                Property::String => "take_str(data)?".to_owned(),
+                property_type => {
+                    format!("{}::{}(take(data)?)", rust_type(property_type), from_bytes)
+                }
+            };
+            let align = alignment.of(value_size(property_type));
+            if align > 1 {
+                value = format!("{{ align(data, {})?; {} }}", align, value);
+            }
+            writeln!(
+                code,
+                "            {}: {},",
+                rust_field_name(property),
+                value
+            )?;
+        }
+        writeln!(code, "        }})")?;
+    }
+    writeln!(code, "    }}")?;
+    writeln!(code, "}}")?;
+    Ok(())
+}
+
+fn rust_type(property_type: &Property) -> &'static str {
+    match property_type {
+        Property::I8 => "i8",
+        Property::U8 => "u8",
+        Property::I16 => "i16",
+        Property::U16 | Property::Atlas | Property::Map => "u16",
+        Property::I32 => "i32",
+        Property::U32 => "u32",
+        Property::F32 => "f32",
+        Property::String => "&'a str",
+    }
+}
+
+fn rust_field_name(name: &str) -> String {
+    let name = field_name(name);
+    if RUST_KEYWORDS.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        let mut assets = Assets::new(Path::new("/game"));
+        let atlas = Uuid::new_v4();
+        let mut component = Component::new(Path::new("/game/enemies/BigSlime.cmp"));
+        component.export_id = 2;
+        component.properties.insert("type".to_owned(), Property::U8);
+        component
+            .properties
+            .insert("Max HP".to_owned(), Property::I16);
+        component
+            .properties
+            .insert("default".to_owned(), Property::F32);
+        component.properties.insert("try".to_owned(), Property::U8);
+        let slime = Uuid::new_v4();
+        assets.components.insert(slime, component);
+        assets.maps.insert(
+            Uuid::new_v4(),
+            Map::new(
+                Path::new("/game/levels/forest-1.map"),
+                U16Vec2::new(1, 1),
+                atlas,
+            ),
+        );
+
+        let c = c_header(&assets).unwrap();
+        assert!(c.contains("#define ORE_MAP_LEVELS_FOREST_1 0\n"));
+        assert!(c.contains("#define ORE_COMPONENT_ATLAS_RENDERER 0\n"));
+        assert!(c.contains("#define ORE_COMPONENT_ENEMIES_BIG_SLIME 2\n"));
+        assert!(c.contains(
+            "    uint8_t type;\n    int16_t max_hp;\n    float default_;\n    uint8_t try;\n} OreEnemiesBigSlime;"
+        ));
+
+        let rust = rust_module(&assets).unwrap();
+        assert!(rust.contains("pub const MAP_LEVELS_FOREST_1: u16 = 0;\n"));
+        assert!(rust.contains(
+            "pub struct EnemiesBigSlime {\n    pub type_: u8,\n    pub max_hp: i16,\n    pub default: f32,\n    pub try_: u8,\n}"
+        ));
+
+        // Both become max_hp
+        let properties = &mut assets.components.get_mut(&slime).unwrap().properties;
+        properties.insert("max_hp".to_owned(), Property::U8);
+        assert_eq!(
+            c_header(&assets).unwrap_err().to_string(),
+            "Several properties of component ENEMIES_BIG_SLIME are named max_hp, rename one of them!"
+        );
+        let properties = &mut assets.components.get_mut(&slime).unwrap().properties;
+        properties.shift_remove("max_hp");
+
+        assets.maps.insert(
+            Uuid::new_v4(),
+            Map::new(
+                Path::new("/game/levels/forest_1.map"),
+                U16Vec2::new(1, 1),
+                atlas,
+            ),
+        );
+        assert!(c_header(&assets).is_err());
+    }
+
+    #[test]
+    fn tile_records() {
+        let mut assets = Assets::new(Path::new("/game"));
+        let uuid = Uuid::new_v4();
+        let mut atlas = Atlas::new(
+            Path::new("/game/Tiles.png"),
+            image::RgbaImage::new(8, 8),
+            U16Vec2::new(8, 8),
+        );
+        atlas.export_id = 1;
+        atlas
+            .tile_properties
+            .insert("solid".to_owned(), Property::U8);
+        atlas
+            .tile_properties
+            .insert("sound".to_owned(), Property::String);
+        assets.atlases.insert(uuid, atlas);
+        assets.atlas_indices.insert(uuid, 1);
+
+        let c = c_header(&assets).unwrap();
+        assert!(c.contains("    uint8_t solid;\n    OreString sound;\n} OreTilesTile;"));
+        assert!(c.contains("static inline const uint8_t *ore_read_tiles_tile(const uint8_t *data, OreTilesTile *out) {"));
+        let rust = rust_module(&assets).unwrap();
+        assert!(rust.contains(
+            "pub struct TilesTile<'a> {\n    pub solid: u8,\n    pub sound: &'a str,\n}"
+        ));
+        assert!(rust.contains("    pub const ATLAS: u16 = 1;\n"));
+
+        assets
+            .atlases
+            .get_mut(&uuid)
+            .unwrap()
+            .tile_properties
+            .clear();
+        assert!(!c_header(&assets).unwrap().contains("OreTilesTile"));
+    }
+
+    #[test]
+    fn byte_order_and_alignment() {
+        let mut assets = Assets::new(Path::new("/game"));
+        let mut component = Component::new(Path::new("/game/Door.cmp"));
+        component.export_id = 2;
+        component.properties.insert("key".to_owned(), Property::U8);
+        component.properties.insert("id".to_owned(), Property::I32);
+        assets.components.insert(Uuid::new_v4(), component);
+
+        let c = c_header(&assets).unwrap();
+        assert!(c.contains("#define ORE_ALIGNMENT 1\n"));
+        assert!(c.contains("return (uint16_t)(data[0] | data[1] << 8);"));
+        assert!(!c.contains("    data = ore_align("));
+        let rust = rust_module(&assets).unwrap();
+        assert!(rust.contains("            id: i32::from_le_bytes(take(data)?),\n"));
+
+        assets.export_settings.byte_order = ByteOrder::BigEndian;
+        assets.export_settings.alignment = Alignment::Align4;
+        let c = c_header(&assets).unwrap();
+        assert!(c.contains("#define ORE_ALIGNMENT 4\n"));
+        assert!(c.contains("return (uint16_t)(data[0] << 8 | data[1]);"));
+        assert!(c.contains(
+            "    out->key = data[0];\n    data += 1;\n    data = ore_align(data, 4);\n    out->id ="
+        ));
+        let rust = rust_module(&assets).unwrap();
+        assert!(rust.contains("pub const ALIGNMENT: usize = 4;\n"));
+        assert!(rust.contains("let length = u16::from_be_bytes(take(data)?) as usize;"));
+        assert!(rust.contains("            key: u8::from_be_bytes(take(data)?),\n"));
+        assert!(rust
+            .contains("            id: { align(data, 4)?; i32::from_be_bytes(take(data)?) },\n"));
+    }
+}
@@ -0,0 +1,262 @@
+use crate::project::*;
+use std::collections::HashMap;
+
+// * ------------------------------------------------------------------------------ COMPRESSION ------------------------------------------------------------------------------- * //
+// Both formats are described in DataFormat.md
+const RLE_MAX_LITERALS: usize = 128;
+const RLE_MIN_RUN: usize = 3;
+const RLE_MAX_RUN: usize = 130;
+
+const LZ_WINDOW: usize = 4096;
+const LZ_MIN_MATCH: usize = 3;
+const LZ_MAX_MATCH: usize = 18;
+const LZ_MAX_CANDIDATES: usize = 64;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[repr(u8)]
+pub enum Compression {
+    #[default]
+    None = 0,
+    Rle = 1,
+    Lz = 2,
+}
+
+impl Compression {
+    pub const VALUES: [Self; 3] = [Self::None, Self::Rle, Self::Lz];
+
+    pub fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::None => data.to_vec(),
+            Self::Rle => compress_rle(data),
+            Self::Lz => compress_lz(data),
+        }
+    }
+
+    pub fn decompress(self, data: &[u8], length: usize) -> Result<Vec<u8>> {
+        let output = match self {
+            Self::None => data.to_vec(),
+            Self::Rle => decompress_rle(data, length)?,
+            Self::Lz => decompress_lz(data, length)?,
+        };
+        if output.len() != length {
+            bail!("Decompressed {} bytes, expected {}!", output.len(), length);
+        }
+        Ok(output)
+    }
+}
+
+// * ----------------------------------------------------------------------------------- RLE ---------------------------------------------------------------------------------- * //
+fn compress_rle(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut literals = Vec::new();
+    let flush = |output: &mut Vec<u8>, literals: &mut Vec<u8>| {
+        for chunk in literals.chunks(RLE_MAX_LITERALS) {
+            output.push((chunk.len() - 1) as u8);
+            output.extend_from_slice(chunk);
+        }
+        literals.clear();
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let run = data[pos..]
+            .iter()
+            .take(RLE_MAX_RUN)
+            .take_while(|&&byte| byte == data[pos])
+            .count();
+        if run >= RLE_MIN_RUN {
+            flush(&mut output, &mut literals);
+            output.push((run - RLE_MIN_RUN + 128) as u8);
+            output.push(data[pos]);
+            pos += run;
+        } else {
+            literals.push(data[pos]);
+            pos += 1;
+        }
+    }
+    flush(&mut output, &mut literals);
+    output
+}
+
+fn decompress_rle(data: &[u8], length: usize) -> Result<Vec<u8>> {
+    // The length comes from the file, two bytes expand to a run at most
+    let mut output = Vec::with_capacity(length.min(data.len() / 2 * RLE_MAX_RUN));
+    let mut data = data.iter().copied();
+    while output.len() < length {
+        let control = data.next().context("RLE data is truncated!")? as usize;
+        if control < 128 {
+            for _ in 0..=control {
+                output.push(data.next().context("RLE literals are truncated!")?);
+            }
+        } else {
+            let byte = data.next().context("RLE run is truncated!")?;
+            output.extend(std::iter::repeat_n(byte, control - 128 + RLE_MIN_RUN));
+        }
+    }
+    Ok(output)
+}
+
+// * ----------------------------------------------------------------------------------- LZ ----------------------------------------------------------------------------------- * //
+fn compress_lz(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut candidates = HashMap::<&[u8], Vec<usize>>::new();
+    let (mut flags, mut bit) = (0, 8);
+
+    let mut pos = 0;
+    while pos < data.len() {
+        if bit == 8 {
+            flags = output.len();
+            output.push(0);
+            bit = 0;
+        }
+
+        // Longest match among recent positions with the same prefix
+        let (mut length, mut distance) = (0, 0);
+        if let Some(positions) = data
+            .get(pos..pos + LZ_MIN_MATCH)
+            .and_then(|prefix| candidates.get(prefix))
+        {
+            for &start in positions.iter().rev().take(LZ_MAX_CANDIDATES) {
+                if pos - start > LZ_WINDOW {
+                    break;
+                }
+                let match_length = data[start..]
+                    .iter()
+                    .zip(&data[pos..])
+                    .take(LZ_MAX_MATCH)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if match_length > length {
+                    (length, distance) = (match_length, pos - start);
+                }
+            }
+        }
+
+        let advance = if length >= LZ_MIN_MATCH {
+            output[flags] |= 0x80 >> bit;
+            output.push(((length - LZ_MIN_MATCH) << 4 | (distance - 1) >> 8) as u8);
+            output.push((distance - 1) as u8);
+            length
+        } else {
+            output.push(data[pos]);
+            1
+        };
+        for start in pos..pos + advance {
+            if let Some(prefix) = data.get(start..start + LZ_MIN_MATCH) {
+                candidates.entry(prefix).or_default().push(start);
+            }
+        }
+        pos += advance;
+        bit += 1;
+    }
+    output
+}
+
+fn decompress_lz(data: &[u8], length: usize) -> Result<Vec<u8>> {
+    // The length comes from the file, two bytes expand to a match at most
+    let mut output = Vec::with_capacity(length.min(data.len() / 2 * LZ_MAX_MATCH));
+    let mut data = data.iter().copied();
+    while output.len() < length {
+        let flags = data.next().context("LZ flags are truncated!")?;
+        for bit in 0..8 {
+            if output.len() >= length {
+                break;
+            }
+            if flags & 0x80 >> bit == 0 {
+                output.push(data.next().context("LZ literal is truncated!")?);
+            } else {
+                let (high, low) = (
+                    data.next().context("LZ match is truncated!")? as usize,
+                    data.next().context("LZ match is truncated!")? as usize,
+                );
+                let match_length = (high >> 4) + LZ_MIN_MATCH;
+                let distance = ((high & 0xF) << 8 | low) + 1;
+                if distance > output.len() {
+                    bail!("LZ match points before the start of data!");
+                }
+                for _ in 0..match_length {
+                    output.push(output[output.len() - distance]);
+                }
+            }
+        }
+    }
+    Ok(output)
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::header::*;
+    use crate::export::reader::*;
+    use crate::export::test_project::*;
+
+    fn samples() -> Vec<Vec<u8>> {
+        vec![
+            vec![],
+            vec![42],
+            vec![0xFF; 1000],
+            (0..=255).collect(),
+            (0..5000u32).map(|x| (x * x % 251) as u8).collect(),
+            (0..3000u32).map(|x| (x / 7 % 3) as u8).collect(),
+            b"abcabcabcabcabcXabcabcabc".repeat(50),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        for compression in Compression::VALUES {
+            for sample in samples() {
+                let compressed = compression.compress(&sample);
+                assert_eq!(
+                    compression.decompress(&compressed, sample.len()).unwrap(),
+                    sample,
+                    "{:?} round trip failed for {} bytes",
+                    compression,
+                    sample.len()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn compresses_repetitive_data() {
+        let data = [0xFF, 0xFF, 0x01, 0x00].repeat(256);
+        assert!(Compression::Rle.compress(&[0xFF; 1024]).len() < 32);
+        assert!(Compression::Lz.compress(&data).len() < data.len() / 4);
+    }
+
+    #[test]
+    fn rejects_corrupted_data() {
+        let data = b"abcabcabcabc".repeat(10);
+        for compression in [Compression::Rle, Compression::Lz] {
+            let compressed = compression.compress(&data);
+            assert!(compression
+                .decompress(&compressed[..compressed.len() - 1], data.len())
+                .is_err());
+        }
+        assert!(Compression::Lz.decompress(&[0x80, 0x00, 0x05], 3).is_err());
+
+        // A bogus length must not be allocated up front
+        for compression in [Compression::Rle, Compression::Lz] {
+            let compressed = compression.compress(&data);
+            assert!(compression.decompress(&compressed, usize::MAX).is_err());
+        }
+    }
+
+    #[test]
+    fn compressed_sections() {
+        let mut assets = test_project();
+        let file = export_and_read(&mut assets);
+        for (atlas_compression, map_compression) in [
+            (Compression::Rle, Compression::Lz),
+            (Compression::Lz, Compression::Rle),
+        ] {
+            assets.export_settings.atlas_compression = atlas_compression;
+            assets.export_settings.map_compression = map_compression;
+            let data = export(&mut assets);
+            assert_eq!(data[HEADER_SIZE + 4], atlas_compression as u8);
+            assert_eq!(read(&data, &component_schemas(&assets)).unwrap(), file);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::export::compression::Compression;
+use crate::export::layout::DataWriter;
+use crate::project::*;
+
+// * --------------------------------------------------------------------------------- HEADER --------------------------------------------------------------------------------- * //
+// Layout is documented in DataFormat.md, keep it in sync!
+pub const MAGIC: [u8; 4] = *b"OREM";
+pub const FORMAT_VERSION: u16 = 8;
+
+pub const HEADER_SIZE: usize = 8;
+pub const SECTION_ENTRY_SIZE: usize = 20;
+
+pub const ATLASES_SECTION: [u8; 4] = *b"ATLS";
+pub const MAPS_SECTION: [u8; 4] = *b"MAPS";
+
+pub struct Section {
+    pub tag: [u8; 4],
+    pub compression: Compression,
+    /// Length before compression
+    pub length: usize,
+    pub data: Vec<u8>,
+}
+
+impl Section {
+    pub fn new(tag: [u8; 4], data: Vec<u8>) -> Self {
+        Self {
+            tag,
+            compression: Compression::None,
+            length: data.len(),
+            data,
+        }
+    }
+
+    pub fn compressed(self, compression: Compression) -> Self {
+        Self {
+            compression,
+            data: compression.compress(&self.data),
+            ..self
+        }
+    }
+}
+
+/// The header and section table use the byte order of the sections, loaders tell it from the version.
+/// Sections start at a multiple of the alignment.
+pub fn write<W: std::io::Write>(
+    file: &mut W,
+    sections: &[Section],
+    settings: &ExportSettings,
+) -> Result<()> {
+    let mut header = DataWriter::new(settings);
+    header.bytes(&MAGIC);
+    header.u16(FORMAT_VERSION);
+    header.u16(
+        sections
+            .len()
+            .try_into()
+            .context("Too many sections in data file!")?,
+    );
+
+    let mut offset = HEADER_SIZE + SECTION_ENTRY_SIZE * sections.len();
+    let mut paddings = Vec::new();
+    for section in sections {
+        let too_big = || {
+            format!(
+                "Section {} is too big!",
+                String::from_utf8_lossy(&section.tag)
+            )
+        };
+        let padding = settings.alignment.padding(offset, 4);
+        offset += padding;
+        paddings.push(padding);
+
+        header.bytes(&section.tag);
+        header.u8(section.compression as _);
+        header.u8(settings.alignment.bytes() as _);
+        header.bytes(&[0; 2]);
+        header.u32(
+            offset
+                .try_into()
+                .context("Data file is too big, section offset doesn't fit in 32 bits!")?,
+        );
+        header.u32(section.data.len().try_into().with_context(too_big)?);
+        header.u32(section.length.try_into().with_context(too_big)?);
+        offset += section.data.len();
+    }
+
+    file.write_all(&header.data)?;
+    for (section, padding) in sections.iter().zip(paddings) {
+        file.write_all(&vec![0; padding])?;
+        file.write_all(&section.data)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use crate::project::*;
+
+// * ------------------------------------------------------------------------------- BYTE ORDER ------------------------------------------------------------------------------- * //
+// Both rules are described in DataFormat.md
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum ByteOrder {
+    #[default]
+    LittleEndian,
+    BigEndian,
+}
+
+impl ByteOrder {
+    pub const VALUES: [Self; 2] = [Self::LittleEndian, Self::BigEndian];
+}
+
+// * -------------------------------------------------------------------------------- ALIGNMENT ------------------------------------------------------------------------------- * //
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Alignment {
+    /// No padding at all
+    #[default]
+    Packed,
+    /// Values of 2 bytes and more, and records, start at even offsets
+    Align2,
+    /// Values start at a multiple of their size, records at a multiple of 4
+    Align4,
+}
+
+impl Alignment {
+    pub const VALUES: [Self; 3] = [Self::Packed, Self::Align2, Self::Align4];
+
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::Packed => 1,
+            Self::Align2 => 2,
+            Self::Align4 => 4,
+        }
+    }
+
+    pub fn from_bytes(bytes: u8) -> Option<Self> {
+        Self::VALUES
+            .into_iter()
+            .find(|alignment| alignment.bytes() == bytes as usize)
+    }
+
+    /// Alignment of a value of `size` bytes
+    pub fn of(self, size: usize) -> usize {
+        size.clamp(1, self.bytes())
+    }
+
+    /// Zero bytes before a value of `size` bytes at `offset`
+    pub fn padding(self, offset: usize, size: usize) -> usize {
+        let align = self.of(size);
+        (align - offset % align) % align
+    }
+}
+
+// * --------------------------------------------------------------------------------- WRITER --------------------------------------------------------------------------------- * //
+/// Section data in the byte order and alignment of the export settings
+pub struct DataWriter {
+    pub data: Vec<u8>,
+    pub byte_order: ByteOrder,
+    pub alignment: Alignment,
+}
+
+macro_rules! write_numbers {
+    ($($type: ident),*) => {
+        $(
+            pub fn $type(&mut self, value: $type) {
+                self.align(std::mem::size_of::<$type>());
+                match self.byte_order {
+                    ByteOrder::LittleEndian => self.data.extend(value.to_le_bytes()),
+                    ByteOrder::BigEndian => self.data.extend(value.to_be_bytes()),
+                }
+            }
+        )*
+    };
+}
+
+impl DataWriter {
+    pub fn new(settings: &ExportSettings) -> Self {
+        Self {
+            data: Vec::new(),
+            byte_order: settings.byte_order,
+            alignment: settings.alignment,
+        }
+    }
+
+    write_numbers!(u8, i8, u16, i16, u32, i32, f32);
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub fn align(&mut self, size: usize) {
+        let padding = self.alignment.padding(self.data.len(), size);
+        self.data.resize(self.data.len() + padding, 0);
+    }
+
+    /// Records start at the full alignment, so they can be read in place
+    pub fn align_record(&mut self) {
+        self.align(4);
+    }
+}
+
+// * --------------------------------------------------------------------------------- READER --------------------------------------------------------------------------------- * //
+/// Reads what `DataWriter` wrote with the same byte order and alignment
+pub struct DataReader<'a> {
+    pub data: &'a [u8],
+    pub position: usize,
+    pub byte_order: ByteOrder,
+    pub alignment: Alignment,
+}
+
+macro_rules! read_numbers {
+    ($($type: ident),*) => {
+        $(
+            pub fn $type(&mut self) -> Result<$type> {
+                self.align(std::mem::size_of::<$type>());
+                let bytes = self.bytes(std::mem::size_of::<$type>())?.try_into().unwrap();
+                Ok(match self.byte_order {
+                    ByteOrder::LittleEndian => $type::from_le_bytes(bytes),
+                    ByteOrder::BigEndian => $type::from_be_bytes(bytes),
+                })
+            }
+        )*
+    };
+}
+
+impl<'a> DataReader<'a> {
+    pub fn new(data: &'a [u8], byte_order: ByteOrder, alignment: Alignment) -> Self {
+        Self {
+            data,
+            position: 0,
+            byte_order,
+            alignment,
+        }
+    }
+
+    read_numbers!(u8, i8, u16, i16, u32, i32, f32);
+
+    pub fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + length)
+            .context("Unexpected end of data!")?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    pub fn align(&mut self, size: usize) {
+        self.position += self.alignment.padding(self.position, size);
+    }
+
+    pub fn align_record(&mut self) {
+        self.align(4);
+    }
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::header::*;
+    use crate::export::reader::*;
+    use crate::export::test_project::*;
+
+    #[test]
+    fn byte_order_and_alignment() {
+        let mut assets = test_project();
+        let file = export_and_read(&mut assets);
+        for byte_order in ByteOrder::VALUES {
+            for alignment in Alignment::VALUES {
+                assets.export_settings.byte_order = byte_order;
+                assets.export_settings.alignment = alignment;
+                let data = export(&mut assets);
+                let mut read_file = read(&data, &component_schemas(&assets)).unwrap();
+                if byte_order == ByteOrder::BigEndian {
+                    for tile in read_file.atlases[0].tiles.iter_mut() {
+                        for pixel in tile.chunks_mut(2) {
+                            pixel.swap(0, 1);
+                        }
+                    }
+                }
+                assert_eq!(read_file, file, "{:?} {:?}", byte_order, alignment);
+
+                let entry = HEADER_SIZE + SECTION_ENTRY_SIZE;
+                assert_eq!(data[entry + 5] as usize, alignment.bytes());
+                let offset = DataReader::new(&data[entry + 8..], byte_order, alignment)
+                    .u32()
+                    .unwrap() as usize;
+                assert_eq!(offset % alignment.bytes(), 0);
+                // Map count, then the first map record at the full alignment
+                let width = &data[offset + alignment.bytes().max(2)..][..2];
+                match byte_order {
+                    ByteOrder::LittleEndian => assert_eq!(width, [3, 0]),
+                    ByteOrder::BigEndian => assert_eq!(width, [0, 3]),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,662 @@
+use crate::export::codegen::{asset_name, camel, split_words};
+use crate::export::tiled::relative_path;
+use crate::project::*;
+use itertools::Itertools;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+// * ---------------------------------------------------------------------------------- LDTK ---------------------------------------------------------------------------------- * //
+pub const JSON_VERSION: &str = "1.5.3";
+/// Where "Export LDtk" writes the project, in the project folder
+pub const PROJECT_FILE: &str = "project.ldtk";
+/// Entity layers, objects that are always on top go to the second one
+pub const OBJECTS_LAYER: &str = "Objects";
+pub const ON_TOP_LAYER: &str = "ObjectsOnTop";
+/// Enums that atlas and map properties pick their values from
+pub const ATLASES_ENUM: &str = "OreMapperAtlases";
+pub const MAPS_ENUM: &str = "OreMapperMaps";
+
+/// LDtk identifier for an asset or property name, `levels/forest-1` becomes `LevelsForest1`
+pub fn identifier(name: &str) -> String {
+    let identifier = camel(&split_words(name));
+    if identifier.is_empty() || identifier.starts_with(|char: char| char.is_ascii_digit()) {
+        format!("_{}", identifier)
+    } else {
+        identifier
+    }
+}
+
+/// LDtk identifiers of assets, which have to be unique
+pub fn identifiers<T>(
+    assets: &Assets,
+    kind: &str,
+    items: &HashMap<Uuid, T>,
+    path: impl Fn(&T) -> &PathBuf,
+) -> Result<HashMap<Uuid, String>> {
+    let identifiers = items
+        .iter()
+        .map(|(uuid, item)| (*uuid, identifier(&asset_name(assets, path(item)))))
+        .collect::<HashMap<_, _>>();
+    if let Some(duplicate) = identifiers.values().duplicates().next() {
+        bail!(
+            "Several {}s are called {} in LDtk, rename one of them!",
+            kind,
+            duplicate
+        );
+    }
+    Ok(identifiers)
+}
+
+/// Writes the project as an LDtk project. Every component instance becomes an entity.
+pub fn export(assets: &Assets, path: &Path) -> Result<()> {
+    let project = project(assets, path)?;
+    std::fs::write(
+        path,
+        serde_json::to_string_pretty(&project).context("Failed to serialize LDtk project!")?,
+    )
+    .context(format!("Failed to write LDtk project {:?}!", path))
+}
+
+/// LDtk project JSON, `path` is where it will be written
+pub fn project(assets: &Assets, path: &Path) -> Result<Value> {
+    let atlas_names = identifiers(assets, "atlas", &assets.atlases, |atlas| &atlas.path)?;
+    let map_names = identifiers(assets, "map", &assets.maps, |map| &map.path)?;
+    let component_names = identifiers(assets, "component", &assets.components, |component| {
+        &component.path
+    })?;
+    let atlases = assets
+        .atlases
+        .iter()
+        .sorted_by_key(|(_, atlas)| atlas.export_id)
+        .collect::<Vec<_>>();
+    let maps = assets
+        .maps
+        .iter()
+        .sorted_by_key(|(_, map)| map.export_id)
+        .collect::<Vec<_>>();
+    let components = assets
+        .components
+        .iter()
+        .sorted_by_key(|(_, component)| component.export_id)
+        .collect::<Vec<_>>();
+    let grid_size = atlases.first().map_or(16, |(_, atlas)| atlas.tile_size.x);
+    let mut next_uid = 0;
+    let mut uid = || {
+        next_uid += 1;
+        next_uid
+    };
+
+    // * --------------------------------------------------------------------------- DEFINITIONS -------------------------------------------------------------------------- * //
+    let mut tilesets = Vec::new();
+    let mut tile_layers = Vec::new();
+    for (uuid, atlas) in &atlases {
+        if atlas.tile_size.x != atlas.tile_size.y {
+            bail!(
+                "LDtk needs square tiles, atlas {:?} has {}x{} tiles!",
+                atlas.path,
+                atlas.tile_size.x,
+                atlas.tile_size.y
+            );
+        }
+        let tileset_uid = uid();
+        tilesets.push(json!({
+            "__cWid": atlas.width(),
+            "__cHei": atlas.height(),
+            "identifier": atlas_names[uuid],
+            "uid": tileset_uid,
+            "relPath": relative_path(path, &atlas.path),
+            "embedAtlas": null,
+            "pxWid": atlas.image.width(),
+            "pxHei": atlas.image.height(),
+            "tileGridSize": atlas.tile_size.x,
+            "spacing": atlas.spacing,
+            "padding": atlas.margin,
+            "tags": [],
+            "tagsSourceEnumUid": null,
+            "enumTags": [],
+            "customData": [],
+            "savedSelections": [],
+            "cachedPixelData": null,
+        }));
+        tile_layers.push((
+            uid(),
+            format!("Tiles_{}", atlas_names[uuid]),
+            **uuid,
+            tileset_uid,
+            atlas.tile_size.x,
+        ));
+    }
+
+    let (atlases_enum, maps_enum) = (uid(), uid());
+    let enum_values = |names: Vec<&String>| {
+        names
+            .into_iter()
+            .map(|name| {
+                json!({
+                    "id": name,
+                    "tileRect": null,
+                    "tileId": null,
+                    "color": 0,
+                    "__tileSrcRect": null,
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let enums = [
+        (
+            ATLASES_ENUM,
+            atlases_enum,
+            enum_values(atlases.iter().map(|(uuid, _)| &atlas_names[uuid]).collect()),
+        ),
+        (
+            MAPS_ENUM,
+            maps_enum,
+            enum_values(maps.iter().map(|(uuid, _)| &map_names[uuid]).collect()),
+        ),
+    ]
+    .into_iter()
+    .map(|(identifier, uid, values)| {
+        json!({
+            "identifier": identifier,
+            "uid": uid,
+            "values": values,
+            "iconTilesetUid": null,
+            "externalRelPath": null,
+            "externalFileChecksum": null,
+            "tags": [],
+        })
+    })
+    .collect::<Vec<_>>();
+
+    // Object layers first, LDtk lists layers from top to bottom
+    let (on_top_uid, objects_uid) = (uid(), uid());
+    let mut layers = vec![
+        layer_definition(ON_TOP_LAYER, "Entities", on_top_uid, grid_size, None),
+        layer_definition(OBJECTS_LAYER, "Entities", objects_uid, grid_size, None),
+    ];
+    for (layer_uid, name, _, tileset_uid, tile_size) in &tile_layers {
+        layers.push(layer_definition(
+            name,
+            "Tiles",
+            *layer_uid,
+            *tile_size,
+            Some(*tileset_uid),
+        ));
+    }
+
+    let mut entities = Vec::new();
+    let mut entity_uids = HashMap::new();
+    let mut field_uids = HashMap::new();
+    for (uuid, component) in &components {
+        let entity_uid = uid();
+        entity_uids.insert(**uuid, entity_uid);
+        let mut fields = Vec::new();
+        for (name, property_type) in &component.properties {
+            let field_uid = uid();
+            field_uids.insert((**uuid, name.clone()), field_uid);
+            fields.push(field_definition(
+                name,
+                property_type,
+                field_uid,
+                atlases_enum,
+                maps_enum,
+            ));
+        }
+        if let Some(duplicate) = component
+            .properties
+            .keys()
+            .map(|name| identifier(name))
+            .duplicates()
+            .next()
+        {
+            bail!(
+                "Several properties of component {:?} are called {} in LDtk, rename one of them!",
+                component.path,
+                duplicate
+            );
+        }
+        entities.push(json!({
+            "identifier": component_names[uuid],
+            "uid": entity_uid,
+            "tags": [],
+            "exportToToc": false,
+            "allowOutOfBounds": true,
+            "doc": null,
+            "width": grid_size,
+            "height": grid_size,
+            "resizableX": false,
+            "resizableY": false,
+            "minWidth": null,
+            "maxWidth": null,
+            "minHeight": null,
+            "maxHeight": null,
+            "keepAspectRatio": false,
+            "tileOpacity": 1,
+            "fillOpacity": 0.08,
+            "lineOpacity": 0,
+            "hollow": false,
+            "color": "#BE4A2F",
+            "renderMode": "Rectangle",
+            "showName": true,
+            "tilesetId": null,
+            "tileRenderMode": "FitInside",
+            "tileRect": null,
+            "uiTileRect": null,
+            "nineSliceBorders": [],
+            "maxCount": 0,
+            "limitScope": "PerLevel",
+            "limitBehavior": "MoveLastOne",
+            "pivotX": 0,
+            "pivotY": 0,
+            "fieldDefs": fields,
+        }));
+    }
+
+    // * ------------------------------------------------------------------------------ LEVELS ------------------------------------------------------------------------------ * //
+    let mut levels = Vec::new();
+    let mut world_x = 0;
+    for (map_uuid, map) in &maps {
+        let atlas = assets
+            .atlases
+            .get(&map.atlas)
+            .context(format!("Map {:?} uses a missing atlas!", map.path))?;
+        let tile_size = atlas.tile_size.x as i32;
+        let px = TVec2::new(map.size.x as i32, map.size.y as i32) * tile_size;
+        let level_uid = uid();
+        let layer_iid =
+            |layer_uid: i32| Uuid::from_u128(map_uuid.as_u128() ^ layer_uid as u128).to_string();
+
+        let mut layer_instances = Vec::new();
+        for (always_on_top, layer_uid, name) in [
+            (true, on_top_uid, ON_TOP_LAYER),
+            (false, objects_uid, OBJECTS_LAYER),
+        ] {
+            let mut instances = Vec::new();
+            for (object_uuid, object) in map
+                .objects
+                .iter()
+                .filter(|(_, object)| object.always_on_top == always_on_top)
+                .sorted_by_key(|(uuid, object)| (object.pos.y, object.pos.x, **uuid))
+            {
+                for (component_uuid, values) in object
+                    .components
+                    .iter()
+                    .sorted_by_key(|(uuid, _)| entity_uids.get(uuid))
+                {
+                    let component = assets
+                        .components
+                        .get(component_uuid)
+                        .context("Object uses a component that is not in the project!")?;
+                    let mut fields = Vec::new();
+                    for (name, property_type) in &component.properties {
+                        let (value, editor_value) = field_value(
+                            property_type,
+                            values.get(name).map_or("", String::as_str),
+                            &atlas_names,
+                            &map_names,
+                        );
+                        fields.push(json!({
+                            "__identifier": identifier(name),
+                            "__type": field_types(property_type).1,
+                            "__value": value,
+                            "__tile": null,
+                            "defUid": field_uids[&(*component_uuid, name.clone())],
+                            "realEditorValues": [editor_value],
+                        }));
+                    }
+                    instances.push(json!({
+                        "__identifier": component_names[component_uuid],
+                        "__grid": [
+                            object.pos.x.div_euclid(grid_size as i32),
+                            object.pos.y.div_euclid(grid_size as i32),
+                        ],
+                        "__pivot": [0, 0],
+                        "__tags": [],
+                        "__tile": null,
+                        "__smartColor": "#BE4A2F",
+                        "iid": Uuid::from_u128(object_uuid.as_u128() ^ component_uuid.as_u128()).to_string(),
+                        "width": grid_size,
+                        "height": grid_size,
+                        "defUid": entity_uids[component_uuid],
+                        "px": [object.pos.x, object.pos.y],
+                        "fieldInstances": fields,
+                        "__worldX": world_x + object.pos.x,
+                        "__worldY": object.pos.y,
+                    }));
+                }
+            }
+            layer_instances.push(layer_instance(
+                name,
+                "Entities",
+                (layer_uid, layer_iid(layer_uid), level_uid),
+                grid_size as i32,
+                px,
+                None,
+                Vec::new(),
+                instances,
+            ));
+        }
+
+        for (layer_uid, name, atlas_uuid, tileset_uid, layer_tile_size) in &tile_layers {
+            let mut tiles = Vec::new();
+            if *atlas_uuid == map.atlas {
+                for y in 0..map.size.y {
+                    for x in 0..map.size.x {
+                        let tile = map.get_tile(TVec2::new(x, y).casted());
+                        if tile < TVec2::zeros() {
+                            continue;
+                        }
+                        let src = atlas.tile_pos(tile.casted());
+                        tiles.push(json!({
+                            "px": [x as i32 * tile_size, y as i32 * tile_size],
+                            "src": [src.x, src.y],
+                            "f": 0,
+                            "t": tile.x as i32 + tile.y as i32 * atlas.width() as i32,
+                            "d": [x as i32 + y as i32 * map.size.x as i32],
+                            "a": 1,
+                        }));
+                    }
+                }
+            }
+            layer_instances.push(layer_instance(
+                name,
+                "Tiles",
+                (*layer_uid, layer_iid(*layer_uid), level_uid),
+                *layer_tile_size as i32,
+                px,
+                Some((
+                    *tileset_uid,
+                    relative_path(path, &assets.atlases[atlas_uuid].path),
+                )),
+                tiles,
+                Vec::new(),
+            ));
+        }
+
+        levels.push(json!({
+            "identifier": map_names[map_uuid],
+            "iid": map_uuid.to_string(),
+            "uid": level_uid,
+            "worldX": world_x,
+            "worldY": 0,
+            "worldDepth": 0,
+            "pxWid": px.x,
+            "pxHei": px.y,
+            "__bgColor": "#696A79",
+            "bgColor": null,
+            "useAutoIdentifier": false,
+            "bgRelPath": null,
+            "bgPos": null,
+            "bgPivotX": 0.5,
+            "bgPivotY": 0.5,
+            "__smartColor": "#ADADB5",
+            "__bgPos": null,
+            "externalRelPath": null,
+            "fieldInstances": [],
+            "layerInstances": layer_instances,
+            "__neighbours": [],
+        }));
+        world_x += px.x + grid_size as i32 * 2;
+    }
+
+    let project_iid = maps.iter().fold(0, |iid, (uuid, _)| iid ^ uuid.as_u128());
+    let mut project = json!({
+        "__header__": {
+            "fileType": "LDtk Project JSON",
+            "app": "LDtk",
+            "doc": "https://ldtk.io/json",
+            "schema": "https://ldtk.io/files/JSON_SCHEMA.json",
+            "appAuthor": "Sebastien 'deepnight' Benard",
+            "appVersion": JSON_VERSION,
+            "url": "https://ldtk.io",
+        },
+        "iid": Uuid::from_u128(project_iid ^ 1).to_string(),
+        "jsonVersion": JSON_VERSION,
+        "appBuildId": 0,
+        "nextUid": next_uid + 1,
+        "identifierStyle": "Capitalize",
+        "toc": [],
+        "worldLayout": "Free",
+        "worldGridWidth": 256,
+        "worldGridHeight": 256,
+        "defaultLevelWidth": 256,
+        "defaultLevelHeight": 256,
+        "defaultPivotX": 0,
+        "defaultPivotY": 0,
+        "defaultGridSize": grid_size,
+        "defaultEntityWidth": grid_size,
+        "defaultEntityHeight": grid_size,
+        "bgColor": "#40465B",
+        "defaultLevelBgColor": "#696A79",
+    });
+    let settings = json!({
+        "minifyJson": false,
+        "externalLevels": false,
+        "exportTiled": false,
+        "simplifiedExport": false,
+        "imageExportMode": "None",
+        "exportLevelBg": true,
+        "pngFilePattern": null,
+        "backupOnSave": false,
+        "backupLimit": 10,
+        "backupRelPath": null,
+        "levelNamePattern": "Level_%idx",
+        "tutorialDesc": null,
+        "customCommands": [],
+        "flags": [],
+        "defs": {
+            "layers": layers,
+            "entities": entities,
+            "tilesets": tilesets,
+            "enums": enums,
+            "externalEnums": [],
+            "levelFields": [],
+        },
+        "levels": levels,
+        "worlds": [],
+        "dummyWorldIid": Uuid::from_u128(project_iid ^ 2).to_string(),
+    });
+    project
+        .as_object_mut()
+        .unwrap()
+        .extend(settings.as_object().unwrap().clone());
+    Ok(project)
+}
+
+fn layer_definition(
+    identifier: &str,
+    layer_type: &str,
+    uid: i32,
+    grid_size: u16,
+    tileset_uid: Option<i32>,
+) -> Value {
+    json!({
+        "__type": layer_type,
+        "identifier": identifier,
+        "type": layer_type,
+        "uid": uid,
+        "doc": null,
+        "uiColor": null,
+        "gridSize": grid_size,
+        "guideGridWid": 0,
+        "guideGridHei": 0,
+        "displayOpacity": 1,
+        "inactiveOpacity": 0.6,
+        "hideInList": false,
+        "hideFieldsWhenInactive": true,
+        "canSelectWhenInactive": true,
+        "renderInWorldView": true,
+        "pxOffsetX": 0,
+        "pxOffsetY": 0,
+        "parallaxFactorX": 0,
+        "parallaxFactorY": 0,
+        "parallaxScaling": true,
+        "requiredTags": [],
+        "excludedTags": [],
+        "autoTilesKilledByOtherLayerUid": null,
+        "uiFilterTags": [],
+        "useAsyncRender": false,
+        "intGridValues": [],
+        "intGridValuesGroups": [],
+        "autoRuleGroups": [],
+        "autoSourceLayerDefUid": null,
+        "tilesetDefUid": tileset_uid,
+        "tilePivotX": 0,
+        "tilePivotY": 0,
+        "biomeFieldUid": null,
+    })
+}
+
+/// `ids` are the layer definition UID, instance IID and level UID
+#[allow(clippy::too_many_arguments)]
+fn layer_instance(
+    identifier: &str,
+    layer_type: &str,
+    ids: (i32, String, i32),
+    grid_size: i32,
+    px: I32Vec2,
+    tileset: Option<(i32, String)>,
+    tiles: Vec<Value>,
+    entities: Vec<Value>,
+) -> Value {
+    let (tileset_uid, tileset_path) = tileset.unzip();
+    json!({
+        "__identifier": identifier,
+        "__type": layer_type,
+        "__cWid": (px.x + grid_size - 1) / grid_size,
+        "__cHei": (px.y + grid_size - 1) / grid_size,
+        "__gridSize": grid_size,
+        "__opacity": 1,
+        "__pxTotalOffsetX": 0,
+        "__pxTotalOffsetY": 0,
+        "__tilesetDefUid": tileset_uid,
+        "__tilesetRelPath": tileset_path,
+        "iid": ids.1,
+        "levelId": ids.2,
+        "layerDefUid": ids.0,
+        "pxOffsetX": 0,
+        "pxOffsetY": 0,
+        "visible": true,
+        "optionalRules": [],
+        "intGridCsv": [],
+        "autoLayerTiles": [],
+        "seed": 0,
+        "overrideTilesetUid": null,
+        "gridTiles": tiles,
+        "entityInstances": entities,
+    })
+}
+
+// * --------------------------------------------------------------------------------- VALUES --------------------------------------------------------------------------------- * //
+/// LDtk field type and its display name, numbers get the range of the property type
+fn field_types(property_type: &Property) -> (&'static str, String) {
+    match property_type {
+        Property::F32 => ("F_Float", "Float".to_owned()),
+        Property::String => ("F_String", "String".to_owned()),
+        Property::Atlas => ("F_Enum", format!("LocalEnum.{}", ATLASES_ENUM)),
+        Property::Map => ("F_Enum", format!("LocalEnum.{}", MAPS_ENUM)),
+        _ => ("F_Int", "Int".to_owned()),
+    }
+}
+
+pub fn integer_range(property_type: &Property) -> Option<(i64, i64)> {
+    match property_type {
+        Property::I8 => Some((i8::MIN as _, i8::MAX as _)),
+        Property::U8 => Some((u8::MIN as _, u8::MAX as _)),
+        Property::I16 => Some((i16::MIN as _, i16::MAX as _)),
+        Property::U16 => Some((u16::MIN as _, u16::MAX as _)),
+        Property::I32 => Some((i32::MIN as _, i32::MAX as _)),
+        Property::U32 => Some((u32::MIN as _, u32::MAX as _)),
+        _ => None,
+    }
+}
+
+fn field_definition(
+    name: &str,
+    property_type: &Property,
+    uid: i32,
+    atlases_enum: i32,
+    maps_enum: i32,
+) -> Value {
+    let (field_type, display_type) = field_types(property_type);
+    let field_type = match property_type {
+        Property::Atlas => format!("{}({})", field_type, atlases_enum),
+        Property::Map => format!("{}({})", field_type, maps_enum),
+        _ => field_type.to_owned(),
+    };
+    let (min, max) = integer_range(property_type).unzip();
+    let mut definition = json!({
+        "identifier": identifier(name),
+        "doc": null,
+        "__type": display_type,
+        "uid": uid,
+        "type": field_type,
+        "isArray": false,
+        "canBeNull": false,
+        "arrayMinLength": null,
+        "arrayMaxLength": null,
+        "editorDisplayMode": "Hidden",
+        "editorDisplayScale": 1,
+        "editorDisplayPos": "Above",
+        "editorLinkStyle": "StraightArrow",
+        "editorDisplayColor": null,
+        "editorAlwaysShow": false,
+        "editorShowInWorld": true,
+        "editorCutLongValues": true,
+        "editorTextSuffix": null,
+        "editorTextPrefix": null,
+        "useForSmartColor": false,
+        "exportToToc": false,
+        "searchable": false,
+        "min": min,
+        "max": max,
+    });
+    let rest = json!({
+        "regex": null,
+        "acceptFileTypes": null,
+        "defaultOverride": null,
+        "textLanguageMode": null,
+        "symmetricalRef": false,
+        "autoChainRef": true,
+        "allowOutOfLevelRef": true,
+        "allowedRefs": "OnlySame",
+        "allowedRefsEntityUid": null,
+        "allowedRefTags": [],
+        "tilesetUid": null,
+    });
+    definition
+        .as_object_mut()
+        .unwrap()
+        .extend(rest.as_object().unwrap().clone());
+    definition
+}
+
+/// Field value and the editor value that LDtk loads it from
+fn field_value(
+    property_type: &Property,
+    value: &str,
+    atlas_names: &HashMap<Uuid, String>,
+    map_names: &HashMap<Uuid, String>,
+) -> (Value, Value) {
+    let reference = |names: &HashMap<Uuid, String>| {
+        Uuid::parse_str(value)
+            .ok()
+            .and_then(|uuid| names.get(&uuid))
+            .map_or((Value::Null, Value::Null), |name| {
+                (json!(name), json!({ "id": "V_String", "params": [name] }))
+            })
+    };
+    match property_type {
+        Property::F32 => {
+            let value = value.parse::<f64>().unwrap_or(0.0);
+            (json!(value), json!({ "id": "V_Float", "params": [value] }))
+        }
+        Property::String => (json!(value), json!({ "id": "V_String", "params": [value] })),
+        Property::Atlas => reference(atlas_names),
+        Property::Map => reference(map_names),
+        _ => {
+            let value = value.parse::<i64>().unwrap_or(0);
+            (json!(value), json!({ "id": "V_Int", "params": [value] }))
+        }
+    }
+}
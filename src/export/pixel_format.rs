@@ -0,0 +1,337 @@
+use crate::export::layout::DataWriter;
+use crate::project::*;
+
+// * ------------------------------------------------------------------------------ PIXEL FORMAT ------------------------------------------------------------------------------ * //
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[repr(u8)]
+pub enum PixelFormat {
+    #[default]
+    Rgb565 = 0,
+    Rgb888 = 1,
+    Rgba4444 = 2,
+    Rgba5551 = 3,
+    /// GameBoy tiles: 2 bits per pixel, each row of 8 pixels is a low bitplane byte followed by a high bitplane byte
+    Planar2bpp = 4,
+    /// Index into the atlas' quantized palette of up to 16 colors, 2 pixels per byte, left one in the high nibble
+    Indexed4 = 5,
+    /// Index into the atlas' quantized palette of up to 256 colors
+    Indexed8 = 6,
+}
+
+impl PixelFormat {
+    pub const VALUES: [Self; 7] = [
+        Self::Rgb565,
+        Self::Rgb888,
+        Self::Rgba4444,
+        Self::Rgba5551,
+        Self::Planar2bpp,
+        Self::Indexed4,
+        Self::Indexed8,
+    ];
+
+    /// Indexed formats keep alpha in their palette
+    pub fn has_alpha(self) -> bool {
+        matches!(
+            self,
+            Self::Rgba4444 | Self::Rgba5551 | Self::Indexed4 | Self::Indexed8
+        )
+    }
+
+    /// Whether the atlas record carries a quantized palette
+    pub fn is_indexed(self) -> bool {
+        matches!(self, Self::Indexed4 | Self::Indexed8)
+    }
+
+    /// Number of colors for formats that index a palette
+    pub fn colors(self) -> Option<usize> {
+        match self {
+            Self::Planar2bpp => Some(4),
+            Self::Indexed4 => Some(16),
+            Self::Indexed8 => Some(256),
+            _ => None,
+        }
+    }
+
+    pub fn bits_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb565 | Self::Rgba4444 | Self::Rgba5551 => 16,
+            Self::Rgb888 => 24,
+            Self::Planar2bpp => 2,
+            Self::Indexed4 => 4,
+            Self::Indexed8 => 8,
+        }
+    }
+
+    pub fn max_value(self) -> u32 {
+        match self {
+            Self::Planar2bpp => 3,
+            _ => ((1u64 << self.bits_per_pixel()) - 1) as _,
+        }
+    }
+
+    pub fn tile_bytes(self, tile_size: U16Vec2) -> usize {
+        tile_size.x as usize * tile_size.y as usize * self.bits_per_pixel() / 8
+    }
+
+    /// Encode color, for palette formats it's an index of the closest palette color.
+    /// The palette is the 2bpp shades for Planar2bpp and the atlas' quantized palette for indexed formats.
+    pub fn encode(self, pixel: image::Rgba<u8>, palette: &[image::Rgba<u8>]) -> u32 {
+        let [r, g, b, a] = pixel.0.map(|channel| channel as u32);
+        match self {
+            Self::Rgb565 => (r >> 3) << 11 | (g >> 2) << 5 | b >> 3,
+            Self::Rgb888 => r << 16 | g << 8 | b,
+            Self::Rgba4444 => (r >> 4) << 12 | (g >> 4) << 8 | (b >> 4) << 4 | a >> 4,
+            Self::Rgba5551 => (r >> 3) << 11 | (g >> 3) << 6 | (b >> 3) << 1 | a >> 7,
+            Self::Planar2bpp | Self::Indexed4 | Self::Indexed8 => {
+                closest(palette, pixel).unwrap_or_default() as _
+            }
+        }
+    }
+
+    /// The color a loader gets back from `encode`, so previews show what the hardware shows
+    pub fn decode(self, value: u32, palette: &[image::Rgba<u8>]) -> image::Rgba<u8> {
+        let expand = |value: u32, bits: u32| {
+            let value = value & ((1 << bits) - 1);
+            (value << (8 - bits) | value >> (2 * bits).saturating_sub(8)) as u8
+        };
+        match self {
+            Self::Rgb565 => image::Rgba([
+                expand(value >> 11, 5),
+                expand(value >> 5, 6),
+                expand(value, 5),
+                255,
+            ]),
+            Self::Rgb888 => {
+                image::Rgba([(value >> 16) as u8, (value >> 8) as u8, value as u8, 255])
+            }
+            Self::Rgba4444 => image::Rgba([
+                expand(value >> 12, 4),
+                expand(value >> 8, 4),
+                expand(value >> 4, 4),
+                expand(value, 4),
+            ]),
+            Self::Rgba5551 => image::Rgba([
+                expand(value >> 11, 5),
+                expand(value >> 6, 5),
+                expand(value >> 1, 5),
+                if value & 1 != 0 { 255 } else { 0 },
+            ]),
+            Self::Planar2bpp | Self::Indexed4 | Self::Indexed8 => palette
+                .get(value as usize)
+                .copied()
+                .unwrap_or(image::Rgba([0, 0, 0, 0])),
+        }
+    }
+
+    pub fn write_tile(
+        self,
+        file: &mut DataWriter,
+        image: &image::RgbaImage,
+        pos: U32Vec2,
+        tile_size: U16Vec2,
+        transparency: Transparency,
+        palette: &[image::Rgba<u8>],
+    ) {
+        let pixel = |x: u32, y: u32| {
+            let mut pixel = *image.get_pixel(pos.x + x, pos.y + y);
+            match transparency {
+                Transparency::ColorKey(key) if pixel[3] < 128 => key,
+                Transparency::Alpha => self.encode(pixel, palette),
+                _ => {
+                    pixel[3] = 255;
+                    self.encode(pixel, palette)
+                }
+            }
+        };
+
+        match self {
+            Self::Rgb565 | Self::Rgba4444 | Self::Rgba5551 => {
+                for y in 0..tile_size.y as u32 {
+                    for x in 0..tile_size.x as u32 {
+                        file.u16(pixel(x, y) as _);
+                    }
+                }
+            }
+            Self::Rgb888 => {
+                for y in 0..tile_size.y as u32 {
+                    for x in 0..tile_size.x as u32 {
+                        file.bytes(&pixel(x, y).to_be_bytes()[1..]);
+                    }
+                }
+            }
+            Self::Indexed4 => {
+                for y in 0..tile_size.y as u32 {
+                    for x in (0..tile_size.x as u32).step_by(2) {
+                        file.u8((pixel(x, y) << 4 | pixel(x + 1, y)) as _);
+                    }
+                }
+            }
+            Self::Indexed8 => {
+                for y in 0..tile_size.y as u32 {
+                    for x in 0..tile_size.x as u32 {
+                        file.u8(pixel(x, y) as _);
+                    }
+                }
+            }
+            Self::Planar2bpp => {
+                for y in 0..tile_size.y as u32 {
+                    for chunk in 0..tile_size.x as u32 / 8 {
+                        let (mut low, mut high) = (0u8, 0u8);
+                        for x in 0..8 {
+                            let index = pixel(chunk * 8 + x, y);
+                            low |= ((index & 1) as u8) << (7 - x);
+                            high |= ((index >> 1 & 1) as u8) << (7 - x);
+                        }
+                        file.u8(low);
+                        file.u8(high);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn check(self, tile_size: U16Vec2, transparency: Transparency) -> Result<()> {
+        if self == Self::Planar2bpp && !tile_size.x.is_multiple_of(8) {
+            bail!(
+                "Tile width must be a multiple of 8 for {:?}, got {}!",
+                self,
+                tile_size.x
+            );
+        }
+        if self == Self::Indexed4 && !tile_size.x.is_multiple_of(2) {
+            bail!(
+                "Tile width must be even for {:?}, got {}!",
+                self,
+                tile_size.x
+            );
+        }
+        match transparency {
+            Transparency::Alpha if !self.has_alpha() => {
+                bail!("Pixel format {:?} has no alpha channel!", self)
+            }
+            Transparency::ColorKey(key) if key > self.max_value() => {
+                bail!(
+                    "Color key {:#X} doesn't fit in pixel format {:?}!",
+                    key,
+                    self
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Index of the palette color closest to the pixel, alpha included
+pub fn closest(palette: &[image::Rgba<u8>], pixel: image::Rgba<u8>) -> Option<usize> {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, color)| {
+            color
+                .0
+                .iter()
+                .zip(pixel.0)
+                .map(|(&color, channel)| (color as i32 - channel as i32).pow(2))
+                .sum::<i32>()
+        })
+        .map(|(index, _)| index)
+}
+
+// * ------------------------------------------------------------------------------ TRANSPARENCY ------------------------------------------------------------------------------ * //
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Transparency {
+    /// Alpha is ignored, every pixel is opaque
+    None,
+    /// Transparent pixels are written as this value (palette index for Planar2bpp)
+    ColorKey(u32),
+    /// Alpha is kept in the alpha channel
+    Alpha,
+}
+
+impl Default for Transparency {
+    fn default() -> Self {
+        Self::ColorKey(0xF81F)
+    }
+}
+
+impl Transparency {
+    pub fn id(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::ColorKey(_) => 1,
+            Self::Alpha => 2,
+        }
+    }
+
+    pub fn key(self) -> u32 {
+        if let Self::ColorKey(key) = self {
+            key
+        } else {
+            0
+        }
+    }
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::test_project::*;
+
+    #[test]
+    fn atlas_pixel_formats() {
+        let mut assets = test_project();
+        let atlas = assets.atlases.values_mut().next().unwrap();
+        atlas.image = image::RgbaImage::from_fn(8, 1, |x, _| match x {
+            0..=1 => image::Rgba([255, 255, 255, 255]),
+            2..=3 => image::Rgba([160, 160, 160, 255]),
+            4..=5 => image::Rgba([90, 90, 90, 255]),
+            6 => image::Rgba([0, 0, 0, 255]),
+            _ => image::Rgba([0, 0, 0, 0]),
+        });
+        atlas.tile_size = U16Vec2::new(8, 1);
+        // The maps' tiles would be outside of the single tile atlas
+        for map in assets.maps.values_mut() {
+            map.data.fill(TVec2::new(-1, -1));
+        }
+
+        let atlas = assets.atlases.values_mut().next().unwrap();
+        atlas.pixel_format = Some(PixelFormat::Planar2bpp);
+        atlas.transparency = Some(Transparency::ColorKey(0));
+        let file = export_and_read(&mut assets);
+        // Shades: 0 0 1 1 2 2 3 0
+        assert_eq!(file.atlases[0].pixel_format, PixelFormat::Planar2bpp);
+        assert_eq!(file.atlases[0].tiles, vec![vec![0b00110010, 0b00001110]]);
+
+        let atlas = assets.atlases.values_mut().next().unwrap();
+        atlas.pixel_format = Some(PixelFormat::Rgba5551);
+        atlas.transparency = Some(Transparency::Alpha);
+        let file = export_and_read(&mut assets);
+        assert_eq!(file.atlases[0].transparency, Transparency::Alpha);
+        assert_eq!(&file.atlases[0].tiles[0][..2], &[0xFF, 0xFF]);
+        assert_eq!(&file.atlases[0].tiles[0][14..], &[0x00, 0x00]);
+
+        // Palette sorted by color after the key: black, 90, 160, white
+        let atlas = assets.atlases.values_mut().next().unwrap();
+        atlas.pixel_format = Some(PixelFormat::Indexed4);
+        atlas.transparency = Some(Transparency::ColorKey(0));
+        let file = export_and_read(&mut assets);
+        assert_eq!(
+            file.atlases[0].palette,
+            vec![
+                [0, 0, 0, 0],
+                [0, 0, 0, 255],
+                [90, 90, 90, 255],
+                [160, 160, 160, 255],
+                [255, 255, 255, 255]
+            ]
+        );
+        assert_eq!(file.atlases[0].tiles, vec![vec![0x44, 0x33, 0x22, 0x10]]);
+
+        let atlas = assets.atlases.values_mut().next().unwrap();
+        atlas.pixel_format = Some(PixelFormat::Rgb888);
+        atlas.transparency = Some(Transparency::Alpha);
+        let mut data = Vec::new();
+        assert!(assets.export_data(&mut data).is_err());
+    }
+}
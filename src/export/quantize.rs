@@ -0,0 +1,295 @@
+use crate::export::pixel_format::*;
+use crate::project::*;
+use std::collections::{BTreeMap, HashMap};
+
+// * -------------------------------------------------------------------------------- QUANTIZE -------------------------------------------------------------------------------- * //
+/// An atlas image as it is exported: in the colors of its pixel format, reduced to its color limit
+#[derive(Clone)]
+pub struct Quantized {
+    /// The colors left after reduction. For indexed formats with a color key, the key index is transparent.
+    pub palette: Vec<image::Rgba<u8>>,
+    /// Every tile pixel replaced by its exported color, transparent pixels of a color key are fully transparent.
+    /// Margin and spacing are left as they are.
+    pub image: image::RgbaImage,
+    /// Distinct opaque colors in the pixel format before reduction
+    pub colors: usize,
+    /// Opaque colors the reduction may keep, the color key takes one of the limit
+    pub limit: Option<usize>,
+    /// For every tile, in the order they were given, whether the reduction changed any of its pixels
+    pub changed_tiles: Vec<bool>,
+}
+
+/// Color limit of an atlas in the given pixel format, indexed formats are limited by their palette size
+pub fn color_limit(atlas: &Atlas, pixel_format: PixelFormat) -> Option<usize> {
+    let limit = atlas.color_limit.map(|limit| limit as usize);
+    if pixel_format.is_indexed() {
+        let colors = pixel_format.colors().unwrap_or_default();
+        Some(limit.map_or(colors, |limit| limit.min(colors)))
+    } else {
+        limit
+    }
+}
+
+/// Quantizes the atlas with its own pixel format and transparency, or the project's
+pub fn quantize_atlas(atlas: &Atlas, settings: &ExportSettings) -> Quantized {
+    let pixel_format = atlas.pixel_format.unwrap_or(settings.pixel_format);
+    let tiles = (0..atlas.height() as u32)
+        .flat_map(|y| (0..atlas.width() as u32).map(move |x| U32Vec2::new(x, y)))
+        .map(|tile| atlas.tile_pos(tile))
+        .collect::<Vec<_>>();
+    quantize(
+        &atlas.image,
+        atlas.tile_size,
+        &tiles,
+        pixel_format,
+        atlas.transparency.unwrap_or(settings.transparency),
+        color_limit(atlas, pixel_format),
+        &settings.shades(),
+    )
+}
+
+/// Color of the pixel in the pixel format, before any reduction, `None` if it is transparent by color key
+fn exported_color(
+    pixel: image::Rgba<u8>,
+    pixel_format: PixelFormat,
+    transparency: Transparency,
+    shades: &[image::Rgba<u8>],
+) -> Option<image::Rgba<u8>> {
+    let mut pixel = pixel;
+    match transparency {
+        Transparency::ColorKey(_) if pixel[3] < 128 => return None,
+        Transparency::Alpha => (),
+        _ => pixel[3] = 255,
+    }
+    if pixel_format.is_indexed() {
+        Some(pixel)
+    } else {
+        Some(pixel_format.decode(pixel_format.encode(pixel, shades), shades))
+    }
+}
+
+/// Reduces the colors of the tiles at the given pixel positions, pixels outside of them don't count
+pub fn quantize(
+    image: &image::RgbaImage,
+    tile_size: U16Vec2,
+    tiles: &[U32Vec2],
+    pixel_format: PixelFormat,
+    transparency: Transparency,
+    limit: Option<usize>,
+    shades: &[image::Rgba<u8>],
+) -> Quantized {
+    let color_key = matches!(transparency, Transparency::ColorKey(_));
+    let limit = limit.map(|limit| limit.saturating_sub(color_key as usize).max(1));
+    let tile_pixels = |tile: U32Vec2| {
+        (0..tile_size.y as u32).flat_map(move |y| {
+            (0..tile_size.x as u32)
+                .map(move |x| (tile.x + x + (tile.y + y) * image.width()) as usize)
+        })
+    };
+    let mut in_tile = vec![false; image.len() / 4];
+    for index in tiles.iter().flat_map(|tile| tile_pixels(*tile)) {
+        in_tile[index] = true;
+    }
+    let exported = image
+        .pixels()
+        .zip(&in_tile)
+        .map(|(pixel, in_tile)| {
+            in_tile.then(|| exported_color(*pixel, pixel_format, transparency, shades))
+        })
+        .collect::<Vec<_>>();
+
+    // Sorted, so the same image always gives the same palette
+    let mut histogram = BTreeMap::new();
+    for color in exported.iter().flatten().flatten() {
+        *histogram.entry(color.0).or_insert(0u64) += 1;
+    }
+    let colors = histogram.len();
+    let mut palette = match limit {
+        Some(limit) if colors > limit => median_cut(histogram.into_iter().collect(), limit)
+            .into_iter()
+            .filter_map(|color| exported_color(color, pixel_format, transparency, shades))
+            .collect::<Vec<_>>(),
+        _ => histogram.into_keys().map(image::Rgba).collect::<Vec<_>>(),
+    };
+    palette.sort_by_key(|color| color.0);
+    palette.dedup();
+
+    let mut closest_colors = HashMap::new();
+    let mut changed = vec![false; exported.len()];
+    let mut quantized = image.clone();
+    for (index, (color, pixel)) in exported.iter().zip(quantized.pixels_mut()).enumerate() {
+        let Some(color) = color else {
+            continue;
+        };
+        let Some(color) = color else {
+            *pixel = image::Rgba([0, 0, 0, 0]);
+            continue;
+        };
+        *pixel = *closest_colors
+            .entry(color.0)
+            .or_insert_with(|| palette[closest(&palette, *color).unwrap_or_default()]);
+        changed[index] = pixel != color;
+    }
+
+    let changed_tiles = tiles
+        .iter()
+        .map(|tile| tile_pixels(*tile).any(|index| changed[index]))
+        .collect();
+
+    if let (true, Transparency::ColorKey(key)) = (pixel_format.is_indexed(), transparency) {
+        let key = key as usize;
+        if palette.len() < key {
+            palette.resize(key, image::Rgba([0, 0, 0, 0]));
+        }
+        palette.insert(key, image::Rgba([0, 0, 0, 0]));
+    }
+
+    Quantized {
+        palette,
+        image: quantized,
+        colors,
+        limit,
+        changed_tiles,
+    }
+}
+
+/// Splits the box with the widest channel at the weighted mean of that channel until there are `count` boxes,
+/// returns the weighted average color of every box
+fn median_cut(colors: Vec<([u8; 4], u64)>, count: usize) -> Vec<image::Rgba<u8>> {
+    let range = |colors: &[([u8; 4], u64)], channel: usize| {
+        let values = colors.iter().map(|(color, _)| color[channel]);
+        values.clone().max().unwrap_or_default() - values.min().unwrap_or_default()
+    };
+
+    let mut boxes = vec![colors];
+    while boxes.len() < count {
+        let Some((index, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| {
+                let channel = (0..4)
+                    .max_by_key(|channel| range(colors, *channel))
+                    .unwrap();
+                (index, channel, range(colors, channel))
+            })
+            .max_by_key(|(index, _, range)| (*range, std::cmp::Reverse(*index)))
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_by_key(|(color, _)| (color[channel], *color));
+        let total = colors.iter().map(|(_, weight)| weight).sum::<u64>();
+        let mean = colors
+            .iter()
+            .map(|(color, weight)| color[channel] as u64 * weight)
+            .sum::<u64>()
+            / total.max(1);
+        let split = colors
+            .iter()
+            .filter(|(color, _)| color[channel] as u64 <= mean)
+            .count()
+            .clamp(1, colors.len() - 1);
+        let rest = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(rest);
+    }
+
+    boxes
+        .iter()
+        .map(|colors| {
+            let total = colors.iter().map(|(_, weight)| weight).sum::<u64>().max(1);
+            image::Rgba(std::array::from_fn(|channel| {
+                (colors
+                    .iter()
+                    .map(|(color, weight)| color[channel] as u64 * weight)
+                    .sum::<u64>()
+                    / total) as u8
+            }))
+        })
+        .collect()
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduces_colors() {
+        // 4 tiles of 2x1: two reds, black and white, a blue gradient, transparent
+        let image = image::RgbaImage::from_vec(
+            8,
+            1,
+            [
+                [255, 0, 0, 255],
+                [250, 0, 0, 255],
+                [0, 0, 0, 255],
+                [255, 255, 255, 255],
+                [0, 0, 200, 255],
+                [0, 0, 255, 255],
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let shades = ExportSettings::default().shades();
+        let tiles = (0..4).map(|x| U32Vec2::new(x * 2, 0)).collect::<Vec<_>>();
+
+        // Rgb888 keeps every color
+        let quantized = quantize(
+            &image,
+            U16Vec2::new(2, 1),
+            &tiles,
+            PixelFormat::Rgb888,
+            Transparency::ColorKey(0),
+            None,
+            &shades,
+        );
+        assert_eq!(quantized.colors, 6);
+        assert_eq!(quantized.palette.len(), 6);
+        assert_eq!(quantized.changed_tiles, vec![false; 4]);
+        assert_eq!(quantized.image.get_pixel(6, 0)[3], 0);
+
+        // Rgb565 truncates the reds to the same color, without transparency the transparent pixels are black
+        let quantized = quantize(
+            &image,
+            U16Vec2::new(2, 1),
+            &tiles,
+            PixelFormat::Rgb565,
+            Transparency::None,
+            None,
+            &shades,
+        );
+        assert_eq!(quantized.colors, 5);
+        assert_eq!(
+            *quantized.image.get_pixel(4, 0),
+            image::Rgba([0, 0, 206, 255])
+        );
+
+        // 4 colors including the key leave 3: black, the reds, and the rest
+        let quantized = quantize(
+            &image,
+            U16Vec2::new(2, 1),
+            &tiles,
+            PixelFormat::Indexed4,
+            Transparency::ColorKey(0),
+            Some(4),
+            &shades,
+        );
+        assert_eq!(quantized.limit, Some(3));
+        assert_eq!(quantized.colors, 6);
+        assert_eq!(quantized.palette.len(), 4);
+        assert_eq!(quantized.palette[0], image::Rgba([0, 0, 0, 0]));
+        assert_eq!(quantized.changed_tiles, vec![true, true, true, false]);
+        assert_eq!(
+            quantized.image.get_pixel(0, 0),
+            quantized.image.get_pixel(1, 0)
+        );
+        for pixel in quantized.image.pixels().filter(|pixel| pixel[3] > 0) {
+            assert!(quantized.palette[1..].contains(pixel));
+        }
+    }
+}
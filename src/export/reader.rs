@@ -0,0 +1,493 @@
+// Only the round-trip tests read data files back, so dead code is only checked in test builds
+#![cfg_attr(not(test), allow(dead_code))]
+use crate::export::compression::Compression;
+use crate::export::header::*;
+use crate::export::layout::*;
+use crate::export::pixel_format::*;
+use crate::project::*;
+use std::collections::HashMap;
+
+// * ---------------------------------------------------------------------------------- DATA ---------------------------------------------------------------------------------- * //
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataFile {
+    pub version: u16,
+    pub atlases: Vec<AtlasData>,
+    pub maps: Vec<MapData>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasData {
+    pub tile_size: U16Vec2,
+    pub pixel_format: PixelFormat,
+    pub transparency: Transparency,
+    /// RGBA colors the pixels index, empty unless the pixel format is indexed
+    pub palette: Vec<[u8; 4]>,
+    /// Encoded pixels of every tile
+    pub tiles: Vec<Vec<u8>>,
+    /// With exported tile indices
+    pub animations: Vec<Animation>,
+    /// Property values of every tile, empty if the atlas has no tile properties
+    pub tile_values: Vec<Vec<Value>>,
+    /// Exported tile index and collision shapes of the tiles that have any
+    pub collisions: Vec<(u16, Vec<Collision>)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapData {
+    pub size: U16Vec2,
+    pub atlas: u16,
+    /// Tile indices row by row, 0xFFFF is empty
+    pub tiles: Vec<u16>,
+    pub objects: Vec<ObjectData>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectData {
+    pub pos: I32Vec2,
+    pub always_on_top: bool,
+    pub components: Vec<ComponentData>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComponentData {
+    pub index: u16,
+    pub values: Vec<(String, Value)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    F32(f32),
+    String(String),
+    Atlas(u16),
+    Map(u16),
+}
+
+/// Component schemas by their export IDs
+pub fn component_schemas(assets: &Assets) -> HashMap<u16, &Component> {
+    assets
+        .components
+        .values()
+        .map(|component| (component.export_id, component))
+        .collect()
+}
+
+// * ---------------------------------------------------------------------------------- READ ---------------------------------------------------------------------------------- * //
+pub fn read(data: &[u8], components: &HashMap<u16, &Component>) -> Result<DataFile> {
+    let magic = data.get(..4).context("Data file is too short!")?;
+    if magic != MAGIC {
+        bail!("Not a data file, magic is {:?}!", magic);
+    }
+
+    // The version is written in the file's byte order
+    let version = data.get(4..6).context("Data file is too short!")?;
+    let byte_order = if u16::from_le_bytes([version[0], version[1]]) == FORMAT_VERSION {
+        ByteOrder::LittleEndian
+    } else if u16::from_be_bytes([version[0], version[1]]) == FORMAT_VERSION {
+        ByteOrder::BigEndian
+    } else {
+        bail!(
+            "Unsupported data file version {}, expected {}!",
+            u16::from_le_bytes([version[0], version[1]]),
+            FORMAT_VERSION
+        );
+    };
+
+    let mut header = DataReader::new(data, byte_order, Alignment::Packed);
+    header.position = 6;
+    let mut sections = HashMap::new();
+    for _ in 0..header.u16()? {
+        let tag: [u8; 4] = header
+            .bytes(4)
+            .context("Section table is truncated!")?
+            .try_into()
+            .unwrap();
+        let compression = header.u8()?;
+        let alignment = header.u8()?;
+        header.bytes(2)?;
+        let offset = header.u32()? as usize;
+        let stored_length = header.u32()? as usize;
+        let length = header.u32()? as usize;
+
+        let name = String::from_utf8_lossy(&tag).into_owned();
+        let compression = *Compression::VALUES
+            .get(compression as usize)
+            .context(format!(
+                "Section {} has unknown compression {}!",
+                name, compression
+            ))?;
+        let alignment = Alignment::from_bytes(alignment).context(format!(
+            "Section {} has unknown alignment {}!",
+            name, alignment
+        ))?;
+        let section = data
+            .get(offset..offset + stored_length)
+            .context(format!("Section {} is out of bounds!", name))?;
+        sections.insert(
+            tag,
+            (
+                compression
+                    .decompress(section, length)
+                    .context(format!("Failed to decompress section {}!", name))?,
+                alignment,
+            ),
+        );
+    }
+
+    let section = |tag: [u8; 4]| {
+        sections
+            .get(&tag)
+            .map(|(data, alignment)| DataReader::new(data, byte_order, *alignment))
+            .context(format!(
+                "Data file has no {} section!",
+                String::from_utf8_lossy(&tag)
+            ))
+    };
+
+    Ok(DataFile {
+        version: FORMAT_VERSION,
+        atlases: read_atlases(section(ATLASES_SECTION)?).context("Failed to read atlases!")?,
+        maps: read_maps(section(MAPS_SECTION)?, components).context("Failed to read maps!")?,
+    })
+}
+
+fn read_atlases(mut section: DataReader) -> Result<Vec<AtlasData>> {
+    let mut atlases = Vec::new();
+    for _ in 0..section.u16()? {
+        section.align_record();
+        let tile_size = U16Vec2::new(section.u16()?, section.u16()?);
+        let tile_count = section.u16()?;
+        let pixel_format = section.u8()?;
+        let pixel_format = *PixelFormat::VALUES
+            .get(pixel_format as usize)
+            .context(format!("Unknown pixel format {}!", pixel_format))?;
+        let transparency = match (section.u8()?, section.u32()?) {
+            (0, _) => Transparency::None,
+            (1, key) => Transparency::ColorKey(key),
+            (2, _) => Transparency::Alpha,
+            (id, _) => bail!("Unknown transparency {}!", id),
+        };
+        let mut palette = Vec::new();
+        if pixel_format.is_indexed() {
+            for _ in 0..section.u16()? {
+                palette.push(
+                    section
+                        .bytes(4)
+                        .context(format!("Palette of atlas {} is truncated!", atlases.len()))?
+                        .try_into()?,
+                );
+            }
+        }
+
+        let mut tiles = Vec::new();
+        for _ in 0..tile_count {
+            tiles.push(
+                section
+                    .bytes(pixel_format.tile_bytes(tile_size))
+                    .context(format!("Atlas {} is truncated!", atlases.len()))?
+                    .to_vec(),
+            );
+        }
+        let mut animations = Vec::new();
+        for _ in 0..section.u16()? {
+            let (tile, frame_time) = (section.u16()?, section.u16()?);
+            let frames = (0..section.u16()?)
+                .map(|_| section.u16())
+                .collect::<Result<_>>()?;
+            animations.push(Animation {
+                tile,
+                frames,
+                frame_time,
+            });
+        }
+
+        let property_types = (0..section.u16()?)
+            .map(|_| {
+                let id = section.u8()?;
+                Property::VALUES
+                    .get(id as usize)
+                    .context(format!("Unknown property type {}!", id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut tile_values = Vec::new();
+        if !property_types.is_empty() {
+            let offsets = (0..tile_count)
+                .map(|_| section.u32())
+                .collect::<Result<Vec<_>>>()?;
+            section.align_record();
+            let table = section.position;
+            for offset in offsets {
+                section.position = table + offset as usize;
+                tile_values.push(
+                    property_types
+                        .iter()
+                        .map(|property_type| read_value(&mut section, property_type))
+                        .collect::<Result<Vec<_>>>()
+                        .context(format!(
+                            "Failed to read values of tile {} of atlas {}!",
+                            tile_values.len(),
+                            atlases.len()
+                        ))?,
+                );
+            }
+        }
+
+        let mut collisions = Vec::new();
+        for _ in 0..section.u16()? {
+            let tile = section.u16()?;
+            let shapes = (0..section.u8()?)
+                .map(|_| read_collision(&mut section))
+                .collect::<Result<_>>()
+                .context(format!(
+                    "Failed to read collision shapes of tile {} of atlas {}!",
+                    tile,
+                    atlases.len()
+                ))?;
+            collisions.push((tile, shapes));
+        }
+        atlases.push(AtlasData {
+            tile_size,
+            pixel_format,
+            transparency,
+            palette,
+            tiles,
+            animations,
+            tile_values,
+            collisions,
+        });
+    }
+    Ok(atlases)
+}
+
+fn read_collision(section: &mut DataReader) -> Result<Collision> {
+    let point = |section: &mut DataReader| -> Result<U16Vec2> {
+        Ok(U16Vec2::new(section.u8()? as _, section.u8()? as _))
+    };
+    let header = section.u8()?;
+    let shape = match header & 0x7F {
+        0 => CollisionShape::Rect {
+            pos: point(section)?,
+            size: point(section)?,
+        },
+        1 => CollisionShape::Polygon(
+            (0..section.u8()?)
+                .map(|_| point(section))
+                .collect::<Result<_>>()?,
+        ),
+        kind => bail!("Unknown collision shape kind {}!", kind),
+    };
+    Ok(Collision {
+        shape,
+        one_way: header & 0x80 != 0,
+    })
+}
+
+fn read_maps(
+    mut section: DataReader,
+    components: &HashMap<u16, &Component>,
+) -> Result<Vec<MapData>> {
+    let mut maps = Vec::new();
+    for _ in 0..section.u16()? {
+        section.align_record();
+        let size = U16Vec2::new(section.u16()?, section.u16()?);
+        let atlas = section.u16()?;
+        let tiles = (0..size.x as usize * size.y as usize)
+            .map(|_| section.u16())
+            .collect::<Result<Vec<_>>>()
+            .context(format!("Map {} is truncated!", maps.len()))?;
+
+        let mut objects = Vec::new();
+        for _ in 0..section.u16()? {
+            objects.push(read_object(&mut section, components).context(format!(
+                "Failed to read object {} of map {}!",
+                objects.len(),
+                maps.len()
+            ))?);
+        }
+
+        maps.push(MapData {
+            size,
+            atlas,
+            tiles,
+            objects,
+        });
+    }
+    Ok(maps)
+}
+
+fn read_object(
+    section: &mut DataReader,
+    components: &HashMap<u16, &Component>,
+) -> Result<ObjectData> {
+    section.align_record();
+    let pos = I32Vec2::new(section.i32()?, section.i32()?);
+    let always_on_top = section.u8()? != 0;
+
+    let mut instances = Vec::new();
+    for _ in 0..section.u16()? {
+        section.align_record();
+        let index = section.u16()?;
+        let component = components
+            .get(&index)
+            .context(format!("Unknown component index {}!", index))?;
+
+        let mut values = Vec::new();
+        for (name, property_type) in &component.properties {
+            values.push((
+                name.clone(),
+                read_value(section, property_type)
+                    .context(format!("Failed to read property {}!", name))?,
+            ));
+        }
+        instances.push(ComponentData { index, values });
+    }
+
+    Ok(ObjectData {
+        pos,
+        always_on_top,
+        components: instances,
+    })
+}
+
+fn read_value(section: &mut DataReader, property_type: &Property) -> Result<Value> {
+    Ok(match property_type {
+        Property::I8 => Value::I8(section.i8()?),
+        Property::U8 => Value::U8(section.u8()?),
+        Property::I16 => Value::I16(section.i16()?),
+        Property::U16 => Value::U16(section.u16()?),
+        Property::I32 => Value::I32(section.i32()?),
+        Property::U32 => Value::U32(section.u32()?),
+        Property::F32 => Value::F32(section.f32()?),
+        Property::String => {
+            let length = section.u16()? as usize;
+            Value::String(
+                String::from_utf8(section.bytes(length)?.to_vec())
+                    .context("String is not valid UTF-8!")?,
+            )
+        }
+        Property::Atlas => Value::Atlas(section.u16()?),
+        Property::Map => Value::Map(section.u16()?),
+    })
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::test_project::*;
+
+    #[test]
+    fn round_trip() {
+        let mut assets = test_project();
+        let data = export(&mut assets);
+        let file = read(&data, &component_schemas(&assets)).expect("Failed to read!");
+        assert_eq!(file.version, FORMAT_VERSION);
+
+        assert_eq!(
+            file.atlases,
+            vec![AtlasData {
+                tile_size: U16Vec2::new(4, 4),
+                pixel_format: PixelFormat::Rgb565,
+                transparency: Transparency::ColorKey(0xF81F),
+                palette: Vec::new(),
+                tiles: vec![RED.repeat(16), BLUE.repeat(16), TRANSPARENT.repeat(16)],
+                animations: Vec::new(),
+                tile_values: Vec::new(),
+                collisions: Vec::new(),
+            }]
+        );
+
+        // Maps are stored by export ID: level, shop
+        assert_eq!(file.maps.len(), 2);
+        let (level, shop) = (&file.maps[0], &file.maps[1]);
+        assert_eq!(level.size, U16Vec2::new(3, 2));
+        assert_eq!(level.atlas, 0);
+        assert_eq!(level.tiles, vec![0, 1, 0xFFFF, 0xFFFF, 0xFFFF, 2]);
+        assert_eq!(shop.tiles, vec![0xFFFF]);
+
+        // Builtin components take IDs 0 and 1, door is 2
+        assert_eq!(
+            level.objects,
+            vec![ObjectData {
+                pos: I32Vec2::new(5, -7),
+                always_on_top: false,
+                components: vec![ComponentData {
+                    index: 2,
+                    values: vec![
+                        ("target".to_owned(), Value::Map(1)),
+                        ("sprite".to_owned(), Value::Atlas(0)),
+                        ("key".to_owned(), Value::I8(-3)),
+                        ("count".to_owned(), Value::U8(200)),
+                        ("dx".to_owned(), Value::I16(-1000)),
+                        ("dy".to_owned(), Value::U16(60000)),
+                        ("id".to_owned(), Value::I32(-100000)),
+                        ("flags".to_owned(), Value::U32(4000000000)),
+                        ("speed".to_owned(), Value::F32(1.5)),
+                        ("name".to_owned(), Value::String("Shop door".to_owned())),
+                    ],
+                }],
+            }]
+        );
+        assert_eq!(
+            shop.objects,
+            vec![ObjectData {
+                pos: I32Vec2::new(1, 2),
+                always_on_top: true,
+                components: vec![ComponentData {
+                    index: 0,
+                    values: vec![("Atlas".to_owned(), Value::Atlas(0))],
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_unknown_sections() {
+        let mut assets = test_project();
+        let data = export(&mut assets);
+        let file = read(&data, &component_schemas(&assets)).unwrap();
+
+        // Re-pack the same sections with an extra one in front
+        let section = |index: usize| {
+            let entry = HEADER_SIZE + SECTION_ENTRY_SIZE * index;
+            let offset = u32::from_le_bytes(data[entry + 8..entry + 12].try_into().unwrap());
+            let length = u32::from_le_bytes(data[entry + 12..entry + 16].try_into().unwrap());
+            Section::new(
+                data[entry..entry + 4].try_into().unwrap(),
+                data[offset as usize..(offset + length) as usize].to_vec(),
+            )
+        };
+        let mut repacked = Vec::new();
+        write(
+            &mut repacked,
+            &[
+                Section::new(*b"TEST", vec![1, 2, 3]),
+                section(1),
+                section(0),
+            ],
+            &assets.export_settings,
+        )
+        .unwrap();
+
+        assert_eq!(read(&repacked, &component_schemas(&assets)).unwrap(), file);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let mut assets = test_project();
+        let data = export(&mut assets);
+        let components = component_schemas(&assets);
+
+        assert!(read(b"PNG\0\0\0\0\0", &components).is_err());
+        assert!(read(&data[..data.len() - 1], &components).is_err());
+
+        let mut newer = data.clone();
+        newer[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(read(&newer, &components).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::project::*;
+use itertools::Itertools;
+
+// * --------------------------------------------------------------------------------- RENDER --------------------------------------------------------------------------------- * //
+/// Draws the map's tiles, then the AtlasRenderer objects like the editor does, every pixel `scale` times
+pub fn render(assets: &Assets, map: &Map, scale: u32) -> Result<image::RgbaImage> {
+    if scale == 0 {
+        bail!("Render scale must be at least 1!");
+    }
+    let atlas = assets.atlases.get(&map.atlas).context(format!(
+        "Atlas of map {:?} is not in the project!",
+        map.path
+    ))?;
+    if atlas.tile_size.x == 0 || atlas.tile_size.y == 0 {
+        bail!("Atlas {:?} has a zero tile size!", atlas.path);
+    }
+
+    let tile_size = atlas.tile_size.casted::<i32>() * scale as i32;
+    let size = map.size.casted::<u32>().component_mul(&tile_size.casted());
+    let mut image = image::RgbaImage::new(size.x, size.y);
+    for y in 0..map.size.y {
+        for x in 0..map.size.x {
+            let tile = map.get_tile(TVec2::new(x, y).casted());
+            // Tiles outside of the atlas are reported by validation, they are left empty here
+            if !atlas.contains_tile(tile) {
+                continue;
+            }
+            atlas.draw_tile(
+                &mut image,
+                TVec2::new(x, y).casted().component_mul(&tile_size),
+                tile.casted(),
+                tile_size,
+            );
+        }
+    }
+
+    // Sorted like the export, so overlapping objects always come out the same
+    let objects = map
+        .objects
+        .iter()
+        .sorted_by_key(|(uuid, object)| (object.always_on_top, object.pos.y, object.pos.x, **uuid));
+    for (_, object) in objects {
+        let Some(atlas) = object
+            .components
+            .get(&ATLAS_RENDERER_UUID)
+            .and_then(|renderer| renderer.get("Atlas"))
+            .and_then(|atlas| Uuid::parse_str(atlas).ok())
+            .and_then(|atlas| assets.atlases.get(&atlas))
+        else {
+            continue;
+        };
+        if atlas.width() == 0 || atlas.height() == 0 {
+            continue;
+        }
+        atlas.draw_tile(
+            &mut image,
+            object.pos * scale as i32,
+            TVec2::zeros(),
+            atlas.tile_size.casted::<i32>() * scale as i32,
+        );
+    }
+    Ok(image)
+}
+
+pub fn render_to(assets: &Assets, map: &Map, scale: u32, path: &Path) -> Result<()> {
+    render(assets, map, scale)?
+        .save(path)
+        .context(format!("Failed to save map render to {:?}!", path))
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view::editor_view::Object;
+
+    #[test]
+    fn renders_tiles_and_objects() {
+        let mut assets = Assets::new(Path::new("/game"));
+        let (red, green, blue) = (
+            image::Rgba([255, 0, 0, 255]),
+            image::Rgba([0, 255, 0, 255]),
+            image::Rgba([0, 0, 255, 255]),
+        );
+        // Tiles: red, green
+        let mut tiles = image::RgbaImage::new(4, 2);
+        for (x, y, pixel) in tiles.enumerate_pixels_mut() {
+            *pixel = if x < 2 { red } else { green };
+            if (x, y) == (3, 1) {
+                *pixel = image::Rgba([0, 0, 0, 0]);
+            }
+        }
+        let atlas = Uuid::new_v4();
+        assets.atlases.insert(
+            atlas,
+            Atlas::new(Path::new("/game/tiles.png"), tiles, U16Vec2::new(2, 2)),
+        );
+        let sprite = Uuid::new_v4();
+        assets.atlases.insert(
+            sprite,
+            Atlas::new(
+                Path::new("/game/sprite.png"),
+                image::RgbaImage::from_pixel(1, 1, blue),
+                U16Vec2::new(1, 1),
+            ),
+        );
+
+        let mut map = Map::new(Path::new("/game/level.map"), U16Vec2::new(3, 1), atlas);
+        map.set_tile(TVec2::new(0, 0), TVec2::new(0, 0));
+        map.set_tile(TVec2::new(1, 0), TVec2::new(1, 0));
+        map.set_tile(TVec2::new(2, 0), TVec2::new(5, 0));
+        let renderer = [("Atlas".to_owned(), sprite.to_string())]
+            .into_iter()
+            .collect();
+        map.objects.insert(
+            Uuid::new_v4(),
+            Object::new(TVec2::new(1, 1), &[(ATLAS_RENDERER_UUID, renderer)]),
+        );
+        map.objects
+            .insert(Uuid::new_v4(), Object::new(TVec2::new(3, 0), &[]));
+
+        let image = render(&assets, &map, 2).unwrap();
+        assert_eq!(image.dimensions(), (12, 4));
+        assert_eq!(*image.get_pixel(0, 0), red);
+        assert_eq!(*image.get_pixel(1, 3), red);
+        assert_eq!(*image.get_pixel(4, 0), green);
+        // Transparent atlas pixels and tiles outside of the atlas stay empty
+        assert_eq!(image.get_pixel(7, 3)[3], 0);
+        assert_eq!(image.get_pixel(8, 0)[3], 0);
+        // The object covers pixel (1, 1) of the map, 2x2 pixels at this scale
+        assert_eq!(*image.get_pixel(2, 2), blue);
+        assert_eq!(*image.get_pixel(3, 3), blue);
+        assert_eq!(*image.get_pixel(1, 1), red);
+        assert_eq!(*image.get_pixel(4, 2), green);
+
+        assert!(render(&assets, &map, 0).is_err());
+        map.atlas = Uuid::new_v4();
+        assert!(render(&assets, &map, 1).is_err());
+    }
+}
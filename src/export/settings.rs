@@ -0,0 +1,99 @@
+use crate::export::compression::Compression;
+use crate::export::layout::*;
+use crate::export::pixel_format::*;
+use crate::project::*;
+
+// * -------------------------------------------------------------------------------- SETTINGS -------------------------------------------------------------------------------- * //
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportSettings {
+    #[serde(default)]
+    pub pixel_format: PixelFormat,
+    #[serde(default)]
+    pub transparency: Transparency,
+    /// Shades for Planar2bpp, from index 0 to 3
+    #[serde(default = "default_palette")]
+    pub palette: [[u8; 3]; 4],
+    /// Export identical tiles only once
+    #[serde(default)]
+    pub deduplicate_tiles: bool,
+    /// Drop tiles that no map or object references
+    #[serde(default)]
+    pub strip_unused_tiles: bool,
+    #[serde(default)]
+    pub atlas_compression: Compression,
+    #[serde(default)]
+    pub map_compression: Compression,
+    /// Byte order of every number in the data file
+    #[serde(default)]
+    pub byte_order: ByteOrder,
+    /// Padding before values and records, so they can be read in place
+    #[serde(default)]
+    pub alignment: Alignment,
+    /// data.dat size in bytes over which export warns, 0 for no budget
+    #[serde(default)]
+    pub size_warning: u32,
+    /// data.dat size in bytes over which export fails, 0 for no budget
+    #[serde(default)]
+    pub size_limit: u32,
+    /// Write a C header with indices and component readers next to the data file
+    #[serde(default)]
+    pub generate_c_header: bool,
+    /// Write a Rust module with indices and component readers next to the data file
+    #[serde(default)]
+    pub generate_rust_module: bool,
+}
+
+fn default_palette() -> [[u8; 3]; 4] {
+    [[255, 255, 255], [170, 170, 170], [85, 85, 85], [0, 0, 0]]
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            pixel_format: PixelFormat::default(),
+            transparency: Transparency::default(),
+            palette: default_palette(),
+            deduplicate_tiles: false,
+            strip_unused_tiles: false,
+            atlas_compression: Compression::None,
+            map_compression: Compression::None,
+            byte_order: ByteOrder::default(),
+            alignment: Alignment::default(),
+            size_warning: 0,
+            size_limit: 0,
+            generate_c_header: false,
+            generate_rust_module: false,
+        }
+    }
+}
+
+impl ExportSettings {
+    /// Planar2bpp shades as opaque colors
+    pub fn shades(&self) -> Vec<image::Rgba<u8>> {
+        self.palette
+            .iter()
+            .map(|[r, g, b]| image::Rgba([*r, *g, *b, 255]))
+            .collect()
+    }
+
+    pub fn load(project: &Path) -> Result<Self> {
+        let path = project.join("export.json");
+        if path.exists() {
+            serde_json::from_str(
+                &std::fs::read_to_string(&path)
+                    .context(format!("Failed to load export settings from {:?}!", path))?,
+            )
+            .context("Failed to deserialize export settings!")
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self, project: &Path) -> Result<()> {
+        std::fs::write(
+            project.join("export.json"),
+            serde_json::to_string(self).context("Failed to serialize export settings!")?,
+        )
+        .context("Failed to save export settings!")
+    }
+}
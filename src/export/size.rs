@@ -0,0 +1,213 @@
+use crate::export::codegen::asset_name;
+use crate::export::header::Section;
+use crate::export::validate::{Problem, Severity};
+use crate::project::*;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// * --------------------------------------------------------------------------------- REPORT --------------------------------------------------------------------------------- * //
+/// What data.dat is made of, filled in by the last export.
+/// Asset sizes are counted before compression and include the padding before their records.
+#[derive(Clone, Default, Debug)]
+pub struct SizeReport {
+    /// Bytes of the data file
+    pub total: usize,
+    /// Header, section table and padding between sections
+    pub header: usize,
+    /// Tag, stored (compressed) bytes and bytes before compression of every section
+    pub sections: Vec<([u8; 4], usize, usize)>,
+    pub atlases: HashMap<Uuid, usize>,
+    /// Map record up to the object count
+    pub map_tiles: HashMap<Uuid, usize>,
+    /// Object count and object records, including their components
+    pub map_objects: HashMap<Uuid, usize>,
+    /// Component records across all objects of all maps
+    pub components: HashMap<Uuid, usize>,
+}
+
+impl SizeReport {
+    pub fn finish(&mut self, sections: &[Section], total: usize) {
+        self.sections = sections
+            .iter()
+            .map(|section| (section.tag, section.data.len(), section.length))
+            .collect();
+        self.total = total;
+        self.header = total - self.sections.iter().map(|section| section.1).sum::<usize>();
+    }
+
+    /// Named entries of every group, largest first
+    pub fn groups(&self, assets: &Assets) -> [(&'static str, Vec<(String, usize)>); 4] {
+        fn named<T>(
+            assets: &Assets,
+            sizes: &HashMap<Uuid, usize>,
+            items: &HashMap<Uuid, T>,
+            path: impl Fn(&T) -> &PathBuf,
+        ) -> Vec<(String, usize)> {
+            sizes
+                .iter()
+                .map(|(uuid, size)| {
+                    let name = items
+                        .get(uuid)
+                        .map_or(uuid.to_string(), |item| asset_name(assets, path(item)));
+                    (name, *size)
+                })
+                .sorted_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)))
+                .collect()
+        }
+
+        [
+            (
+                "Atlases",
+                named(assets, &self.atlases, &assets.atlases, |atlas| &atlas.path),
+            ),
+            (
+                "Map tiles",
+                named(assets, &self.map_tiles, &assets.maps, |map| &map.path),
+            ),
+            (
+                "Map objects",
+                named(assets, &self.map_objects, &assets.maps, |map| &map.path),
+            ),
+            (
+                "Components",
+                named(assets, &self.components, &assets.components, |component| {
+                    &component.path
+                }),
+            ),
+        ]
+    }
+
+    pub fn text(&self, assets: &Assets) -> String {
+        let mut text = String::new();
+        writeln!(text, "data.dat: {} bytes", self.total).unwrap();
+        writeln!(text, "  Header: {} bytes", self.header).unwrap();
+        for (tag, stored, length) in &self.sections {
+            write!(
+                text,
+                "  Section {}: {} bytes",
+                String::from_utf8_lossy(tag),
+                stored
+            )
+            .unwrap();
+            if stored != length {
+                write!(text, ", {} before compression", length).unwrap();
+            }
+            writeln!(text).unwrap();
+        }
+        for (group, entries) in self.groups(assets) {
+            if entries.is_empty() {
+                continue;
+            }
+            writeln!(text, "{} (before compression):", group).unwrap();
+            for (name, size) in entries {
+                writeln!(text, "  {}: {} bytes", name, size).unwrap();
+            }
+        }
+        text
+    }
+}
+
+// * --------------------------------------------------------------------------------- BUDGET --------------------------------------------------------------------------------- * //
+/// Problems for a data file of `total` bytes over the budgets of the export settings
+pub fn check_budget(settings: &ExportSettings, project: &Path, total: usize) -> Vec<Problem> {
+    [
+        (Severity::Error, settings.size_limit),
+        (Severity::Warning, settings.size_warning),
+    ]
+    .into_iter()
+    .find(|(_, budget)| *budget > 0 && total > *budget as usize)
+    .map(|(severity, budget)| Problem {
+        severity,
+        asset: project.to_path_buf(),
+        message: format!(
+            "data.dat is {} bytes, {} over the budget of {} bytes",
+            total,
+            total - budget as usize,
+            budget
+        ),
+    })
+    .into_iter()
+    .collect()
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view::editor_view::Object;
+
+    #[test]
+    fn sizes_and_budget() {
+        let mut assets = Assets::new(Path::new("/game"));
+        let atlas = Uuid::new_v4();
+        assets.atlases.insert(
+            atlas,
+            Atlas::new(
+                Path::new("/game/tiles.png"),
+                image::RgbaImage::new(16, 8),
+                U16Vec2::new(8, 8),
+            ),
+        );
+        let map = Uuid::new_v4();
+        let mut level = Map::new(Path::new("/game/level.map"), U16Vec2::new(2, 1), atlas);
+        let renderer: HashMap<_, _> = [("Atlas".to_owned(), atlas.to_string())]
+            .into_iter()
+            .collect();
+        for x in 0..3 {
+            level.objects.insert(
+                Uuid::new_v4(),
+                Object::new(
+                    TVec2::new(x, 0),
+                    &[
+                        (ATLAS_RENDERER_UUID, renderer.clone()),
+                        (SERIALIZE_UUID, Default::default()),
+                    ],
+                ),
+            );
+        }
+        assets.maps.insert(map, level);
+
+        let mut data = Vec::new();
+        assets.export_data(&mut data).unwrap();
+        let sizes = &assets.export_sizes;
+        assert_eq!(sizes.total, data.len());
+        assert_eq!(sizes.header, 8 + 2 * 20);
+        // Tile size, tile count, format, transparency, key, 2 Rgb565 tiles, animation, tile property and collision counts
+        assert_eq!(sizes.atlases[&atlas], 12 + 2 * 8 * 8 * 2 + 6);
+        assert_eq!(sizes.sections[0], (*b"ATLS", 2 + 274, 2 + 274));
+        // Size, atlas and 2 tiles
+        assert_eq!(sizes.map_tiles[&map], 6 + 2 * 2);
+        // Count, then per object position, flag, component count and components
+        assert_eq!(sizes.map_objects[&map], 2 + 3 * (9 + 2 + 4 + 2));
+        assert_eq!(sizes.components[&ATLAS_RENDERER_UUID], 3 * 4);
+        assert_eq!(sizes.components[&SERIALIZE_UUID], 3 * 2);
+        assert_eq!(sizes.sections[1].2, 2 + 10 + 53);
+        assert!(sizes.text(&assets).contains(
+            "Components (before compression):\n  AtlasRenderer: 12 bytes\n  Serialize: 6 bytes\n"
+        ));
+
+        assets.export_settings.size_warning = data.len() as u32 - 1;
+        assets.export_data(&mut Vec::new()).unwrap();
+        assert_eq!(assets.export_problems.len(), 1);
+        assert_eq!(assets.export_problems[0].severity, Severity::Warning);
+        assert_eq!(
+            assets.export_problems[0].message,
+            format!(
+                "data.dat is {} bytes, 1 over the budget of {} bytes",
+                data.len(),
+                data.len() - 1
+            )
+        );
+
+        assets.export_settings.size_limit = data.len() as u32 - 10;
+        let mut data = Vec::new();
+        assert!(assets.export_data(&mut data).is_err());
+        assert!(data.is_empty());
+        assert_eq!(assets.export_problems.len(), 1);
+        assert_eq!(assets.export_problems[0].severity, Severity::Error);
+
+        assets.export_settings.size_limit = 1 << 20;
+        assert!(assets.export_data(&mut Vec::new()).is_ok());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::export::reader::*;
+use crate::project::*;
+use crate::view::editor_view::Object;
+use std::collections::HashMap;
+
+// * -------------------------------------------------------------------------------- FIXTURES -------------------------------------------------------------------------------- * //
+// Project the export tests share, with every property type and two maps referencing each other
+pub const RED: [u8; 2] = 0xF800u16.to_le_bytes();
+pub const BLUE: [u8; 2] = 0x001Fu16.to_le_bytes();
+pub const TRANSPARENT: [u8; 2] = 0xF81Fu16.to_le_bytes();
+
+pub const ATLAS: Uuid = Uuid::from_u128(1);
+pub const DOOR: Uuid = Uuid::from_u128(2);
+pub const LEVEL: Uuid = Uuid::from_u128(3);
+pub const SHOP: Uuid = Uuid::from_u128(4);
+
+pub fn door_instance(target: Uuid, name: &str) -> HashMap<String, String> {
+    [
+        ("target", target.to_string()),
+        ("sprite", ATLAS.to_string()),
+        ("key", "-3".to_owned()),
+        ("count", "200".to_owned()),
+        ("dx", "-1000".to_owned()),
+        ("dy", "60000".to_owned()),
+        ("id", "-100000".to_owned()),
+        ("flags", "4000000000".to_owned()),
+        ("speed", "1.5".to_owned()),
+        ("name", name.to_owned()),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_owned(), value))
+    .collect()
+}
+
+pub fn test_project() -> Assets {
+    let mut assets = Assets::new(Path::new("/test"));
+
+    // 3x1 tiles: red, blue, transparent
+    let atlas = ATLAS;
+    assets.atlases.insert(
+        atlas,
+        Atlas::new(
+            Path::new("/test/tiles.png"),
+            image::RgbaImage::from_fn(12, 4, |x, _| match x / 4 {
+                0 => image::Rgba([255, 0, 0, 255]),
+                1 => image::Rgba([0, 0, 255, 255]),
+                _ => image::Rgba([0, 0, 0, 0]),
+            }),
+            U16Vec2::new(4, 4),
+        ),
+    );
+
+    let door = DOOR;
+    let mut component = Component::new(Path::new("/test/door.cmp"));
+    component.export_id = 2;
+    for (name, property_type) in [
+        ("target", Property::Map),
+        ("sprite", Property::Atlas),
+        ("key", Property::I8),
+        ("count", Property::U8),
+        ("dx", Property::I16),
+        ("dy", Property::U16),
+        ("id", Property::I32),
+        ("flags", Property::U32),
+        ("speed", Property::F32),
+        ("name", Property::String),
+    ] {
+        component.properties.insert(name.to_owned(), property_type);
+    }
+    assets.components.insert(door, component);
+
+    let (level, shop) = (LEVEL, SHOP);
+    let mut map = Map::new(Path::new("/test/level.map"), U16Vec2::new(3, 2), atlas);
+    map.set_tile(TVec2::new(0, 0), TVec2::new(0, 0));
+    map.set_tile(TVec2::new(1, 0), TVec2::new(1, 0));
+    map.set_tile(TVec2::new(2, 1), TVec2::new(2, 0));
+    map.objects.insert(
+        Uuid::from_u128(10),
+        Object::new(
+            TVec2::new(5, -7),
+            &[(door, door_instance(shop, "Shop door"))],
+        ),
+    );
+    assets.maps.insert(level, map);
+
+    let mut map = Map::new(Path::new("/test/shop.map"), U16Vec2::new(1, 1), atlas);
+    map.export_id = 1;
+    let mut object = Object::new(
+        TVec2::new(1, 2),
+        &[(
+            ATLAS_RENDERER_UUID,
+            [("Atlas".to_owned(), atlas.to_string())]
+                .into_iter()
+                .collect(),
+        )],
+    );
+    object.always_on_top = true;
+    map.objects.insert(Uuid::from_u128(11), object);
+    assets.maps.insert(shop, map);
+    assets
+}
+
+/// Test project with several objects and components per object, for the golden files
+pub fn golden_project() -> Assets {
+    let mut assets = test_project();
+    let renderer = (
+        ATLAS_RENDERER_UUID,
+        [("Atlas".to_owned(), ATLAS.to_string())]
+            .into_iter()
+            .collect(),
+    );
+    let level = assets.maps.get_mut(&LEVEL).unwrap();
+    level.objects.insert(
+        Uuid::from_u128(12),
+        Object::new(
+            TVec2::new(8, 0),
+            &[
+                (DOOR, door_instance(LEVEL, "Back door")),
+                (SERIALIZE_UUID, HashMap::new()),
+                renderer.clone(),
+            ],
+        ),
+    );
+    for (uuid, name) in [(13, "Left"), (14, "Right")] {
+        level.objects.insert(
+            Uuid::from_u128(uuid),
+            Object::new(
+                TVec2::new(0, 4),
+                &[(DOOR, door_instance(SHOP, name)), renderer.clone()],
+            ),
+        );
+    }
+    assets
+}
+
+pub fn export(assets: &mut Assets) -> Vec<u8> {
+    let mut data = Vec::new();
+    assets.export_data(&mut data).expect("Failed to export!");
+    data
+}
+
+/// Exports and reads the data file back
+pub fn export_and_read(assets: &mut Assets) -> DataFile {
+    read(&export(assets), &component_schemas(assets)).expect("Failed to read!")
+}
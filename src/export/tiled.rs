@@ -0,0 +1,360 @@
+use crate::export::codegen::asset_name;
+use crate::project::*;
+use itertools::Itertools;
+use std::fmt::Write;
+
+// * ---------------------------------------------------------------------------------- PATHS --------------------------------------------------------------------------------- * //
+/// Writes every atlas as TSX and every map as TMX into `output`, keeping the project's folder layout
+pub fn export(assets: &Assets, output: &Path) -> Result<()> {
+    let write = |path: &Path, content: String| -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create directory {:?}!", parent))?;
+        }
+        std::fs::write(path, content).context(format!("Failed to write {:?}!", path))
+    };
+
+    for atlas in assets.atlases.values() {
+        let path = output_path(assets, output, &atlas.path, "tsx");
+        write(&path, tsx(atlas, &path))?;
+    }
+    for map in assets.maps.values() {
+        let path = output_path(assets, output, &map.path, "tmx");
+        write(
+            &path,
+            tmx(assets, map, output).context(format!("Failed to convert map {:?}!", map.path))?,
+        )?;
+    }
+    Ok(())
+}
+
+pub fn output_path(assets: &Assets, output: &Path, path: &Path, extension: &str) -> PathBuf {
+    output
+        .join(
+            path.strip_prefix(&assets.path)
+                .unwrap_or_else(|_| Path::new(path.file_name().unwrap_or_default())),
+        )
+        .with_extension(extension)
+}
+
+/// Path of `target` relative to the directory of `from`, with forward slashes like Tiled writes them
+pub fn relative_path(from: &Path, target: &Path) -> String {
+    // A bare file name has an empty parent, which isn't a valid path on its own
+    let directory = from.parent().unwrap_or(from);
+    let (from, target) = (absolute(directory.join(".")), absolute(target));
+    let from = from.components().collect::<Vec<_>>();
+    let target = target.components().collect::<Vec<_>>();
+    let common = from.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    std::iter::repeat_n("..".to_owned(), from.len() - common)
+        .chain(
+            target[common..]
+                .iter()
+                .map(|component| component.as_os_str().to_string_lossy().into_owned()),
+        )
+        .join("/")
+}
+
+/// Absolute path with `..` resolved, so relative and absolute paths can be compared
+pub fn absolute(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    let mut absolute = PathBuf::new();
+    for component in std::path::absolute(path)
+        .unwrap_or_else(|_| path.to_owned())
+        .components()
+    {
+        match component {
+            std::path::Component::ParentDir => {
+                absolute.pop();
+            }
+            std::path::Component::CurDir => (),
+            component => absolute.push(component),
+        }
+    }
+    absolute
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            _ => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+// * ----------------------------------------------------------------------------------- TSX ---------------------------------------------------------------------------------- * //
+/// Tileset for an atlas, `path` is where the TSX will be written
+pub fn tsx(atlas: &Atlas, path: &Path) -> String {
+    // Tiled leaves out a zero spacing and margin
+    let mut layout = String::new();
+    if atlas.spacing != 0 {
+        layout += &format!(" spacing=\"{}\"", atlas.spacing);
+    }
+    if atlas.margin != 0 {
+        layout += &format!(" margin=\"{}\"", atlas.margin);
+    }
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<tileset version=\"1.10\" name=\"{}\" tilewidth=\"{}\" tileheight=\"{}\"{} tilecount=\"{}\" columns=\"{}\">\n",
+            " <image source=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+            "</tileset>\n"
+        ),
+        escape(&atlas.path.file_stem().unwrap_or_default().to_string_lossy()),
+        atlas.tile_size.x,
+        atlas.tile_size.y,
+        layout,
+        atlas.width() as u32 * atlas.height() as u32,
+        atlas.width(),
+        escape(&relative_path(path, &atlas.path)),
+        atlas.image.width(),
+        atlas.image.height(),
+    )
+}
+
+// * ----------------------------------------------------------------------------------- TMX ---------------------------------------------------------------------------------- * //
+/// Tile layer and object layer for a map. Component values become object properties named `Component.property`,
+/// components without properties a `Component` bool.
+pub fn tmx(assets: &Assets, map: &Map, output: &Path) -> Result<String> {
+    let path = output_path(assets, output, &map.path, "tmx");
+    let atlas = assets
+        .atlases
+        .get(&map.atlas)
+        .context("Map uses an atlas that is not in the project!")?;
+
+    let mut code = String::new();
+    writeln!(code, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        code,
+        "<map version=\"1.10\" orientation=\"orthogonal\" renderorder=\"right-down\" width=\"{}\" height=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" infinite=\"0\" nextlayerid=\"3\" nextobjectid=\"{}\">",
+        map.size.x,
+        map.size.y,
+        atlas.tile_size.x,
+        atlas.tile_size.y,
+        map.objects.len() + 1
+    )?;
+    writeln!(
+        code,
+        " <tileset firstgid=\"1\" source=\"{}\"/>",
+        escape(&relative_path(
+            &path,
+            &output_path(assets, output, &atlas.path, "tsx")
+        ))
+    )?;
+
+    // * Tiles, GID 0 is empty
+    writeln!(
+        code,
+        " <layer id=\"1\" name=\"Tiles\" width=\"{}\" height=\"{}\">",
+        map.size.x, map.size.y
+    )?;
+    writeln!(code, "  <data encoding=\"csv\">")?;
+    let rows = (0..map.size.y).map(|y| {
+        (0..map.size.x)
+            .map(|x| {
+                let tile = map.get_tile(TVec2::new(x, y).casted());
+                if tile < TVec2::zeros() {
+                    0
+                } else {
+                    tile.x as u32 + tile.y as u32 * atlas.width() as u32 + 1
+                }
+            })
+            .join(",")
+    });
+    writeln!(code, "{}", rows.format(",\n"))?;
+    writeln!(code, "</data>")?;
+    writeln!(code, " </layer>")?;
+
+    // * Objects, in the same order as in data.dat
+    writeln!(code, " <objectgroup id=\"2\" name=\"Objects\">")?;
+    for (id, (_, object)) in map
+        .objects
+        .iter()
+        .sorted_by_key(|(uuid, object)| (object.pos.y, object.pos.x, **uuid))
+        .enumerate()
+    {
+        writeln!(
+            code,
+            "  <object id=\"{}\" x=\"{}\" y=\"{}\">",
+            id + 1,
+            object.pos.x,
+            object.pos.y
+        )?;
+        writeln!(code, "   <properties>")?;
+        if object.always_on_top {
+            writeln!(
+                code,
+                "    <property name=\"always_on_top\" type=\"bool\" value=\"true\"/>"
+            )?;
+        }
+        for (uuid, instance) in object
+            .components
+            .iter()
+            .sorted_by_key(|(uuid, _)| assets.components.get(uuid).map(|c| c.export_id))
+        {
+            let component = assets
+                .components
+                .get(uuid)
+                .context("Object uses a component that is not in the project!")?;
+            let name = asset_name(assets, &component.path);
+            if component.properties.is_empty() {
+                writeln!(
+                    code,
+                    "    <property name=\"{}\" type=\"bool\" value=\"true\"/>",
+                    escape(&name)
+                )?;
+            }
+            for (property, property_type) in &component.properties {
+                let value = instance.get(property).cloned().unwrap_or_default();
+                let (tiled_type, value) = match property_type {
+                    Property::F32 => ("float", value),
+                    Property::String => ("string", value),
+                    Property::Atlas => (
+                        "file",
+                        file_value(assets, output, &path, &value, "tsx", |uuid| {
+                            assets.atlases.get(uuid).map(|atlas| &atlas.path)
+                        }),
+                    ),
+                    Property::Map => (
+                        "file",
+                        file_value(assets, output, &path, &value, "tmx", |uuid| {
+                            assets.maps.get(uuid).map(|map| &map.path)
+                        }),
+                    ),
+                    _ => ("int", value),
+                };
+                writeln!(
+                    code,
+                    "    <property name=\"{}.{}\" type=\"{}\" value=\"{}\"/>",
+                    escape(&name),
+                    escape(property),
+                    tiled_type,
+                    escape(&value)
+                )?;
+            }
+        }
+        writeln!(code, "   </properties>")?;
+        writeln!(code, "   <point/>")?;
+        writeln!(code, "  </object>")?;
+    }
+    writeln!(code, " </objectgroup>")?;
+    writeln!(code, "</map>")?;
+    Ok(code)
+}
+
+/// Atlas and map properties point to the exported TSX or TMX file
+fn file_value<'a>(
+    assets: &Assets,
+    output: &Path,
+    from: &Path,
+    uuid: &str,
+    extension: &str,
+    asset_path: impl Fn(&Uuid) -> Option<&'a PathBuf>,
+) -> String {
+    Uuid::parse_str(uuid)
+        .ok()
+        .and_then(|uuid| asset_path(&uuid))
+        .map(|path| relative_path(from, &output_path(assets, output, path, extension)))
+        .unwrap_or_default()
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view::editor_view::Object;
+
+    #[test]
+    fn tiled_files() {
+        let mut assets = Assets::new(Path::new("/game"));
+        let atlas = Uuid::new_v4();
+        assets.atlases.insert(
+            atlas,
+            Atlas::new(
+                Path::new("/game/art/tiles.png"),
+                image::RgbaImage::new(16, 8),
+                U16Vec2::new(8, 8),
+            ),
+        );
+
+        let door = Uuid::new_v4();
+        let mut component = Component::new(Path::new("/game/Door.cmp"));
+        component.export_id = 2;
+        component
+            .properties
+            .insert("target".to_owned(), Property::Map);
+        component
+            .properties
+            .insert("label".to_owned(), Property::String);
+        assets.components.insert(door, component);
+
+        let level = Uuid::new_v4();
+        let mut map = Map::new(
+            Path::new("/game/levels/level.map"),
+            U16Vec2::new(2, 2),
+            atlas,
+        );
+        map.set_tile(TVec2::new(0, 0), TVec2::new(1, 0));
+        let mut object = Object::new(
+            TVec2::new(4, 12),
+            &[
+                (
+                    door,
+                    [
+                        ("target".to_owned(), level.to_string()),
+                        ("label".to_owned(), "A & \"B\"".to_owned()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                (SERIALIZE_UUID, Default::default()),
+            ],
+        );
+        object.always_on_top = true;
+        map.objects.insert(Uuid::new_v4(), object);
+        assets.maps.insert(level, map);
+
+        let output = Path::new("/out");
+        let tsx = tsx(&assets.atlases[&atlas], Path::new("/out/art/tiles.tsx"));
+        assert!(tsx.contains("tilewidth=\"8\" tileheight=\"8\" tilecount=\"2\" columns=\"2\""));
+        assert!(
+            tsx.contains("<image source=\"../../game/art/tiles.png\" width=\"16\" height=\"8\"/>")
+        );
+
+        let tmx = tmx(&assets, &assets.maps[&level], output).unwrap();
+        assert!(tmx.contains("<tileset firstgid=\"1\" source=\"../art/tiles.tsx\"/>"));
+        assert!(tmx.contains("<data encoding=\"csv\">\n2,0,\n0,0\n</data>"));
+        assert!(tmx.contains(concat!(
+            "  <object id=\"1\" x=\"4\" y=\"12\">\n",
+            "   <properties>\n",
+            "    <property name=\"always_on_top\" type=\"bool\" value=\"true\"/>\n",
+            "    <property name=\"Serialize\" type=\"bool\" value=\"true\"/>\n",
+            "    <property name=\"Door.target\" type=\"file\" value=\"level.tmx\"/>\n",
+            "    <property name=\"Door.label\" type=\"string\" value=\"A &amp; &quot;B&quot;\"/>\n",
+            "   </properties>\n",
+        )));
+    }
+
+    #[test]
+    fn relative_and_absolute_paths() {
+        let cwd = std::env::current_dir().unwrap();
+        let atlas = cwd.join("game/art/tiles.png");
+        assert_eq!(
+            relative_path(Path::new("out/art/tiles.tsx"), &atlas),
+            "../../game/art/tiles.png"
+        );
+        assert_eq!(
+            relative_path(
+                &cwd.join("out/art/tiles.tsx"),
+                Path::new("./game/../game/art/tiles.png")
+            ),
+            "../../game/art/tiles.png"
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use crate::project::*;
+use std::collections::HashMap;
+
+// * ---------------------------------------------------------------------------------- USAGE --------------------------------------------------------------------------------- * //
+/// Which tiles of every atlas are referenced by maps or objects.
+/// AtlasRenderer draws the first tile, any other atlas property, tile properties included, may use the whole atlas.
+/// Frames of used animated tiles are used too.
+pub fn used_tiles(assets: &Assets) -> HashMap<Uuid, Vec<bool>> {
+    let mut used = assets
+        .atlases
+        .iter()
+        .map(|(uuid, atlas)| {
+            (
+                *uuid,
+                vec![false; atlas.width() as usize * atlas.height() as usize],
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+    for map in assets.maps.values() {
+        if let (Some(atlas), Some(used)) =
+            (assets.atlases.get(&map.atlas), used.get_mut(&map.atlas))
+        {
+            for tile in &map.data {
+                if *tile < TVec2::zeros() {
+                    continue;
+                }
+                if let Some(used) =
+                    used.get_mut(tile.x as usize + tile.y as usize * atlas.width() as usize)
+                {
+                    *used = true;
+                }
+            }
+        }
+
+        for object in map.objects.values() {
+            for (uuid, properties) in &object.components {
+                let Some(component) = assets.components.get(uuid) else {
+                    continue;
+                };
+                for (name, property_type) in &component.properties {
+                    if *property_type != Property::Atlas {
+                        continue;
+                    }
+                    let Some(used) = properties
+                        .get(name)
+                        .and_then(|value| Uuid::parse_str(value).ok())
+                        .and_then(|atlas| used.get_mut(&atlas))
+                    else {
+                        continue;
+                    };
+                    if *uuid == ATLAS_RENDERER_UUID {
+                        if let Some(first) = used.first_mut() {
+                            *first = true;
+                        }
+                    } else {
+                        used.fill(true);
+                    }
+                }
+            }
+        }
+    }
+
+    // Atlases referenced by tile properties may be used whole, like with other atlas properties
+    let mut referenced = Vec::new();
+    for atlas in assets.atlases.values() {
+        for (name, _) in atlas
+            .tile_properties
+            .iter()
+            .filter(|(_, property_type)| **property_type == Property::Atlas)
+        {
+            referenced.extend(
+                atlas
+                    .tile_values
+                    .values()
+                    .filter_map(|values| values.get(name))
+                    .cloned(),
+            );
+        }
+    }
+    for atlas in referenced {
+        if let Some(used) = Uuid::parse_str(&atlas)
+            .ok()
+            .and_then(|atlas| used.get_mut(&atlas))
+        {
+            used.fill(true);
+        }
+    }
+
+    // Animated tiles need all of their frames
+    for (uuid, atlas) in &assets.atlases {
+        let used = used.get_mut(uuid).unwrap();
+        for animation in &atlas.animations {
+            if used.get(animation.tile as usize) == Some(&true) {
+                for frame in &animation.frames {
+                    if let Some(used) = used.get_mut(*frame as usize) {
+                        *used = true;
+                    }
+                }
+            }
+        }
+    }
+    used
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::reader::Value;
+    use crate::export::test_project::*;
+
+    #[test]
+    fn merges_and_strips_tiles() {
+        let mut assets = test_project();
+        // Red, blue, red, transparent
+        assets.atlases.values_mut().next().unwrap().image =
+            image::RgbaImage::from_fn(16, 4, |x, _| match x / 4 {
+                0 | 2 => image::Rgba([255, 0, 0, 255]),
+                1 => image::Rgba([0, 0, 255, 255]),
+                _ => image::Rgba([0, 0, 0, 0]),
+            });
+        // Only keep the AtlasRenderer, so the atlas isn't fully used
+        for map in assets.maps.values_mut() {
+            map.objects
+                .retain(|_, object| object.components.contains_key(&ATLAS_RENDERER_UUID));
+        }
+
+        assets.export_settings.strip_unused_tiles = true;
+        let file = export_and_read(&mut assets);
+        assert_eq!(
+            file.atlases[0].tiles,
+            vec![RED.repeat(16), BLUE.repeat(16), RED.repeat(16)]
+        );
+        assert_eq!(file.maps[0].tiles, vec![0, 1, 0xFFFF, 0xFFFF, 0xFFFF, 2]);
+
+        assets.export_settings.deduplicate_tiles = true;
+        let file = export_and_read(&mut assets);
+        assert_eq!(file.atlases[0].tiles, vec![RED.repeat(16), BLUE.repeat(16)]);
+        assert_eq!(file.maps[0].tiles, vec![0, 1, 0xFFFF, 0xFFFF, 0xFFFF, 0]);
+    }
+
+    #[test]
+    fn keeps_atlases_of_tile_properties() {
+        let mut assets = test_project();
+        for map in assets.maps.values_mut() {
+            map.objects
+                .retain(|_, object| object.components.contains_key(&ATLAS_RENDERER_UUID));
+        }
+        // No map uses the items, only the tiles that drop them
+        let items = Uuid::from_u128(20);
+        let mut atlas = Atlas::new(
+            Path::new("/test/items.png"),
+            image::RgbaImage::from_pixel(12, 4, image::Rgba([0, 0, 255, 255])),
+            U16Vec2::new(4, 4),
+        );
+        atlas.export_id = 1;
+        assets.atlases.insert(items, atlas);
+        let tiles = assets.atlases.get_mut(&ATLAS).unwrap();
+        tiles
+            .tile_properties
+            .insert("drop".to_owned(), Property::Atlas);
+        for tile in 0..3 {
+            tiles.tile_values.insert(
+                tile,
+                [("drop".to_owned(), items.to_string())]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+
+        assets.export_settings.strip_unused_tiles = true;
+        let file = export_and_read(&mut assets);
+        assert_eq!(file.atlases[0].tiles.len(), 3);
+        assert_eq!(file.atlases[1].tiles, vec![BLUE.repeat(16); 3]);
+    }
+
+    #[test]
+    fn unset_references_are_reproducible() {
+        // Tiles drop items or gems, the door has no sprite
+        let project = || {
+            let mut assets = test_project();
+            let tiles = assets.atlases.get_mut(&ATLAS).unwrap();
+            tiles
+                .tile_properties
+                .insert("drop".to_owned(), Property::Atlas);
+            for (tile, drop) in [(0, 20), (1, 21), (2, 21)] {
+                tiles.tile_values.insert(
+                    tile,
+                    [("drop".to_owned(), Uuid::from_u128(drop).to_string())]
+                        .into_iter()
+                        .collect(),
+                );
+            }
+            for (uuid, export_id) in [(20, 1), (21, 2)] {
+                let mut atlas = Atlas::new(
+                    Path::new(&format!("/test/{}.png", uuid)),
+                    image::RgbaImage::from_pixel(8, 4, image::Rgba([0, 0, 255, 255])),
+                    U16Vec2::new(4, 4),
+                );
+                atlas.export_id = export_id;
+                assets.atlases.insert(Uuid::from_u128(uuid), atlas);
+            }
+            for map in assets.maps.values_mut() {
+                for object in map.objects.values_mut() {
+                    if let Some(door) = object.components.get_mut(&DOOR) {
+                        door.remove("sprite");
+                    }
+                }
+            }
+            assets.export_settings.strip_unused_tiles = true;
+            assets
+        };
+
+        let data = export(&mut project());
+        for _ in 0..4 {
+            assert_eq!(export(&mut project()), data);
+        }
+        let mut assets = project();
+        let file = export_and_read(&mut assets);
+        assert_eq!(file.atlases[1].tiles.len(), 2);
+        assert_eq!(file.atlases[2].tiles.len(), 2);
+        assert_eq!(
+            file.maps[0].objects[0].components[0].values[1],
+            ("sprite".to_owned(), Value::Atlas(0xFFFF))
+        );
+    }
+
+    #[test]
+    fn animated_tiles() {
+        let mut assets = test_project();
+        // Red, blue, red, transparent, the transparent tile is only used by the blue animation
+        let atlas = assets.atlases.get_mut(&ATLAS).unwrap();
+        atlas.image = image::RgbaImage::from_fn(16, 4, |x, _| match x / 4 {
+            0 | 2 => image::Rgba([255, 0, 0, 255]),
+            1 => image::Rgba([0, 0, 255, 255]),
+            _ => image::Rgba([0, 0, 0, 0]),
+        });
+        let animation = |tile, frames: &[u16], frame_time| Animation {
+            tile,
+            frames: frames.to_vec(),
+            frame_time,
+        };
+        atlas.animations = vec![animation(0, &[0, 2], 8), animation(1, &[1, 3], 4)];
+        assert_eq!(
+            atlas.animated_tile(U32Vec2::new(1, 0), 3),
+            U32Vec2::new(1, 0)
+        );
+        assert_eq!(
+            atlas.animated_tile(U32Vec2::new(1, 0), 4),
+            U32Vec2::new(3, 0)
+        );
+        assert_eq!(
+            atlas.animated_tile(U32Vec2::new(1, 0), 8),
+            U32Vec2::new(1, 0)
+        );
+        assert_eq!(
+            atlas.animated_tile(U32Vec2::new(2, 0), 4),
+            U32Vec2::new(2, 0)
+        );
+        // A frame past the last tile, as after the image shrank, shows the animated tile
+        atlas.animations.push(animation(2, &[2, 9], 1));
+        assert_eq!(
+            atlas.animated_tile(U32Vec2::new(2, 0), 1),
+            U32Vec2::new(2, 0)
+        );
+        atlas.animations.pop();
+        for map in assets.maps.values_mut() {
+            map.objects
+                .retain(|_, object| object.components.contains_key(&ATLAS_RENDERER_UUID));
+        }
+
+        // The animated red tile keeps its own copy
+        assets.export_settings.strip_unused_tiles = true;
+        assets.export_settings.deduplicate_tiles = true;
+        let file = export_and_read(&mut assets);
+        assert_eq!(
+            file.atlases[0].tiles,
+            vec![
+                RED.repeat(16),
+                BLUE.repeat(16),
+                RED.repeat(16),
+                TRANSPARENT.repeat(16)
+            ]
+        );
+        assert_eq!(
+            file.atlases[0].animations,
+            vec![animation(0, &[0, 2], 8), animation(1, &[1, 3], 4)]
+        );
+        assert_eq!(file.maps[0].tiles, vec![0, 1, 0xFFFF, 0xFFFF, 0xFFFF, 2]);
+
+        // Without the blue tile on the map, its animation and frames are dropped
+        let level = assets
+            .maps
+            .values_mut()
+            .find(|map| map.export_id == 0)
+            .unwrap();
+        level.set_tile(TVec2::new(1, 0), TVec2::new(-1, -1));
+        let file = export_and_read(&mut assets);
+        assert_eq!(file.atlases[0].tiles.len(), 2);
+        assert_eq!(file.atlases[0].animations, vec![animation(0, &[0, 1], 8)]);
+    }
+}
@@ -0,0 +1,788 @@
+use crate::export::codegen::asset_name;
+use crate::export::pixel_format::Transparency;
+use crate::project::*;
+use itertools::Itertools;
+
+// * -------------------------------------------------------------------------------- PROBLEMS -------------------------------------------------------------------------------- * //
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    /// Exported anyway, but probably not what was meant
+    Warning,
+    /// Prevents the export
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct Problem {
+    pub severity: Severity,
+    pub asset: PathBuf,
+    pub message: String,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {:?}: {}", self.severity, self.asset, self.message)
+    }
+}
+
+struct Problems(Vec<Problem>);
+
+impl Problems {
+    fn warning(&mut self, asset: &Path, message: String) {
+        self.0.push(Problem {
+            severity: Severity::Warning,
+            asset: asset.to_path_buf(),
+            message,
+        });
+    }
+
+    fn error(&mut self, asset: &Path, message: String) {
+        self.0.push(Problem {
+            severity: Severity::Error,
+            asset: asset.to_path_buf(),
+            message,
+        });
+    }
+}
+
+/// Everything that would fail the export, or be exported differently from what the editor shows
+pub fn validate(assets: &Assets) -> Vec<Problem> {
+    let mut problems = Problems(Vec::new());
+    if let Err(err) = assets.check_export_ids() {
+        problems.error(&assets.path, err.to_string());
+    }
+    let atlases = assets
+        .atlases
+        .values()
+        .map(|atlas| (atlas.export_id, &atlas.path));
+    validate_export_ids("atlas", atlases, &mut problems);
+    let maps = assets.maps.values().map(|map| (map.export_id, &map.path));
+    validate_export_ids("map", maps, &mut problems);
+    for atlas in assets.atlases.values().sorted_by_key(|atlas| &atlas.path) {
+        validate_atlas(assets, atlas, &mut problems);
+    }
+    for map in assets.maps.values().sorted_by_key(|map| &map.path) {
+        validate_map(assets, map, &mut problems);
+    }
+    problems.0
+}
+
+/// Every ID up to the highest one gets a record, so a stray high ID fills data.dat with empty ones
+fn validate_export_ids<'a>(
+    kind: &str,
+    assets: impl Iterator<Item = (u16, &'a PathBuf)>,
+    problems: &mut Problems,
+) {
+    let assets = assets.collect::<Vec<_>>();
+    let Some((highest, path)) = assets.iter().max() else {
+        return;
+    };
+    let unused = (*highest as usize + 1).saturating_sub(assets.len());
+    if unused > MAX_UNUSED_EXPORT_IDS.max(assets.len()) {
+        problems.warning(
+            path,
+            format!(
+                "Export ID {} is the highest of {} {}s, the {} unused IDs below it are exported as empty records",
+                highest,
+                assets.len(),
+                kind,
+                unused
+            ),
+        );
+    }
+}
+
+/// Unused export IDs that are expected from deleting assets
+const MAX_UNUSED_EXPORT_IDS: usize = 16;
+
+/// Fails if any of the problems prevents the export
+pub fn check(problems: &[Problem]) -> Result<()> {
+    let errors = problems
+        .iter()
+        .filter(|problem| problem.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        bail!("Can't export, validation found {} errors!", errors);
+    }
+    Ok(())
+}
+
+// * --------------------------------------------------------------------------------- ATLASES -------------------------------------------------------------------------------- * //
+fn validate_atlas(assets: &Assets, atlas: &Atlas, problems: &mut Problems) {
+    if atlas.tile_size.x == 0 || atlas.tile_size.y == 0 {
+        problems.error(&atlas.path, "Tile size is zero".to_owned());
+        return;
+    }
+    let (width, height) = atlas.image.dimensions();
+    // Pixels right of and below the last tile, a margin there is expected
+    let end = atlas
+        .tile_pos(U32Vec2::new(atlas.width() as u32, atlas.height() as u32))
+        .map(|end| end.saturating_sub(atlas.spacing as u32));
+    let rest = U32Vec2::new(width, height).zip_map(&end, |size, end| size.saturating_sub(end));
+    if rest
+        .iter()
+        .any(|rest| *rest != 0 && *rest != atlas.margin as u32)
+    {
+        let layout = if atlas.margin == 0 && atlas.spacing == 0 {
+            format!(
+                "which is not a multiple of the {}x{} tile size",
+                atlas.tile_size.x, atlas.tile_size.y
+            )
+        } else {
+            format!(
+                "which doesn't fit a whole number of {}x{} tiles with a margin of {} and spacing of {}",
+                atlas.tile_size.x, atlas.tile_size.y, atlas.margin, atlas.spacing
+            )
+        };
+        problems.warning(
+            &atlas.path,
+            format!(
+                "Image is {}x{}, {}, the pixels past the last full tile are not exported",
+                width, height, layout
+            ),
+        );
+    }
+    let tiles = atlas.width() as u32 * atlas.height() as u32;
+    if tiles >= 0xFFFF {
+        problems.error(
+            &atlas.path,
+            format!(
+                "Atlas has {} tiles, tile indices only go up to {}",
+                tiles,
+                0xFFFF - 1
+            ),
+        );
+    }
+
+    for (index, animation) in atlas.animations.iter().enumerate() {
+        let at = format!("Animation of tile {}", animation.tile);
+        if atlas.animations[..index]
+            .iter()
+            .any(|other| other.tile == animation.tile)
+        {
+            problems.error(&atlas.path, format!("{} is defined twice", at));
+        }
+        if animation.frames.is_empty() || animation.frame_time == 0 {
+            problems.error(
+                &atlas.path,
+                format!("{} has no frames or a frame time of 0", at),
+            );
+        }
+        let outside = std::iter::once(&animation.tile)
+            .chain(&animation.frames)
+            .filter(|tile| **tile as u32 >= tiles)
+            .count();
+        if outside > 0 {
+            problems.error(
+                &atlas.path,
+                format!(
+                    "{} uses {} tiles outside of the atlas, which has {} tiles",
+                    at, outside, tiles
+                ),
+            );
+        }
+    }
+
+    // Tiles without an atlas or map would export 0xFFFF, which is likely a mistake
+    for (property, property_type) in atlas
+        .tile_properties
+        .iter()
+        .filter(|(_, property_type)| matches!(property_type, Property::Atlas | Property::Map))
+    {
+        let unset = (0..tiles as u16)
+            .filter(|tile| {
+                atlas
+                    .tile_values
+                    .get(tile)
+                    .and_then(|values| values.get(property))
+                    .is_none()
+            })
+            .count();
+        if unset > 0 {
+            problems.error(
+                &atlas.path,
+                format!(
+                    "Tile property {} is {:?}, but {} tiles have no value",
+                    property, property_type, unset
+                ),
+            );
+        }
+    }
+
+    for (tile, values) in &atlas.tile_values {
+        if *tile as u32 >= tiles {
+            problems.warning(
+                &atlas.path,
+                format!(
+                    "Tile {} has property values, but is outside of the atlas, which has {} tiles",
+                    tile, tiles
+                ),
+            );
+            continue;
+        }
+        for (property, property_type) in &atlas.tile_properties {
+            if let Some(message) = values
+                .get(property)
+                .and_then(|value| validate_value(assets, property_type, value))
+            {
+                problems.error(
+                    &atlas.path,
+                    format!("Tile {}: {} {}", tile, property, message),
+                );
+            }
+        }
+    }
+
+    for (tile, collisions) in &atlas.collisions {
+        if *tile as u32 >= tiles {
+            problems.warning(
+                &atlas.path,
+                format!(
+                    "Tile {} has collision shapes, but is outside of the atlas, which has {} tiles",
+                    tile, tiles
+                ),
+            );
+            continue;
+        }
+        if collisions.len() > 255 {
+            problems.error(
+                &atlas.path,
+                format!(
+                    "Tile {} has {} collision shapes, at most 255 are exported",
+                    tile,
+                    collisions.len()
+                ),
+            );
+        }
+        for collision in collisions {
+            let (corners, valid) = match &collision.shape {
+                CollisionShape::Rect { pos, size } => (
+                    vec![pos.casted::<u32>(), pos.casted() + size.casted::<u32>()],
+                    size.x > 0 && size.y > 0,
+                ),
+                CollisionShape::Polygon(points) => (
+                    points.iter().map(|point| point.casted()).collect(),
+                    (3..=255).contains(&points.len()),
+                ),
+            };
+            if !valid {
+                problems.error(
+                    &atlas.path,
+                    format!(
+                        "Tile {} has an empty rectangle or a polygon without 3 to 255 corners",
+                        tile
+                    ),
+                );
+            }
+            if corners.iter().any(|corner| {
+                corner.x > atlas.tile_size.x as u32 || corner.y > atlas.tile_size.y as u32
+            }) {
+                problems.error(
+                    &atlas.path,
+                    format!("Tile {} has a collision shape outside of the tile", tile),
+                );
+            } else if corners
+                .iter()
+                .any(|corner| corner.x > 255 || corner.y > 255)
+            {
+                problems.error(
+                    &atlas.path,
+                    format!(
+                        "Tile {} has a collision shape corner past pixel 255, corners are exported as bytes",
+                        tile
+                    ),
+                );
+            }
+        }
+    }
+
+    let settings = &assets.export_settings;
+    let pixel_format = atlas.pixel_format.unwrap_or(settings.pixel_format);
+    let transparency = atlas.transparency.unwrap_or(settings.transparency);
+    if let Err(err) = pixel_format.check(atlas.tile_size, transparency) {
+        problems.error(&atlas.path, err.to_string());
+        return;
+    }
+    if !pixel_format.is_indexed() && atlas.color_limit.is_none() {
+        return;
+    }
+
+    let limit = crate::export::quantize::color_limit(atlas, pixel_format).unwrap_or_default();
+    if let (true, Some(colors)) = (pixel_format.is_indexed(), pixel_format.colors()) {
+        if atlas
+            .color_limit
+            .is_some_and(|limit| limit as usize > colors)
+        {
+            problems.error(
+                &atlas.path,
+                format!(
+                    "Color limit is {}, but {:?} has only {} colors",
+                    atlas.color_limit.unwrap_or_default(),
+                    pixel_format,
+                    colors
+                ),
+            );
+        }
+        if let Transparency::ColorKey(key) = transparency {
+            if key as usize >= limit {
+                problems.error(
+                    &atlas.path,
+                    format!(
+                        "Color key {} is not a palette index below the color limit of {}",
+                        key, limit
+                    ),
+                );
+            }
+        }
+    }
+
+    let quantized = crate::export::quantize::quantize_atlas(atlas, settings);
+    if let Some(limit) = quantized.limit.filter(|limit| quantized.colors > *limit) {
+        problems.warning(
+            &atlas.path,
+            format!(
+                "Atlas has {} colors, {} over the limit, {} tiles change when the colors are reduced",
+                quantized.colors,
+                quantized.colors - limit,
+                quantized.changed_tiles.iter().filter(|changed| **changed).count()
+            ),
+        );
+    }
+}
+
+// * ---------------------------------------------------------------------------------- MAPS ---------------------------------------------------------------------------------- * //
+fn validate_map(assets: &Assets, map: &Map, problems: &mut Problems) {
+    match assets.atlases.get(&map.atlas) {
+        Some(atlas) if atlas.tile_size.x > 0 && atlas.tile_size.y > 0 => {
+            let tiles = atlas.width() as u32 * atlas.height() as u32;
+            let outside = map
+                .data
+                .iter()
+                .filter(|tile| {
+                    **tile >= TVec2::zeros()
+                        && (tile.x as u16 >= atlas.width()
+                            || tile.x as u32 + tile.y as u32 * atlas.width() as u32 >= tiles)
+                })
+                .count();
+            if outside > 0 {
+                problems.error(
+                    &map.path,
+                    format!(
+                        "{} tiles are outside of atlas {:?}, which has {}x{} tiles",
+                        outside,
+                        atlas.path,
+                        atlas.width(),
+                        atlas.height()
+                    ),
+                );
+            }
+        }
+        Some(_) => (),
+        None => problems.error(&map.path, "Map's atlas is not in the project".to_owned()),
+    }
+
+    if map.objects.len() > u16::MAX as usize {
+        problems.error(
+            &map.path,
+            format!(
+                "Map has {} objects, at most {} can be exported",
+                map.objects.len(),
+                u16::MAX
+            ),
+        );
+    }
+    let bounds = assets.atlases.get(&map.atlas).map(|atlas| {
+        map.size
+            .casted::<i32>()
+            .component_mul(&atlas.tile_size.casted())
+    });
+    for object in map
+        .objects
+        .values()
+        .sorted_by_key(|object| (object.pos.y, object.pos.x))
+    {
+        let at = format!("Object at ({}, {})", object.pos.x, object.pos.y);
+        if let Some(bounds) = bounds {
+            if object.pos.x < 0
+                || object.pos.y < 0
+                || object.pos.x >= bounds.x
+                || object.pos.y >= bounds.y
+            {
+                problems.warning(
+                    &map.path,
+                    format!(
+                        "{} is outside of the map, which is {}x{} pixels",
+                        at, bounds.x, bounds.y
+                    ),
+                );
+            }
+        }
+        for (uuid, instance) in object
+            .components
+            .iter()
+            .sorted_by_key(|(uuid, _)| assets.components.get(uuid).map(|c| c.export_id))
+        {
+            let Some(component) = assets.components.get(uuid) else {
+                problems.error(
+                    &map.path,
+                    format!(
+                        "{} uses component {}, which is not in the project",
+                        at, uuid
+                    ),
+                );
+                continue;
+            };
+            let name = asset_name(assets, &component.path);
+            for (property, property_type) in &component.properties {
+                let Some(value) = instance.get(property) else {
+                    problems.warning(
+                        &map.path,
+                        format!(
+                            "{} has no value for {}.{}, the default is exported",
+                            at, name, property
+                        ),
+                    );
+                    continue;
+                };
+                if let Some(message) = validate_value(assets, property_type, value) {
+                    problems.error(
+                        &map.path,
+                        format!("{}: {}.{} {}", at, name, property, message),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Why the value can't be exported as it is
+fn validate_value(assets: &Assets, property_type: &Property, value: &str) -> Option<String> {
+    match property_type {
+        Property::String if value.len() > u16::MAX as usize => Some(format!(
+            "is {} bytes long, at most {} can be exported",
+            value.len(),
+            u16::MAX
+        )),
+        Property::String => None,
+        Property::Atlas | Property::Map
+            if Uuid::parse_str(value).is_ok_and(|uuid| uuid.is_nil()) =>
+        {
+            Some("is not set".to_owned())
+        }
+        Property::Atlas | Property::Map => {
+            let exists = Uuid::parse_str(value).is_ok_and(|uuid| {
+                if *property_type == Property::Atlas {
+                    assets.atlases.contains_key(&uuid)
+                } else {
+                    assets.maps.contains_key(&uuid)
+                }
+            });
+            (!exists).then(|| format!("points to {:?}, which is not in the project", value))
+        }
+        Property::F32 => value
+            .parse::<f32>()
+            .is_err()
+            .then(|| format!("is {:?}, which is not a number", value)),
+        _ => {
+            let fixed = property_type.fix_value(std::iter::empty(), std::iter::empty(), value);
+            (fixed != value).then(|| {
+                format!(
+                    "is {:?}, which doesn't fit in {:?} and would be exported as {}",
+                    value, property_type, fixed
+                )
+            })
+        }
+    }
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::pixel_format::PixelFormat;
+    use crate::view::editor_view::Object;
+
+    #[test]
+    fn finds_problems() {
+        let mut assets = Assets::new(Path::new("/game"));
+        let atlas = Uuid::new_v4();
+        assets.atlases.insert(
+            atlas,
+            Atlas::new(
+                Path::new("/game/tiles.png"),
+                image::RgbaImage::new(20, 8),
+                U16Vec2::new(8, 8),
+            ),
+        );
+        let tiles = assets.atlases.get_mut(&atlas).unwrap();
+        tiles.animations.push(Animation {
+            tile: 1,
+            frames: vec![1, 5],
+            frame_time: 8,
+        });
+        tiles
+            .tile_properties
+            .insert("damage".to_owned(), Property::I8);
+        for (tile, damage) in [(0, "2"), (1, "-200"), (7, "1")] {
+            tiles.tile_values.insert(
+                tile,
+                [("damage".to_owned(), damage.to_owned())]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+        let collision = |shape| Collision {
+            shape,
+            one_way: false,
+        };
+        tiles.collisions.insert(
+            0,
+            vec![collision(CollisionShape::Rect {
+                pos: U16Vec2::new(4, 4),
+                size: U16Vec2::new(8, 2),
+            })],
+        );
+        tiles.collisions.insert(
+            1,
+            vec![collision(CollisionShape::Polygon(vec![
+                U16Vec2::new(0, 8),
+                U16Vec2::new(8, 0),
+            ]))],
+        );
+        // Black, white and 2 reds with a limit of 3 colors, one taken by the key
+        let mut colorful = Atlas::new(
+            Path::new("/game/colorful.png"),
+            image::RgbaImage::from_fn(4, 2, |x, _| match x {
+                0 => image::Rgba([0, 0, 0, 255]),
+                1 => image::Rgba([255, 255, 255, 255]),
+                2 => image::Rgba([255, 0, 0, 255]),
+                _ => image::Rgba([200, 0, 0, 255]),
+            }),
+            U16Vec2::new(2, 2),
+        );
+        colorful.export_id = 1;
+        colorful.pixel_format = Some(PixelFormat::Indexed4);
+        colorful.transparency = Some(Transparency::ColorKey(3));
+        colorful.color_limit = Some(3);
+        assets.atlases.insert(Uuid::new_v4(), colorful);
+        let door = Uuid::new_v4();
+        let mut component = Component::new(Path::new("/game/Door.cmp"));
+        component.export_id = 2;
+        for (name, property_type) in [
+            ("target", Property::Map),
+            ("count", Property::U8),
+            ("speed", Property::F32),
+            ("label", Property::String),
+        ] {
+            component.properties.insert(name.to_owned(), property_type);
+        }
+        assets.components.insert(door, component);
+
+        let mut map = Map::new(Path::new("/game/level.map"), U16Vec2::new(2, 2), atlas);
+        map.set_tile(TVec2::new(0, 0), TVec2::new(1, 0));
+        map.set_tile(TVec2::new(1, 0), TVec2::new(2, 0));
+        map.set_tile(TVec2::new(1, 1), TVec2::new(0, 1));
+        let instance = |count: &str, label: String| {
+            [
+                ("target".to_owned(), Uuid::nil().to_string()),
+                ("count".to_owned(), count.to_owned()),
+                ("speed".to_owned(), "1.5".to_owned()),
+                ("label".to_owned(), label),
+            ]
+            .into_iter()
+            .collect()
+        };
+        map.objects.insert(
+            Uuid::new_v4(),
+            Object::new(
+                TVec2::new(4, 4),
+                &[(door, instance("300", "a".repeat(70000)))],
+            ),
+        );
+        map.objects.insert(
+            Uuid::new_v4(),
+            Object::new(TVec2::new(40, -4), &[(Uuid::new_v4(), Default::default())]),
+        );
+        assets.maps.insert(Uuid::new_v4(), map);
+        let mut lost = Map::new(
+            Path::new("/game/lost.map"),
+            U16Vec2::new(1, 1),
+            Uuid::new_v4(),
+        );
+        lost.export_id = 1;
+        assets.maps.insert(Uuid::new_v4(), lost);
+
+        let problems = validate(&assets)
+            .into_iter()
+            .map(|problem| (problem.severity, problem.asset, problem.message))
+            .collect::<Vec<_>>();
+        let expected = [
+            (
+                Severity::Error,
+                "/game/colorful.png",
+                "Color key 3 is not a palette index below the color limit of 3",
+            ),
+            (
+                Severity::Warning,
+                "/game/colorful.png",
+                "Atlas has 4 colors, 2 over the limit, 2 tiles change",
+            ),
+            (
+                Severity::Warning,
+                "/game/tiles.png",
+                "Image is 20x8, which is not a multiple of the 8x8 tile size",
+            ),
+            (
+                Severity::Error,
+                "/game/tiles.png",
+                "Animation of tile 1 uses 1 tiles outside of the atlas, which has 2 tiles",
+            ),
+            (
+                Severity::Error,
+                "/game/tiles.png",
+                "Tile 1: damage is \"-200\", which doesn't fit in I8",
+            ),
+            (
+                Severity::Warning,
+                "/game/tiles.png",
+                "Tile 7 has property values, but is outside of the atlas",
+            ),
+            (
+                Severity::Error,
+                "/game/tiles.png",
+                "Tile 0 has a collision shape outside of the tile",
+            ),
+            (
+                Severity::Error,
+                "/game/tiles.png",
+                "Tile 1 has an empty rectangle or a polygon without 3 to 255 corners",
+            ),
+            (
+                Severity::Error,
+                "/game/level.map",
+                "2 tiles are outside of atlas",
+            ),
+            (
+                Severity::Warning,
+                "/game/level.map",
+                "Object at (40, -4) is outside of the map",
+            ),
+            (
+                Severity::Error,
+                "/game/level.map",
+                "Object at (40, -4) uses component",
+            ),
+            (
+                Severity::Error,
+                "/game/level.map",
+                "Object at (4, 4): Door.target is not set",
+            ),
+            (
+                Severity::Error,
+                "/game/level.map",
+                "Object at (4, 4): Door.count is \"300\", which doesn't fit in U8",
+            ),
+            (
+                Severity::Error,
+                "/game/level.map",
+                "Object at (4, 4): Door.label is 70000 bytes long",
+            ),
+            (
+                Severity::Error,
+                "/game/lost.map",
+                "Map's atlas is not in the project",
+            ),
+        ];
+        assert_eq!(problems.len(), expected.len(), "{:#?}", problems);
+        for (problem, (severity, asset, message)) in problems.iter().zip(expected) {
+            assert_eq!(problem.0, severity, "{:?}", problem);
+            assert_eq!(problem.1, Path::new(asset), "{:?}", problem);
+            assert!(problem.2.starts_with(message), "{:?}", problem);
+        }
+
+        let mut data = Vec::new();
+        let err = assets.export_data(&mut data).unwrap_err().to_string();
+        assert_eq!(err, "Can't export, validation found 11 errors!");
+        assert!(data.is_empty());
+        assert_eq!(assets.export_problems.len(), expected.len());
+    }
+
+    #[test]
+    fn sparse_export_ids() {
+        let mut assets = Assets::new(Path::new("/game"));
+        let atlas = Uuid::new_v4();
+        assets.atlases.insert(
+            atlas,
+            Atlas::new(
+                Path::new("/game/tiles.png"),
+                image::RgbaImage::new(8, 8),
+                U16Vec2::new(8, 8),
+            ),
+        );
+        let mut map = Map::new(Path::new("/game/level.map"), U16Vec2::new(1, 1), atlas);
+        map.export_id = 16;
+        assets.maps.insert(Uuid::new_v4(), map);
+        assert!(validate(&assets).is_empty());
+
+        let mut map = Map::new(Path::new("/game/stray.map"), U16Vec2::new(1, 1), atlas);
+        map.export_id = 5000;
+        assets.maps.insert(Uuid::new_v4(), map);
+        let problems = validate(&assets);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].severity, Severity::Warning);
+        assert_eq!(problems[0].asset, Path::new("/game/stray.map"));
+        assert_eq!(
+            problems[0].message,
+            "Export ID 5000 is the highest of 2 maps, the 4999 unused IDs below it are exported as empty records"
+        );
+    }
+
+    #[test]
+    fn unset_tile_references() {
+        let mut assets = Assets::new(Path::new("/game"));
+        let atlas = Uuid::new_v4();
+        let mut tiles = Atlas::new(
+            Path::new("/game/tiles.png"),
+            image::RgbaImage::new(16, 8),
+            U16Vec2::new(8, 8),
+        );
+        tiles
+            .tile_properties
+            .insert("exit".to_owned(), Property::Map);
+        tiles
+            .tile_properties
+            .insert("drop".to_owned(), Property::Atlas);
+        tiles.tile_values.insert(
+            0,
+            [("drop".to_owned(), atlas.to_string())]
+                .into_iter()
+                .collect(),
+        );
+        assets.atlases.insert(atlas, tiles);
+
+        // Missing and dangling references become nil, even when there are others to choose
+        for value in ["", &Uuid::new_v4().to_string()] {
+            assert_eq!(
+                Property::Atlas.fix_value([atlas].into_iter(), std::iter::empty(), value),
+                Uuid::nil().to_string()
+            );
+        }
+        assert_eq!(
+            Property::Atlas.fix_value([atlas].into_iter(), std::iter::empty(), &atlas.to_string()),
+            atlas.to_string()
+        );
+
+        let problems = validate(&assets)
+            .into_iter()
+            .map(|problem| problem.message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            [
+                "Tile property exit is Map, but 2 tiles have no value",
+                "Tile property drop is Atlas, but 1 tiles have no value",
+            ]
+        );
+        assert!(assets.export_data(&mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,309 @@
+use crate::export::tiled::absolute;
+use crate::project::*;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+
+// * --------------------------------------------------------------------------------- WATCHER -------------------------------------------------------------------------------- * //
+/// Files that change the exported data
+pub const WATCHED_EXTENSIONS: [&str; 11] = [
+    "png", "bmp", "gif", "tga", "qoi", "webp", "ase", "aseprite", "atl", "map", "cmp",
+];
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Polls modification times and sizes of the watched files in the project folder
+pub struct Watcher {
+    pub path: PathBuf,
+    extensions: &'static [&'static str],
+    files: HashMap<PathBuf, (SystemTime, u64)>,
+    last_poll: Instant,
+}
+
+impl Watcher {
+    pub fn new(path: &Path) -> Self {
+        Self::with_extensions(path, &WATCHED_EXTENSIONS)
+    }
+
+    /// Watches only files with the given extensions
+    pub fn with_extensions(path: &Path, extensions: &'static [&'static str]) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            extensions,
+            files: scan(path, extensions),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Files added, removed or modified since the last poll, sorted.
+    /// Scans at most once per `POLL_INTERVAL`, returns nothing in between.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let files = scan(&self.path, self.extensions);
+        let mut changed = files
+            .iter()
+            .filter(|(path, stamp)| self.files.get(*path) != Some(stamp))
+            .map(|(path, _)| path.clone())
+            .chain(
+                self.files
+                    .keys()
+                    .filter(|path| !files.contains_key(*path))
+                    .cloned(),
+            )
+            .collect::<Vec<_>>();
+        changed.sort();
+        self.files = files;
+        changed
+    }
+}
+
+fn scan(path: &Path, extensions: &[&str]) -> HashMap<PathBuf, (SystemTime, u64)> {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && entry
+                    .path()
+                    .extension()
+                    .is_some_and(|extension| extensions.iter().any(|watched| extension == *watched))
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((
+                entry.path().to_path_buf(),
+                (metadata.modified().ok()?, metadata.len()),
+            ))
+        })
+        .collect()
+}
+
+/// Loads the project from disk again and exports it to `output`, or data.dat in the project.
+/// Returns the validation problems even if the export fails, and the data file on success.
+pub fn reexport(
+    project: &Path,
+    output: Option<&Path>,
+) -> (Vec<crate::export::validate::Problem>, Result<PathBuf>) {
+    let mut assets = match Assets::load(project) {
+        Ok(assets) => assets,
+        Err(err) => {
+            return (
+                Vec::new(),
+                Err(err.context(format!("Failed to load project {:?}!", project))),
+            )
+        }
+    };
+    let output = output.map_or_else(|| assets.path.join("data.dat"), Path::to_path_buf);
+    let result = assets
+        .export_to(&output)
+        .context(format!("Failed to export project {:?}!", project))
+        .map(|_| output);
+    (assets.export_problems, result)
+}
+
+/// Loads the images of the atlases among the changed files again, keeping their tile size and everything else.
+/// Returns the reloaded atlases, warnings about tiles that maps or the atlases themselves use and that moved or are gone,
+/// and an error for every image that failed to load. Atlases that fail keep their old image, the others are still reloaded.
+pub fn reload_atlases(
+    assets: &mut Assets,
+    changed: &[PathBuf],
+) -> (Vec<Uuid>, Vec<String>, Vec<anyhow::Error>) {
+    let mut reloaded = Vec::new();
+    let mut warnings = Vec::new();
+    let mut errors = Vec::new();
+    // The project path may be relative or spelled differently from the watched one
+    let changed = changed.iter().map(absolute).collect::<Vec<_>>();
+    for (uuid, atlas) in assets
+        .atlases
+        .iter_mut()
+        .filter(|(_, atlas)| changed.contains(&absolute(&atlas.path)))
+        .sorted_by(|a, b| a.1.path.cmp(&b.1.path))
+    {
+        let old_size = U16Vec2::new(atlas.width(), atlas.height());
+        match crate::view::atlas_view::load_image(&atlas.path) {
+            Ok(image) => atlas.image = image,
+            Err(err) => {
+                errors.push(err.context(format!("Failed to reload atlas image {:?}!", atlas.path)));
+                continue;
+            }
+        }
+        reloaded.push(*uuid);
+
+        let size = U16Vec2::new(atlas.width(), atlas.height());
+        if size == old_size {
+            continue;
+        }
+        let name = atlas.path.file_name().unwrap_or_default();
+        for map in assets.maps.values().filter(|map| map.atlas == *uuid) {
+            let outside = map
+                .data
+                .iter()
+                .filter(|tile| {
+                    **tile >= TVec2::zeros() && (tile.x as u16 >= size.x || tile.y as u16 >= size.y)
+                })
+                .count();
+            if outside > 0 {
+                warnings.push(format!(
+                    "{:?} is now {}x{} tiles instead of {}x{}, {} tiles of map {:?} are outside of it",
+                    name,
+                    size.x,
+                    size.y,
+                    old_size.x,
+                    old_size.y,
+                    outside,
+                    map.path.file_name().unwrap_or_default()
+                ));
+            }
+        }
+        if size.x != old_size.x
+            && !(atlas.animations.is_empty()
+                && atlas.tile_values.is_empty()
+                && atlas.collisions.is_empty())
+        {
+            warnings.push(format!(
+                "{:?} is now {} tiles wide instead of {}, its animations, tile properties and collision shapes \
+                 are stored by tile index and now belong to other tiles",
+                name, size.x, old_size.x
+            ));
+        }
+    }
+    (reloaded, warnings, errors)
+}
+
+/// Short description of the changed files for the status bar and the command line
+pub fn describe(project: &Path, changed: &[PathBuf]) -> String {
+    let first = changed
+        .first()
+        .map(|path| path.strip_prefix(project).unwrap_or(path).to_string_lossy())
+        .unwrap_or_default();
+    match changed.len() {
+        0 | 1 => format!("{:?}", first),
+        count => format!("{:?} and {} more files", first, count - 1),
+    }
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::temp_dir::temp_dir;
+
+    #[test]
+    fn detects_changes() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("levels")).unwrap();
+        std::fs::write(dir.join("door.cmp"), "{}").unwrap();
+        let mut watcher = Watcher::new(&dir);
+        let mut poll = || {
+            watcher.last_poll -= POLL_INTERVAL;
+            watcher.poll()
+        };
+        assert!(poll().is_empty());
+
+        std::fs::write(dir.join("levels/forest.map"), "{}").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not watched").unwrap();
+        assert_eq!(poll(), vec![dir.join("levels/forest.map")]);
+        assert!(poll().is_empty());
+
+        std::fs::write(dir.join("door.cmp"), "{\"properties\": {}}").unwrap();
+        std::fs::remove_file(dir.join("levels/forest.map")).unwrap();
+        assert_eq!(
+            poll(),
+            vec![dir.join("door.cmp"), dir.join("levels/forest.map")]
+        );
+
+        // Too early to scan again
+        std::fs::write(dir.join("tiles.png"), "").unwrap();
+        assert!(watcher.poll().is_empty());
+    }
+
+    #[test]
+    fn reloads_atlases() {
+        let dir = temp_dir();
+        let path = dir.join("tiles.png");
+        image::RgbaImage::new(8, 8).save(&path).unwrap();
+
+        let mut assets = Assets::new(&dir);
+        let atlas = Uuid::new_v4();
+        let mut tiles = Atlas::new(&path, image::RgbaImage::new(16, 8), U16Vec2::new(8, 8));
+        tiles.animations.push(Animation {
+            tile: 0,
+            frames: vec![0, 1],
+            frame_time: 8,
+        });
+        assets.atlases.insert(atlas, tiles);
+        let mut map = Map::new(&dir.join("level.map"), U16Vec2::new(2, 1), atlas);
+        map.set_tile(TVec2::new(0, 0), TVec2::new(0, 0));
+        map.set_tile(TVec2::new(1, 0), TVec2::new(1, 0));
+        assets.maps.insert(Uuid::new_v4(), map);
+
+        // Files that aren't atlas images are ignored
+        let (reloaded, _, _) = reload_atlases(&mut assets, &[dir.join("other.png")]);
+        assert!(reloaded.is_empty());
+        assert_eq!(assets.atlases[&atlas].image.width(), 16);
+
+        let changed = [dir.join("levels/../tiles.png")];
+        let (reloaded, warnings, errors) = reload_atlases(&mut assets, &changed);
+        assert!(errors.is_empty());
+        assert_eq!(reloaded, vec![atlas]);
+        assert_eq!(assets.atlases[&atlas].image.width(), 8);
+        assert_eq!(assets.atlases[&atlas].tile_size, U16Vec2::new(8, 8));
+        assert_eq!(
+            warnings,
+            vec![
+                "\"tiles.png\" is now 1x1 tiles instead of 2x1, 1 tiles of map \"level.map\" are outside of it",
+                "\"tiles.png\" is now 1 tiles wide instead of 2, its animations, tile properties and collision shapes \
+                 are stored by tile index and now belong to other tiles",
+            ]
+        );
+
+        // The map editor skips the tile that is gone instead of reading past the image
+        let (tiles, map) = (
+            &assets.atlases[&atlas],
+            assets.maps.values().next().unwrap(),
+        );
+        assert!(tiles.contains_tile(map.get_tile(TVec2::new(0, 0))));
+        assert!(!tiles.contains_tile(map.get_tile(TVec2::new(1, 0))));
+        let mut canvas = image::RgbaImage::new(16, 8);
+        for x in 0..2 {
+            let tile = map.get_tile(TVec2::new(x, 0));
+            if tiles.contains_tile(tile) {
+                tiles.draw_tile(
+                    &mut canvas,
+                    TVec2::new(x as i32 * 8, 0),
+                    tile.casted(),
+                    TVec2::new(8, 8),
+                );
+            }
+        }
+
+        // A selection of the tile that is gone moves back into the atlas
+        let mut view = AtlasView::new(atlas);
+        view.selection_pos = U32Vec2::new(1, 0);
+        view.selection_size = U32Vec2::new(1, 1);
+        view.clamp_selection(tiles);
+        assert_eq!(tiles.selected_tiles(&view), vec![0]);
+
+        // Same size, nothing to warn about
+        let (_, warnings, _) = reload_atlases(&mut assets, &changed);
+        assert!(warnings.is_empty());
+
+        // A broken image keeps its old pixels and doesn't stop the other atlases from reloading
+        let broken = dir.join("broken.png");
+        std::fs::write(&broken, "not an image").unwrap();
+        let other = Uuid::new_v4();
+        assets.atlases.insert(
+            other,
+            Atlas::new(&broken, image::RgbaImage::new(8, 8), U16Vec2::new(8, 8)),
+        );
+        image::RgbaImage::new(16, 16).save(&path).unwrap();
+        let (reloaded, _, errors) = reload_atlases(&mut assets, &[broken, path]);
+        assert_eq!(reloaded, vec![atlas]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(assets.atlases[&atlas].image.height(), 16);
+        assert_eq!(assets.atlases[&other].image.width(), 8);
+    }
+}
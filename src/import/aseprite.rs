@@ -0,0 +1,681 @@
+use crate::export::layout::*;
+use crate::project::*;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::io::Read;
+
+// Chunks are described in https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md
+const FILE_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const LAYER_CHUNK: u16 = 0x2004;
+const CEL_CHUNK: u16 = 0x2005;
+const TAGS_CHUNK: u16 = 0x2018;
+const PALETTE_CHUNK: u16 = 0x2019;
+
+pub fn is_aseprite(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "ase" || extension == "aseprite")
+}
+
+// * -------------------------------------------------------------------------------- ASEPRITE -------------------------------------------------------------------------------- * //
+/// Sprite of an Aseprite file, flattened, with its frames side by side from left to right
+pub struct Aseprite {
+    pub image: image::RgbaImage,
+    pub frame_size: U16Vec2,
+    /// `None` if the sprite has no grid
+    pub grid: Option<U16Vec2>,
+    /// Milliseconds every frame is shown for
+    pub durations: Vec<u16>,
+    pub tags: Vec<Tag>,
+    /// Everything that could not be flattened as it is
+    pub warnings: Vec<String>,
+}
+
+pub struct Tag {
+    pub name: String,
+    pub from: u16,
+    pub to: u16,
+    /// `0` forward, `1` reverse, `2` ping-pong, `3` ping-pong reverse
+    pub direction: u8,
+}
+
+struct Layer {
+    /// Visible, and so are all groups it is in
+    visible: bool,
+    opacity: u8,
+}
+
+struct Cel {
+    frame: u16,
+    layer: u16,
+    pos: I16Vec2,
+    opacity: u8,
+    z_index: i16,
+    pixels: CelPixels,
+}
+
+enum CelPixels {
+    /// Size and pixels in the color depth of the sprite
+    Image(U16Vec2, Vec<u8>),
+    /// Same cel as in that frame
+    Linked(u16),
+}
+
+pub fn load(path: &Path) -> Result<Aseprite> {
+    let data = std::fs::read(path).context(format!("Failed to read {:?}!", path))?;
+    parse(&data).context(format!("Failed to load Aseprite file {:?}!", path))
+}
+
+fn string(chunk: &mut DataReader) -> Result<String> {
+    let length = chunk.u16()? as usize;
+    Ok(String::from_utf8_lossy(chunk.bytes(length)?).into_owned())
+}
+
+fn parse(data: &[u8]) -> Result<Aseprite> {
+    let mut file = DataReader::new(data, ByteOrder::LittleEndian, Alignment::Packed);
+    file.u32()?;
+    if file.u16()? != FILE_MAGIC {
+        bail!("Not an Aseprite file!");
+    }
+    let frame_count = file.u16()?;
+    let frame_size = U16Vec2::new(file.u16()?, file.u16()?);
+    let depth = file.u16()?;
+    let layer_opacity = file.u32()? & 1 != 0;
+    file.bytes(10)?;
+    let transparent_index = file.u8()?;
+    file.bytes(7)?;
+    file.bytes(4)?;
+    let grid = U16Vec2::new(file.u16()?, file.u16()?);
+    file.position = 128;
+    if frame_size.x == 0 || frame_size.y == 0 {
+        bail!("Sprite has no pixels!");
+    }
+    if ![8, 16, 32].contains(&depth) {
+        bail!("Unknown color depth {}!", depth);
+    }
+
+    let mut warnings = Vec::new();
+    let mut durations = Vec::new();
+    let mut layers = Vec::new();
+    let mut visible_groups = Vec::new();
+    let mut cels = Vec::new();
+    let mut tags = Vec::new();
+    let mut palette = Vec::new();
+    for frame in 0..frame_count {
+        let start = file.position;
+        let length = file.u32()? as usize;
+        if file.u16()? != FRAME_MAGIC {
+            bail!("Frame {} is corrupted!", frame);
+        }
+        let old_chunk_count = file.u16()? as u32;
+        durations.push(file.u16()?);
+        file.bytes(2)?;
+        let chunk_count = match file.u32()? {
+            0 => old_chunk_count,
+            count => count,
+        };
+
+        for _ in 0..chunk_count {
+            let size = file.u32()? as usize;
+            let kind = file.u16()?;
+            let mut chunk = DataReader::new(
+                file.bytes(size.checked_sub(6).context("Chunk is too small!")?)?,
+                ByteOrder::LittleEndian,
+                Alignment::Packed,
+            );
+            match kind {
+                LAYER_CHUNK => {
+                    let flags = chunk.u16()?;
+                    let layer_type = chunk.u16()?;
+                    let level = chunk.u16()? as usize;
+                    chunk.bytes(4)?;
+                    let blend_mode = chunk.u16()?;
+                    let opacity = chunk.u8()?;
+                    chunk.bytes(3)?;
+                    let name = string(&mut chunk)?;
+
+                    // Hidden and reference layers are left out, as is everything in hidden groups
+                    let in_visible_group =
+                        level == 0 || visible_groups.get(level - 1).copied().unwrap_or(true);
+                    let visible = in_visible_group && flags & 1 != 0 && flags & 64 == 0;
+                    visible_groups.truncate(level);
+                    visible_groups.push(visible);
+                    if visible && layer_type == 2 {
+                        warnings.push(format!(
+                            "Tilemap layer {:?} is not supported and was left out",
+                            name
+                        ));
+                    }
+                    if visible && layer_type == 0 && blend_mode != 0 {
+                        warnings.push(format!(
+                            "Layer {:?} has a blend mode other than Normal, it was blended as Normal",
+                            name
+                        ));
+                    }
+                    layers.push(Layer {
+                        visible: visible && layer_type == 0,
+                        opacity: if layer_opacity { opacity } else { 255 },
+                    });
+                }
+                CEL_CHUNK => {
+                    let layer = chunk.u16()?;
+                    let pos = I16Vec2::new(chunk.i16()?, chunk.i16()?);
+                    let opacity = chunk.u8()?;
+                    let cel_type = chunk.u16()?;
+                    let z_index = chunk.i16()?;
+                    chunk.bytes(5)?;
+                    let pixels = match cel_type {
+                        0 | 2 => {
+                            let size = U16Vec2::new(chunk.u16()?, chunk.u16()?);
+                            let data = &chunk.data[chunk.position..];
+                            let mut pixels = Vec::new();
+                            if cel_type == 0 {
+                                pixels.extend_from_slice(data);
+                            } else {
+                                flate2::read::ZlibDecoder::new(data)
+                                    .read_to_end(&mut pixels)
+                                    .context(format!(
+                                        "Failed to decompress cel of frame {}!",
+                                        frame
+                                    ))?;
+                            }
+                            let length = size.x as usize * size.y as usize * depth as usize / 8;
+                            if pixels.len() < length {
+                                bail!("Cel of frame {} is truncated!", frame);
+                            }
+                            CelPixels::Image(size, pixels)
+                        }
+                        1 => CelPixels::Linked(chunk.u16()?),
+                        _ => continue,
+                    };
+                    cels.push(Cel {
+                        frame,
+                        layer,
+                        pos,
+                        opacity,
+                        z_index,
+                        pixels,
+                    });
+                }
+                TAGS_CHUNK => {
+                    let count = chunk.u16()?;
+                    chunk.bytes(8)?;
+                    for _ in 0..count {
+                        let (from, to, direction) = (chunk.u16()?, chunk.u16()?, chunk.u8()?);
+                        chunk.bytes(12)?;
+                        tags.push(Tag {
+                            name: string(&mut chunk)?,
+                            from,
+                            to,
+                            direction,
+                        });
+                    }
+                }
+                PALETTE_CHUNK => {
+                    let size = chunk.u32()? as usize;
+                    let (first, last) = (chunk.u32()? as usize, chunk.u32()? as usize);
+                    chunk.bytes(8)?;
+                    if first > last {
+                        bail!("Palette goes from color {} to {}!", first, last);
+                    }
+                    if size > 256 || last >= 256 {
+                        bail!("Palette has more than 256 colors!");
+                    }
+                    palette.resize(size.max(last + 1), [0; 4]);
+                    for color in &mut palette[first..=last] {
+                        let flags = chunk.u16()?;
+                        *color = chunk.bytes(4)?.try_into()?;
+                        if flags & 1 != 0 {
+                            string(&mut chunk)?;
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        file.position = start + length;
+    }
+
+    let image = flatten(
+        frame_size,
+        durations.len(),
+        &layers,
+        &cels,
+        |pixel: &[u8]| match depth {
+            32 => pixel.try_into().unwrap(),
+            16 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+            _ if pixel[0] == transparent_index => [0; 4],
+            _ => palette.get(pixel[0] as usize).copied().unwrap_or([0; 4]),
+        },
+        depth as usize / 8,
+    );
+    Ok(Aseprite {
+        image,
+        frame_size,
+        grid: (grid.x > 0 && grid.y > 0).then_some(grid),
+        durations,
+        tags,
+        warnings,
+    })
+}
+
+// * --------------------------------------------------------------------------------- FLATTEN -------------------------------------------------------------------------------- * //
+/// Draws the cels of visible layers over each other, bottom layer first, every frame next to the previous one
+fn flatten(
+    frame_size: U16Vec2,
+    frame_count: usize,
+    layers: &[Layer],
+    cels: &[Cel],
+    color: impl Fn(&[u8]) -> [u8; 4],
+    pixel_bytes: usize,
+) -> image::RgbaImage {
+    let mut image = image::RgbaImage::new(
+        frame_size.x as u32 * frame_count as u32,
+        frame_size.y as u32,
+    );
+    let by_frame = cels
+        .iter()
+        .map(|cel| ((cel.frame, cel.layer), cel))
+        .collect::<HashMap<_, _>>();
+    for frame in 0..frame_count as u16 {
+        let frame_cels = cels
+            .iter()
+            .filter(|cel| cel.frame == frame)
+            .filter(|cel| {
+                layers
+                    .get(cel.layer as usize)
+                    .is_some_and(|layer| layer.visible)
+            })
+            .sorted_by_key(|cel| (cel.layer as i32 + cel.z_index as i32, cel.z_index));
+        for cel in frame_cels {
+            let source = match cel.pixels {
+                CelPixels::Linked(linked) => match by_frame.get(&(linked, cel.layer)) {
+                    Some(source) => *source,
+                    None => continue,
+                },
+                CelPixels::Image(..) => cel,
+            };
+            let CelPixels::Image(size, pixels) = &source.pixels else {
+                continue;
+            };
+            let opacity = source.opacity as u32 * layers[cel.layer as usize].opacity as u32 / 255;
+            for y in 0..size.y as i32 {
+                for x in 0..size.x as i32 {
+                    let pos = source.pos.casted::<i32>() + I32Vec2::new(x, y);
+                    if pos.x < 0
+                        || pos.y < 0
+                        || pos.x >= frame_size.x as i32
+                        || pos.y >= frame_size.y as i32
+                    {
+                        continue;
+                    }
+                    let index = (x as usize + y as usize * size.x as usize) * pixel_bytes;
+                    let source = color(&pixels[index..index + pixel_bytes]);
+                    let target = image.get_pixel_mut(
+                        pos.x as u32 + frame as u32 * frame_size.x as u32,
+                        pos.y as u32,
+                    );
+                    *target = image::Rgba(blend(target.0, source, opacity));
+                }
+            }
+        }
+    }
+    image
+}
+
+/// Normal blending of straight alpha colors
+fn blend(target: [u8; 4], source: [u8; 4], opacity: u32) -> [u8; 4] {
+    let source_alpha = source[3] as u32 * opacity / 255;
+    let target_alpha = target[3] as u32 * (255 - source_alpha) / 255;
+    let alpha = source_alpha + target_alpha;
+    if alpha == 0 {
+        return [0; 4];
+    }
+    let mut color = [0, 0, 0, alpha as u8];
+    for channel in 0..3 {
+        color[channel] = ((source[channel] as u32 * source_alpha
+            + target[channel] as u32 * target_alpha)
+            / alpha) as u8;
+    }
+    color
+}
+
+// * ---------------------------------------------------------------------------------- TAGS ---------------------------------------------------------------------------------- * //
+/// Animations for the tags of the sprite, cut into tiles of `tile_size`.
+/// Every tile that changes during a tag cycles through the same tile in the tag's frames.
+pub fn animations(
+    sprite: &Aseprite,
+    tile_size: U16Vec2,
+    warnings: &mut Vec<String>,
+) -> Vec<Animation> {
+    let mut animations = Vec::<Animation>::new();
+    if sprite.tags.is_empty() {
+        return animations;
+    }
+    if !sprite.frame_size.x.is_multiple_of(tile_size.x)
+        || !sprite.frame_size.y.is_multiple_of(tile_size.y)
+    {
+        warnings.push(format!(
+            "Frames of {}x{} pixels don't split into {}x{} tiles, tags were not turned into animations",
+            sprite.frame_size.x, sprite.frame_size.y, tile_size.x, tile_size.y
+        ));
+        return animations;
+    }
+
+    let tiles = sprite.frame_size.component_div(&tile_size);
+    let width = tiles.x as u32 * sprite.durations.len() as u32;
+    for tag in &sprite.tags {
+        if tag.from > tag.to || tag.to as usize >= sprite.durations.len() {
+            warnings.push(format!(
+                "Tag {:?} goes from frame {} to {}, which are not in the sprite",
+                tag.name, tag.from, tag.to
+            ));
+            continue;
+        }
+        let forward = (tag.from..=tag.to).collect::<Vec<_>>();
+        let backward = forward.iter().rev().copied().collect::<Vec<_>>();
+        let ping_pong = |there: &[u16]| {
+            let back = there.iter().rev().skip(1);
+            let back = back.take(there.len().saturating_sub(2));
+            there.iter().chain(back).copied().collect::<Vec<_>>()
+        };
+        let frames = match tag.direction {
+            1 => backward,
+            2 => ping_pong(&forward),
+            3 => ping_pong(&backward),
+            _ => forward,
+        };
+
+        let duration = sprite.durations[frames[0] as usize];
+        if frames
+            .iter()
+            .any(|frame| sprite.durations[*frame as usize] != duration)
+        {
+            warnings.push(format!(
+                "Frames of tag {:?} have different durations, all of them are shown for {} ms",
+                tag.name, duration
+            ));
+        }
+        let frame_time = ((duration as f64 * ANIMATION_FPS / 1000.0).round() as u16).max(1);
+
+        for y in 0..tiles.y as u32 {
+            for x in 0..tiles.x as u32 {
+                let tile = |frame: u16| frame as u32 * tiles.x as u32 + x + y * width;
+                let pixels = |frame: u16| {
+                    let tile = tile(frame);
+                    image::imageops::crop_imm(
+                        &sprite.image,
+                        tile % width * tile_size.x as u32,
+                        tile / width * tile_size.y as u32,
+                        tile_size.x as u32,
+                        tile_size.y as u32,
+                    )
+                    .to_image()
+                };
+                let first = pixels(frames[0]);
+                if frames.iter().all(|frame| pixels(*frame) == first) {
+                    continue;
+                }
+                if tile(*frames.iter().max().unwrap()) >= 0xFFFF {
+                    warnings.push(format!(
+                        "Tag {:?} uses tiles past index {}",
+                        tag.name,
+                        0xFFFF - 1
+                    ));
+                    break;
+                }
+                let animation = Animation {
+                    tile: tile(frames[0]) as u16,
+                    frames: frames.iter().map(|frame| tile(*frame) as u16).collect(),
+                    frame_time,
+                };
+                if animations.iter().any(|other| other.tile == animation.tile) {
+                    warnings.push(format!(
+                        "Tag {:?} animates tile {} again, only the first animation was kept",
+                        tag.name, animation.tile
+                    ));
+                    continue;
+                }
+                animations.push(animation);
+            }
+        }
+    }
+    animations
+}
+
+// * --------------------------------------------------------------------------------- IMPORT --------------------------------------------------------------------------------- * //
+/// Imports an Aseprite file of the project as a new atlas with the grid size as its tile size, and tags as tile animations.
+/// Returns the UUID of the new atlas and everything that could not be imported.
+pub fn import(assets: &mut Assets, path: &Path) -> Result<(Uuid, Vec<String>)> {
+    let sprite = load(path)?;
+    let tile_size = sprite.grid.unwrap_or(sprite.frame_size);
+    let mut warnings = Vec::new();
+    let animations = animations(&sprite, tile_size, &mut warnings);
+    warnings.splice(0..0, sprite.warnings);
+
+    let mut atlas = Atlas::new(path, sprite.image, tile_size);
+    atlas.animations = animations;
+    atlas.export_id = free_export_id(assets.atlases.values().map(|atlas| atlas.export_id));
+    atlas.save()?;
+    let uuid = Uuid::new_v4();
+    assets.atlases.insert(uuid, atlas);
+    assets.uuids.insert(path.to_path_buf(), uuid);
+    Ok((uuid, warnings))
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::temp_dir::temp_dir;
+    use std::io::Write;
+
+    fn writer() -> DataWriter {
+        DataWriter::new(&ExportSettings::default())
+    }
+
+    fn string(writer: &mut DataWriter, string: &str) {
+        writer.u16(string.len() as u16);
+        writer.bytes(string.as_bytes());
+    }
+
+    fn layer(name: &str, visible: bool) -> (u16, Vec<u8>) {
+        let mut chunk = writer();
+        chunk.u16(visible as u16);
+        chunk.bytes(&[0; 10]);
+        chunk.u8(255);
+        chunk.bytes(&[0; 3]);
+        string(&mut chunk, name);
+        (LAYER_CHUNK, chunk.data)
+    }
+
+    /// Cel of a single color, compressed or not
+    fn cel(
+        layer: u16,
+        pos: (i16, i16),
+        size: (u16, u16),
+        color: [u8; 4],
+        compressed: bool,
+    ) -> (u16, Vec<u8>) {
+        let mut chunk = writer();
+        chunk.u16(layer);
+        chunk.i16(pos.0);
+        chunk.i16(pos.1);
+        chunk.u8(255);
+        chunk.u16(if compressed { 2 } else { 0 });
+        chunk.bytes(&[0; 7]);
+        chunk.u16(size.0);
+        chunk.u16(size.1);
+        let pixels = color.repeat(size.0 as usize * size.1 as usize);
+        if compressed {
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&pixels).unwrap();
+            chunk.bytes(&encoder.finish().unwrap());
+        } else {
+            chunk.bytes(&pixels);
+        }
+        (CEL_CHUNK, chunk.data)
+    }
+
+    fn linked_cel(layer: u16, frame: u16) -> (u16, Vec<u8>) {
+        let mut chunk = writer();
+        chunk.u16(layer);
+        chunk.bytes(&[0; 5]);
+        chunk.u16(1);
+        chunk.bytes(&[0; 7]);
+        chunk.u16(frame);
+        (CEL_CHUNK, chunk.data)
+    }
+
+    fn palette(size: u32, first: u32, last: u32) -> (u16, Vec<u8>) {
+        let mut chunk = writer();
+        chunk.u32(size);
+        chunk.u32(first);
+        chunk.u32(last);
+        chunk.bytes(&[0; 8]);
+        for _ in first..=last {
+            chunk.u16(0);
+            chunk.bytes(&[255; 4]);
+        }
+        (PALETTE_CHUNK, chunk.data)
+    }
+
+    /// 16x8 sprite of 32 bit colors with an 8x8 grid, every frame shown for 100 ms
+    fn file(frames: &[Vec<(u16, Vec<u8>)>]) -> Vec<u8> {
+        let mut file = writer();
+        file.u32(0);
+        file.u16(FILE_MAGIC);
+        file.u16(frames.len() as u16);
+        file.u16(16);
+        file.u16(8);
+        file.u16(32);
+        file.u32(1);
+        file.bytes(&[0; 22]);
+        file.u16(8);
+        file.u16(8);
+        file.bytes(&[0; 84]);
+        for chunks in frames {
+            let mut frame = writer();
+            for (kind, data) in chunks {
+                frame.u32(data.len() as u32 + 6);
+                frame.u16(*kind);
+                frame.bytes(data);
+            }
+            file.u32(frame.data.len() as u32 + 16);
+            file.u16(FRAME_MAGIC);
+            file.u16(chunks.len() as u16);
+            file.u16(100);
+            file.bytes(&[0; 2]);
+            file.u32(chunks.len() as u32);
+            file.bytes(&frame.data);
+        }
+        file.data
+    }
+
+    /// 16x8 sprite with an 8x8 grid and 2 frames of 100 ms.
+    /// The left tile is red, then blue. The right one is green with a linked white dot, and a hidden white layer covers both.
+    fn sprite() -> Vec<u8> {
+        let (red, green, blue, white) = (
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [255; 4],
+        );
+        let mut tags = writer();
+        tags.u16(1);
+        tags.bytes(&[0; 8]);
+        tags.u16(0);
+        tags.u16(1);
+        tags.u8(0);
+        tags.bytes(&[0; 12]);
+        string(&mut tags, "Blink");
+        let frames = [
+            vec![
+                layer("Background", true),
+                layer("Hidden", false),
+                layer("Dot", true),
+                (TAGS_CHUNK, tags.data),
+                cel(0, (0, 0), (8, 8), red, false),
+                cel(0, (8, 0), (8, 8), green, false),
+                cel(1, (0, 0), (16, 8), white, true),
+                cel(2, (9, 1), (2, 2), white, false),
+            ],
+            vec![
+                cel(0, (-8, 0), (16, 8), blue, true),
+                cel(0, (8, 0), (8, 8), green, false),
+                linked_cel(2, 0),
+            ],
+        ];
+        file(&frames)
+    }
+
+    #[test]
+    fn imports_sprite() {
+        let dir = temp_dir();
+        let path = dir.join("torch.aseprite");
+        std::fs::write(&path, sprite()).unwrap();
+        let mut assets = Assets::new(&dir);
+        let (uuid, warnings) = import(&mut assets, &path).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let atlas = &assets.atlases[&uuid];
+        assert_eq!(atlas.tile_size, U16Vec2::new(8, 8));
+        assert_eq!(atlas.image.dimensions(), (32, 8));
+        let pixel = |x, y| atlas.image.get_pixel(x, y).0;
+        assert_eq!(pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(8, 0), [0, 255, 0, 255]);
+        assert_eq!(pixel(9, 1), [255; 4]);
+        assert_eq!(pixel(16, 0), [0, 0, 255, 255]);
+        assert_eq!(pixel(25, 2), [255; 4]);
+        assert_eq!(pixel(31, 7), [0, 255, 0, 255]);
+
+        // Only the left tile changes, every 100 ms
+        assert_eq!(atlas.animations.len(), 1);
+        assert_eq!(atlas.animations[0].tile, 0);
+        assert_eq!(atlas.animations[0].frames, vec![0, 2]);
+        assert_eq!(atlas.animations[0].frame_time, 6);
+        assert!(path.with_extension("atl").exists());
+        assert_eq!(
+            crate::view::atlas_view::load_image(&path).unwrap(),
+            atlas.image
+        );
+    }
+
+    #[test]
+    fn rejects_broken_palettes() {
+        assert!(parse(&file(&[vec![palette(2, 0, 1)]])).is_ok());
+        for (size, first, last) in [(4, 3u32, 1u32), (1 << 30, 0, 0), (2, 0, 1 << 30)] {
+            let mut broken = palette(size, 0, 0);
+            broken.1[4..12].copy_from_slice(&[first.to_le_bytes(), last.to_le_bytes()].concat());
+            assert!(parse(&file(&[vec![broken]])).is_err());
+        }
+    }
+
+    #[test]
+    fn backwards_tags_past_the_last_tile() {
+        // 1100 frames of 8x8 one pixel tiles, the last frames of the bottom row are past tile 65535
+        let frames = 1100;
+        let sprite = Aseprite {
+            image: image::RgbaImage::from_fn(8 * frames, 8, |x, _| {
+                image::Rgba([(x / 8) as u8, 0, 0, 255])
+            }),
+            frame_size: U16Vec2::new(8, 8),
+            grid: None,
+            durations: vec![100; frames as usize],
+            tags: vec![Tag {
+                name: "Back".to_owned(),
+                from: 0,
+                to: frames as u16 - 1,
+                direction: 1,
+            }],
+            warnings: Vec::new(),
+        };
+        let mut warnings = Vec::new();
+        let animations = animations(&sprite, U16Vec2::new(1, 1), &mut warnings);
+        assert_eq!(warnings, vec!["Tag \"Back\" uses tiles past index 65534"]);
+        assert!(animations
+            .iter()
+            .all(|animation| animation.frames.iter().all(|frame| *frame < 0xFFFF - 8)));
+    }
+}
@@ -0,0 +1,74 @@
+use crate::project::*;
+
+// * ---------------------------------------------------------------------------------- ATLAS --------------------------------------------------------------------------------- * //
+/// Atlas for a tileset image with the given tile size, margin and spacing, reused if the project already has one.
+/// Images from outside of the project are copied into `copy_to`.
+pub fn import_atlas(
+    assets: &mut Assets,
+    image: &Path,
+    tile_size: U16Vec2,
+    (margin, spacing): (u16, u16),
+    copy_to: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<Uuid> {
+    let project = assets
+        .path
+        .canonicalize()
+        .context("Failed to find project directory!")?;
+    let mut image = image
+        .canonicalize()
+        .context(format!("Tileset image {:?} was not found!", image))?;
+    if !image.starts_with(&project) {
+        let copy = copy_to.join(image.file_name().context("Invalid tileset image!")?);
+        if copy.exists() {
+            warnings.push(format!(
+                "Used {:?} instead of tileset image {:?}, because it has the same name",
+                copy, image
+            ));
+        } else {
+            std::fs::copy(&image, &copy)
+                .context(format!("Failed to copy tileset image to {:?}!", copy))?;
+        }
+        image = copy.canonicalize()?;
+    }
+
+    if let Some((uuid, atlas)) = assets
+        .atlases
+        .iter()
+        .find(|(_, atlas)| atlas.path.canonicalize().ok().as_ref() == Some(&image))
+    {
+        if atlas.tile_size != tile_size {
+            bail!(
+                "Atlas {:?} has {}x{} tiles, but the tileset has {}x{} tiles!",
+                atlas.path,
+                atlas.tile_size.x,
+                atlas.tile_size.y,
+                tile_size.x,
+                tile_size.y
+            );
+        }
+        if (atlas.margin, atlas.spacing) != (margin, spacing) {
+            bail!(
+                "Atlas {:?} has a margin of {} and spacing of {}, but the tileset has a margin of {} and spacing of {}!",
+                atlas.path,
+                atlas.margin,
+                atlas.spacing,
+                margin,
+                spacing
+            );
+        }
+        return Ok(*uuid);
+    }
+
+    let path = assets.path.join(image.strip_prefix(&project)?);
+    let mut atlas = Atlas::load(&path)?;
+    atlas.tile_size = tile_size;
+    atlas.margin = margin;
+    atlas.spacing = spacing;
+    atlas.export_id = free_export_id(assets.atlases.values().map(|atlas| atlas.export_id));
+    atlas.save()?;
+    let uuid = Uuid::new_v4();
+    assets.atlases.insert(uuid, atlas);
+    assets.uuids.insert(path, uuid);
+    Ok(uuid)
+}
@@ -0,0 +1,715 @@
+use crate::export::codegen::asset_name;
+use crate::export::ldtk::{identifier, integer_range, ATLASES_ENUM, MAPS_ENUM, ON_TOP_LAYER};
+use crate::import::atlas::import_atlas;
+use crate::project::*;
+use crate::view::editor_view::Object;
+use serde_json::Value;
+use std::collections::HashMap;
+
+// * --------------------------------------------------------------------------------- IMPORT --------------------------------------------------------------------------------- * //
+/// Imports an LDtk project. Tilesets become atlases, entity definitions components and levels maps.
+/// Assets with the same identifier as an LDtk definition or level are updated instead of added.
+/// Returns the UUIDs of the imported maps and everything that could not be imported.
+pub fn import(assets: &mut Assets, ldtk: &Path) -> Result<(Vec<Uuid>, Vec<String>)> {
+    let text = std::fs::read_to_string(ldtk).context(format!("Failed to read {:?}!", ldtk))?;
+    let project: Value =
+        serde_json::from_str(&text).context(format!("Failed to parse {:?}!", ldtk))?;
+    if project["externalLevels"].as_bool() == Some(true) {
+        bail!("Projects with separate level files can't be imported, turn off \"Save levels to separate files\"!");
+    }
+    let base = ldtk.parent().unwrap_or(Path::new(""));
+    let mut warnings = Vec::new();
+    let defs = &project["defs"];
+
+    // * Tilesets
+    let project_path = assets.path.clone();
+    let mut tilesets = HashMap::new();
+    for tileset in array(defs, "tilesets") {
+        let name = tileset["identifier"].as_str().unwrap_or_default();
+        let Some(path) = tileset["relPath"].as_str() else {
+            if tileset["embedAtlas"].is_null() {
+                warnings.push(format!("Tileset {} has no image, skipped it", name));
+            }
+            continue;
+        };
+        let tile_size = u16::try_from(int(tileset, "tileGridSize")?)
+            .ok()
+            .filter(|tile_size| *tile_size > 0)
+            .context(format!("Tileset {} has an invalid tile size!", name))?;
+        let atlas = import_atlas(
+            assets,
+            &base.join(path),
+            U16Vec2::new(tile_size, tile_size),
+            (
+                int(tileset, "padding")? as u16,
+                int(tileset, "spacing")? as u16,
+            ),
+            &project_path,
+            &mut warnings,
+        )
+        .context(format!("Can't import tileset {}!", name))?;
+        tilesets.insert(int(tileset, "uid")?, atlas);
+    }
+
+    // * Components
+    let mut entities = HashMap::new();
+    let mut fields = HashMap::new();
+    for entity in array(defs, "entities") {
+        let (uuid, entity_fields) = import_component(assets, entity, &mut warnings)?;
+        entities.insert(int(entity, "uid")?, uuid);
+        fields.extend(entity_fields);
+    }
+
+    // * Levels, matched to maps by identifier
+    let levels = array(&project, "levels")
+        .iter()
+        .map(|level| {
+            let name = level["identifier"].as_str().unwrap_or_default().to_owned();
+            let existing = assets
+                .maps
+                .iter()
+                .find(|(_, map)| identifier(&asset_name(assets, &map.path)) == name);
+            match existing {
+                Some((uuid, map)) => (name, *uuid, map.path.clone()),
+                None => (
+                    name.clone(),
+                    Uuid::new_v4(),
+                    assets.path.join(&name).with_extension("map"),
+                ),
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut imported = Vec::new();
+    for (level, (name, uuid, path)) in array(&project, "levels").iter().zip(&levels) {
+        let mut map = import_level(assets, level, &tilesets, path, &mut warnings)
+            .context(format!("Can't import level {}!", name))?;
+        let valid_maps = assets
+            .maps
+            .keys()
+            .copied()
+            .chain(levels.iter().map(|(_, uuid, _)| *uuid))
+            .collect::<Vec<_>>();
+        let map_names = assets
+            .maps
+            .iter()
+            .map(|(uuid, map)| (identifier(&asset_name(assets, &map.path)), *uuid))
+            .chain(levels.iter().map(|(name, uuid, _)| (name.clone(), *uuid)))
+            .collect::<HashMap<_, _>>();
+        let references = References {
+            entities: &entities,
+            fields: &fields,
+            map_names: &map_names,
+        };
+        for layer in array(level, "layerInstances").iter().rev() {
+            import_entities(assets, layer, &references, &mut map, &mut warnings)?;
+        }
+        for object in map.objects.values_mut() {
+            for (component, instance) in object.components.iter_mut() {
+                assets.components[component].fix_instance(
+                    instance,
+                    assets.atlases.keys().copied(),
+                    valid_maps.iter().copied(),
+                );
+            }
+        }
+
+        map.export_id = match assets.maps.get(uuid) {
+            Some(existing) => existing.export_id,
+            None => free_export_id(assets.maps.values().map(|map| map.export_id)),
+        };
+        map.save()?;
+        assets.maps.insert(*uuid, map);
+        assets.uuids.insert(path.clone(), *uuid);
+        imported.push(*uuid);
+    }
+    Ok((imported, warnings))
+}
+
+// * ------------------------------------------------------------------------------- COMPONENTS ------------------------------------------------------------------------------- * //
+/// Component and property name of entity fields, by field UID
+type Fields = HashMap<i64, (Uuid, String)>;
+
+/// Component for an entity definition, created if the project doesn't have one with the same identifier.
+/// Returns it with the properties of the entity fields by field UID.
+fn import_component(
+    assets: &mut Assets,
+    entity: &Value,
+    warnings: &mut Vec<String>,
+) -> Result<(Uuid, Fields)> {
+    let name = entity["identifier"]
+        .as_str()
+        .context("Entity definition has no identifier!")?;
+    let existing = assets
+        .components
+        .iter()
+        .find(|(_, component)| identifier(&asset_name(assets, &component.path)) == name)
+        .map(|(uuid, _)| *uuid);
+    let uuid = match existing {
+        Some(uuid) => uuid,
+        None => {
+            let path = assets.path.join(name).with_extension("cmp");
+            let mut component = Component::new(&path);
+            component.export_id = free_export_id(
+                assets
+                    .components
+                    .values()
+                    .map(|component| component.export_id),
+            );
+            let uuid = Uuid::new_v4();
+            assets.components.insert(uuid, component);
+            assets.uuids.insert(path, uuid);
+            uuid
+        }
+    };
+
+    let component = assets.components.get_mut(&uuid).unwrap();
+    let builtin = component.path.starts_with("/\nbuiltin/");
+    let mut fields = HashMap::new();
+    for field in array(entity, "fieldDefs") {
+        let field_name = field["identifier"].as_str().unwrap_or_default();
+        let property_type = match property_type(field) {
+            Ok(property_type) => property_type,
+            Err(reason) => {
+                warnings.push(format!(
+                    "Entity {}: skipped field {}, {}",
+                    name, field_name, reason
+                ));
+                continue;
+            }
+        };
+        let property = component
+            .properties
+            .iter()
+            .find(|(property, _)| identifier(property) == field_name)
+            .map(|(property, existing_type)| (property.clone(), existing_type.clone()));
+        match property {
+            Some((property, existing_type)) => {
+                if existing_type != property_type {
+                    warnings.push(format!(
+                        "Entity {}: field {} is {:?} in the project and {:?} in LDtk, kept {:?}",
+                        name, field_name, existing_type, property_type, existing_type
+                    ));
+                }
+                fields.insert(int(field, "uid")?, (uuid, property));
+            }
+            None if builtin => warnings.push(format!(
+                "Entity {}: builtin component can't get field {}",
+                name, field_name
+            )),
+            None => {
+                component
+                    .properties
+                    .insert(field_name.to_owned(), property_type);
+                fields.insert(int(field, "uid")?, (uuid, field_name.to_owned()));
+            }
+        }
+    }
+    component.save()?;
+    Ok((uuid, fields))
+}
+
+/// Property type for an entity field, or why it has none
+fn property_type(field: &Value) -> std::result::Result<Property, String> {
+    if field["isArray"].as_bool() == Some(true) {
+        return Err("arrays are not supported".to_owned());
+    }
+    let field_type = field["type"].as_str().unwrap_or_default();
+    let display_type = field["__type"].as_str().unwrap_or_default();
+    match field_type {
+        "F_Int" => {
+            let (min, max) = (field["min"].as_f64(), field["max"].as_f64());
+            Ok([
+                Property::U8,
+                Property::I8,
+                Property::U16,
+                Property::I16,
+                Property::U32,
+                Property::I32,
+            ]
+            .into_iter()
+            .find(|property_type| {
+                let (type_min, type_max) = integer_range(property_type).unwrap();
+                min.is_some_and(|min| min >= type_min as f64)
+                    && max.is_some_and(|max| max <= type_max as f64)
+            })
+            .unwrap_or(Property::I32))
+        }
+        "F_Float" => Ok(Property::F32),
+        "F_String" | "F_Text" | "F_Path" => Ok(Property::String),
+        "F_Bool" => Ok(Property::U8),
+        "F_Color" => Ok(Property::U32),
+        _ if field_type.starts_with("F_Enum") => match display_type.rsplit('.').next() {
+            Some(ATLASES_ENUM) => Ok(Property::Atlas),
+            Some(MAPS_ENUM) => Ok(Property::Map),
+            _ => Ok(Property::String),
+        },
+        _ => Err(format!("{} fields are not supported", display_type)),
+    }
+}
+
+// * --------------------------------------------------------------------------------- LEVELS --------------------------------------------------------------------------------- * //
+/// Map with the tiles of a level, tile layers are merged with upper layers covering lower ones
+fn import_level(
+    assets: &Assets,
+    level: &Value,
+    tilesets: &HashMap<i64, Uuid>,
+    path: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<Map> {
+    let name = level["identifier"].as_str().unwrap_or_default();
+    if level["layerInstances"].is_null() {
+        bail!("Level has no layers, levels in separate files are not supported!");
+    }
+    // LDtk lists layers from top to bottom
+    let tile_layers = array(level, "layerInstances")
+        .iter()
+        .rev()
+        .filter(|layer| !layer_tiles(layer).is_empty())
+        .collect::<Vec<_>>();
+    let tileset = |layer: &Value| {
+        layer["overrideTilesetUid"]
+            .as_i64()
+            .or(layer["__tilesetDefUid"].as_i64())
+    };
+    let atlas_uuid = tile_layers
+        .iter()
+        .find_map(|layer| tileset(layer).and_then(|uid| tilesets.get(&uid)))
+        .or(tilesets.values().next())
+        .copied()
+        .context("Project has no tilesets!")?;
+    let atlas = &assets.atlases[&atlas_uuid];
+
+    let pixels = |key| {
+        u16::try_from(int(level, key)?).context(format!(
+            "Level {} is larger than 65535 pixels, which a map can't hold!",
+            name
+        ))
+    };
+    let size = U16Vec2::new(
+        pixels("pxWid")?.div_ceil(atlas.tile_size.x),
+        pixels("pxHei")?.div_ceil(atlas.tile_size.y),
+    );
+    let mut map = Map::new(path, size, atlas_uuid);
+    let (mut flipped, mut skipped) = (0, 0);
+    for layer in &tile_layers {
+        let layer_name = layer["__identifier"].as_str().unwrap_or_default();
+        if tileset(layer).and_then(|uid| tilesets.get(&uid)) != Some(&atlas_uuid) {
+            skipped += layer_tiles(layer).len();
+            continue;
+        }
+        for tile in layer_tiles(layer) {
+            if tile["f"].as_i64().unwrap_or(0) != 0 {
+                flipped += 1;
+            }
+            let id = int(tile, "t").context(format!("Invalid tile in layer {}!", layer_name))?;
+            let pos = &tile["px"];
+            let pos = I16Vec2::new(
+                (pos[0].as_i64().unwrap_or(-1) / atlas.tile_size.x as i64) as i16,
+                (pos[1].as_i64().unwrap_or(-1) / atlas.tile_size.y as i64) as i16,
+            );
+            if id < 0 || id >= atlas.width() as i64 * atlas.height() as i64 {
+                skipped += 1;
+                continue;
+            }
+            map.set_tile(
+                pos,
+                I16Vec2::new(
+                    (id % atlas.width() as i64) as i16,
+                    (id / atlas.width() as i64) as i16,
+                ),
+            );
+        }
+    }
+    if tile_layers.len() > 1 {
+        warnings.push(format!(
+            "Level {}: merged {} tile layers into one",
+            name,
+            tile_layers.len()
+        ));
+    }
+    if flipped > 0 {
+        warnings.push(format!(
+            "Level {}: {} tiles are flipped, imported them unflipped",
+            name, flipped
+        ));
+    }
+    if skipped > 0 {
+        warnings.push(format!(
+            "Level {}: {} tiles are not from atlas {:?}, left them empty",
+            name, skipped, atlas.path
+        ));
+    }
+    Ok(map)
+}
+
+/// Tiles placed by hand and by auto layer rules
+fn layer_tiles(layer: &Value) -> Vec<&Value> {
+    array(layer, "gridTiles")
+        .iter()
+        .chain(array(layer, "autoLayerTiles"))
+        .collect()
+}
+
+// * -------------------------------------------------------------------------------- ENTITIES -------------------------------------------------------------------------------- * //
+/// Components and properties of the imported entity definitions and the maps that enum values can name
+struct References<'a> {
+    entities: &'a HashMap<i64, Uuid>,
+    fields: &'a Fields,
+    map_names: &'a HashMap<String, Uuid>,
+}
+
+/// Entities at the same position become one object, unless it already has their component
+fn import_entities(
+    assets: &Assets,
+    layer: &Value,
+    references: &References,
+    map: &mut Map,
+    warnings: &mut Vec<String>,
+) -> Result<()> {
+    let always_on_top = layer["__identifier"].as_str() == Some(ON_TOP_LAYER);
+    let mut objects: Vec<Object> = Vec::new();
+    for entity in array(layer, "entityInstances") {
+        let name = entity["__identifier"].as_str().unwrap_or_default();
+        let Some(component) = entity["defUid"]
+            .as_i64()
+            .and_then(|uid| references.entities.get(&uid))
+        else {
+            warnings.push(format!("Entity {} has no definition, skipped it", name));
+            continue;
+        };
+        let (px, pivot) = (&entity["px"], &entity["__pivot"]);
+        let pos = I32Vec2::new(
+            (px[0].as_f64().unwrap_or(0.0)
+                - pivot[0].as_f64().unwrap_or(0.0) * entity["width"].as_f64().unwrap_or(0.0))
+            .round() as i32,
+            (px[1].as_f64().unwrap_or(0.0)
+                - pivot[1].as_f64().unwrap_or(0.0) * entity["height"].as_f64().unwrap_or(0.0))
+            .round() as i32,
+        );
+
+        let mut instance = HashMap::new();
+        for field in array(entity, "fieldInstances") {
+            let Some((_, property)) = field["defUid"]
+                .as_i64()
+                .and_then(|uid| references.fields.get(&uid))
+            else {
+                continue;
+            };
+            let property_type = &assets.components[component].properties[property];
+            match field_value(
+                assets,
+                property_type,
+                &field["__value"],
+                references.map_names,
+            ) {
+                Some(value) => {
+                    instance.insert(property.clone(), value);
+                }
+                None if field["__value"].is_null() => {}
+                None if matches!(property_type, Property::Atlas | Property::Map) => {
+                    warnings.push(format!(
+                        "Entity {}: {} of {} is not in the project, left it unset",
+                        name, field["__value"], property
+                    ))
+                }
+                None => warnings.push(format!(
+                    "Entity {}: {} is not a valid {:?} for {}, used the default",
+                    name, field["__value"], property_type, property
+                )),
+            }
+        }
+
+        match objects
+            .iter_mut()
+            .find(|object| object.pos == pos && !object.components.contains_key(component))
+        {
+            Some(object) => {
+                object.components.insert(*component, instance);
+            }
+            None => {
+                let mut object = Object::new(pos, &[(*component, instance)]);
+                object.always_on_top = always_on_top;
+                objects.push(object);
+            }
+        }
+    }
+    map.objects
+        .extend(objects.into_iter().map(|object| (Uuid::new_v4(), object)));
+    Ok(())
+}
+
+/// Property value for an LDtk field value, enums name atlases and maps by their identifier
+fn field_value(
+    assets: &Assets,
+    property_type: &Property,
+    value: &Value,
+    map_names: &HashMap<String, Uuid>,
+) -> Option<String> {
+    let text = match value {
+        Value::Null => return None,
+        Value::Bool(value) => (*value as u8).to_string(),
+        Value::Number(value) => value.to_string(),
+        Value::String(value) => value.clone(),
+        _ => return None,
+    };
+    match property_type {
+        Property::Atlas => assets
+            .atlases
+            .iter()
+            .find(|(_, atlas)| identifier(&asset_name(assets, &atlas.path)) == text)
+            .map(|(uuid, _)| uuid.to_string()),
+        Property::Map => map_names.get(&text).map(Uuid::to_string),
+        Property::String => Some(text),
+        Property::U32 if text.starts_with('#') => u32::from_str_radix(&text[1..], 16)
+            .ok()
+            .map(|color| color.to_string()),
+        _ => {
+            let fixed = property_type.fix_value(std::iter::empty(), std::iter::empty(), &text);
+            (fixed == text
+                || (matches!(property_type, Property::F32) && text.parse::<f32>().is_ok()))
+            .then_some(fixed)
+        }
+    }
+}
+
+// * --------------------------------------------------------------------------------- HELPERS -------------------------------------------------------------------------------- * //
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value[key].as_array().map_or(&[], Vec::as_slice)
+}
+
+fn int(value: &Value, key: &str) -> Result<i64> {
+    value[key]
+        .as_i64()
+        .context(format!("LDtk {} is missing or not a number!", key))
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::temp_dir::temp_dir;
+
+    #[test]
+    fn round_trip() {
+        let dir = temp_dir();
+        let mut assets = Assets::new(&dir);
+        let image = image::RgbaImage::from_fn(16, 8, |x, _| image::Rgba([x as u8, 0, 0, 255]));
+        image.save(dir.join("tiles.png")).unwrap();
+        let atlas = Uuid::new_v4();
+        assets.atlases.insert(
+            atlas,
+            Atlas::new(&dir.join("tiles.png"), image, U16Vec2::new(8, 8)),
+        );
+        let door = Uuid::new_v4();
+        let mut component = Component::new(&dir.join("door.cmp"));
+        component.export_id = 2;
+        component
+            .properties
+            .insert("target".to_owned(), Property::Map);
+        component
+            .properties
+            .insert("speed".to_owned(), Property::F32);
+        component
+            .properties
+            .insert("count".to_owned(), Property::I16);
+        assets.components.insert(door, component);
+
+        let level = Uuid::new_v4();
+        let mut map = Map::new(&dir.join("levels/level-1.map"), U16Vec2::new(3, 2), atlas);
+        map.set_tile(TVec2::new(0, 0), TVec2::new(1, 0));
+        map.set_tile(TVec2::new(2, 1), TVec2::new(0, 0));
+        let mut object = Object::new(
+            TVec2::new(5, -7),
+            &[
+                (
+                    door,
+                    [
+                        ("target".to_owned(), level.to_string()),
+                        ("speed".to_owned(), "1.5".to_owned()),
+                        ("count".to_owned(), "-300".to_owned()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                (SERIALIZE_UUID, HashMap::new()),
+            ],
+        );
+        object.always_on_top = true;
+        map.objects.insert(Uuid::new_v4(), object);
+        map.objects
+            .insert(Uuid::new_v4(), Object::new(TVec2::new(16, 8), &[]));
+        std::fs::create_dir_all(dir.join("levels")).unwrap();
+        assets.maps.insert(level, map);
+        let original = serde_json::to_value(&assets.maps[&level]).unwrap();
+
+        crate::export::ldtk::export(&assets, &dir.join("project.ldtk")).unwrap();
+        let (imported, warnings) = import(&mut assets, &dir.join("project.ldtk")).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(imported, vec![level]);
+        assert_eq!(assets.atlases.len(), 1);
+        assert_eq!(assets.components.len(), 3);
+
+        // Objects without components have no entities to come back from
+        let map = &assets.maps[&level];
+        let object = map.objects.values().next().unwrap();
+        assert_eq!(map.objects.len(), 1);
+        assert_eq!(object.pos, I32Vec2::new(5, -7));
+        assert!(object.always_on_top);
+        assert_eq!(original["data"], serde_json::to_value(map).unwrap()["data"]);
+        assert_eq!(
+            serde_json::to_value(&object.components).unwrap(),
+            original["objects"]
+                .as_object()
+                .unwrap()
+                .values()
+                .find(|object| object["pos"][0] == 5)
+                .unwrap()["components"]
+        );
+    }
+
+    #[test]
+    fn reports_what_it_skips() {
+        let (dir, outside) = (temp_dir(), temp_dir());
+        let mut assets = Assets::new(&dir);
+        image::RgbaImage::new(16, 16)
+            .save(outside.join("other.png"))
+            .unwrap();
+        std::fs::write(
+            outside.join("world.ldtk"),
+            r##"{
+  "defs": {
+    "tilesets": [
+      { "identifier": "Other", "uid": 1, "relPath": "other.png", "tileGridSize": 8, "spacing": 0, "padding": 0 },
+      { "identifier": "Internal_Icons", "uid": 2, "relPath": null, "embedAtlas": "LdtkIcons" }
+    ],
+    "entities": [
+      { "identifier": "Chest", "uid": 3, "fieldDefs": [
+        { "identifier": "coins", "uid": 4, "type": "F_Int", "__type": "Int", "isArray": false, "min": 0, "max": 1000 },
+        { "identifier": "locked", "uid": 5, "type": "F_Bool", "__type": "Bool", "isArray": false },
+        { "identifier": "tint", "uid": 6, "type": "F_Color", "__type": "Color", "isArray": false },
+        { "identifier": "spawn", "uid": 7, "type": "F_Point", "__type": "Point", "isArray": false },
+        { "identifier": "loot", "uid": 8, "type": "F_String", "__type": "Array<String>", "isArray": true },
+        { "identifier": "exit", "uid": 11, "type": "F_Enum(12)", "__type": "LocalEnum.OreMapperMaps", "isArray": false }
+      ] },
+      { "identifier": "Serialize", "uid": 9, "fieldDefs": [
+        { "identifier": "id", "uid": 10, "type": "F_Int", "__type": "Int", "isArray": false, "min": null, "max": null }
+      ] }
+    ]
+  },
+  "levels": [
+    { "identifier": "Cave", "pxWid": 16, "pxHei": 12, "layerInstances": [
+      { "__identifier": "Entities", "__type": "Entities", "entityInstances": [
+        { "__identifier": "Chest", "defUid": 3, "px": [12, 16], "__pivot": [0.5, 1], "width": 8, "height": 8, "fieldInstances": [
+          { "__value": 12, "defUid": 4 }, { "__value": true, "defUid": 5 }, { "__value": "#FF0000", "defUid": 6 },
+          { "__value": { "cx": 1, "cy": 1 }, "defUid": 7 }, { "__value": "Nowhere", "defUid": 11 }
+        ] },
+        { "__identifier": "Serialize", "defUid": 9, "px": [4, 0], "__pivot": [0, 0], "fieldInstances": [] },
+        { "__identifier": "Chest", "defUid": 3, "px": [4, 0], "__pivot": [0, 0], "fieldInstances": [{ "__value": -1, "defUid": 4 }] }
+      ] },
+      { "__identifier": "Detail", "__type": "Tiles", "__tilesetDefUid": 1, "autoLayerTiles": [], "gridTiles": [
+        { "px": [8, 0], "t": 3, "f": 1 }
+      ] },
+      { "__identifier": "Ground", "__type": "AutoLayer", "__tilesetDefUid": 1, "gridTiles": [], "autoLayerTiles": [
+        { "px": [0, 0], "t": 1, "f": 0 }, { "px": [8, 0], "t": 2, "f": 0 }, { "px": [0, 8], "t": 9, "f": 0 }
+      ] }
+    ] }
+  ]
+}"##,
+        )
+        .unwrap();
+
+        let (maps, warnings) = import(&mut assets, &outside.join("world.ldtk")).unwrap();
+        assert_eq!(warnings.len(), 8, "{:?}", warnings);
+        assert!(warnings.contains(
+            &"Entity Chest: \"Nowhere\" of exit is not in the project, left it unset".to_owned()
+        ));
+        assert!(dir.join("other.png").exists());
+        assert!(dir.join("Cave.map").exists());
+
+        let (chest, component) = assets
+            .components
+            .iter()
+            .find(|(_, component)| component.path == dir.join("Chest.cmp"))
+            .unwrap();
+        assert_eq!(
+            component.properties.values().collect::<Vec<_>>(),
+            vec![
+                &Property::U16,
+                &Property::U8,
+                &Property::U32,
+                &Property::Map
+            ]
+        );
+        assert!(assets.components[&SERIALIZE_UUID].properties.is_empty());
+
+        let map = &assets.maps[&maps[0]];
+        assert_eq!(map.size, U16Vec2::new(2, 2));
+        assert_eq!(
+            map.data,
+            vec![
+                TVec2::new(1, 0),
+                TVec2::new(1, 1),
+                TVec2::new(-1, -1),
+                TVec2::new(-1, -1)
+            ]
+        );
+        assert_eq!(map.objects.len(), 2);
+        let merged = map
+            .objects
+            .values()
+            .find(|object| object.pos == I32Vec2::new(4, 0))
+            .unwrap();
+        assert!(merged.components.contains_key(&SERIALIZE_UUID));
+        assert_eq!(merged.components[chest]["coins"], "0");
+        let chest_object = map
+            .objects
+            .values()
+            .find(|object| object.pos == I32Vec2::new(8, 8))
+            .unwrap();
+        assert_eq!(chest_object.components[chest]["coins"], "12");
+        assert_eq!(chest_object.components[chest]["locked"], "1");
+        assert_eq!(chest_object.components[chest]["tint"], "16711680");
+        // Not some other map that happens to come first
+        assert_eq!(
+            chest_object.components[chest]["exit"],
+            Uuid::nil().to_string()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        let dir = temp_dir();
+        let mut assets = Assets::new(&dir);
+        image::RgbaImage::new(16, 16)
+            .save(dir.join("tiles.png"))
+            .unwrap();
+        let ldtk = |grid_size: u32, width: u32| {
+            format!(
+                r#"{{
+  "defs": {{
+    "tilesets": [{{ "identifier": "Tiles", "uid": 1, "relPath": "tiles.png", "tileGridSize": {}, "spacing": 0, "padding": 0 }}],
+    "entities": []
+  }},
+  "levels": [{{ "identifier": "Wide", "pxWid": {}, "pxHei": 8, "layerInstances": [] }}]
+}}"#,
+                grid_size, width
+            )
+        };
+        for (grid_size, width, error) in [
+            (0, 16, "Tileset Tiles has an invalid tile size!"),
+            (
+                8,
+                70000,
+                "Level Wide is larger than 65535 pixels, which a map can't hold!",
+            ),
+        ] {
+            std::fs::write(dir.join("world.ldtk"), ldtk(grid_size, width)).unwrap();
+            let err = import(&mut assets, &dir.join("world.ldtk")).unwrap_err();
+            assert!(
+                err.chain().any(|cause| cause.to_string() == error),
+                "{:?}",
+                err
+            );
+        }
+    }
+}
@@ -0,0 +1,597 @@
+use crate::export::codegen::asset_name;
+use crate::import::atlas::import_atlas;
+use crate::project::*;
+use crate::view::editor_view::Object;
+use roxmltree::{Document, Node};
+
+/// Tiled keeps flip and rotation flags in the top bits of a GID
+const GID_FLAGS: u32 = 0xF000_0000;
+
+// * --------------------------------------------------------------------------------- IMPORT --------------------------------------------------------------------------------- * //
+/// Imports a Tiled map as a new map at `map_path`.
+/// Returns the UUID of the new map and everything that could not be imported.
+pub fn import(assets: &mut Assets, tmx: &Path, map_path: &Path) -> Result<(Uuid, Vec<String>)> {
+    if map_path.exists() {
+        bail!("Map {:?} already exists!", map_path);
+    }
+    let text = std::fs::read_to_string(tmx).context(format!("Failed to read {:?}!", tmx))?;
+    let document = Document::parse(&text).context(format!("Failed to parse {:?}!", tmx))?;
+    let root = document.root_element();
+    if !root.has_tag_name("map") {
+        bail!("{:?} is not a Tiled map!", tmx);
+    }
+    if root.attribute("orientation").unwrap_or("orthogonal") != "orthogonal" {
+        bail!("Only orthogonal maps can be imported!");
+    }
+    if root.attribute("infinite") == Some("1") {
+        bail!("Infinite maps can't be imported, turn off Infinite in the map properties!");
+    }
+
+    let base = tmx.parent().unwrap_or(Path::new(""));
+    let mut warnings = Vec::new();
+    let size = U16Vec2::new(attribute(root, "width")?, attribute(root, "height")?);
+    let tilesets = root
+        .children()
+        .filter(|node| node.has_tag_name("tileset"))
+        .map(|node| load_tileset(node, base))
+        .collect::<Result<Vec<_>>>()?;
+    let tileset_of = |gid: u32| {
+        tilesets
+            .iter()
+            .filter(|tileset| tileset.first_gid <= gid & !GID_FLAGS)
+            .max_by_key(|tileset| tileset.first_gid)
+    };
+
+    // * Tiles, later layers cover earlier ones
+    let tile_layers = layers(root, "layer");
+    let mut gids = vec![0; size.x as usize * size.y as usize];
+    for layer in &tile_layers {
+        for (gid, layer_gid) in gids.iter_mut().zip(layer_data(*layer, size)?) {
+            if layer_gid != 0 {
+                *gid = layer_gid;
+            }
+        }
+    }
+    if tile_layers.len() > 1 {
+        warnings.push(format!("Merged {} tile layers into one", tile_layers.len()));
+    }
+
+    let tileset = gids
+        .iter()
+        .find(|gid| **gid != 0)
+        .and_then(|gid| tileset_of(*gid))
+        .or(tilesets.first())
+        .context("Map has no tilesets!")?;
+    let atlas_uuid = import_atlas(
+        assets,
+        &tileset.image,
+        tileset.tile_size,
+        (tileset.margin, tileset.spacing),
+        map_path.parent().unwrap_or(Path::new("")),
+        &mut warnings,
+    )?;
+    let atlas = &assets.atlases[&atlas_uuid];
+
+    let mut map = Map::new(map_path, size, atlas_uuid);
+    let (mut flipped, mut skipped) = (0, 0);
+    for (index, gid) in gids.into_iter().enumerate() {
+        if gid == 0 {
+            continue;
+        }
+        if gid & GID_FLAGS != 0 {
+            flipped += 1;
+        }
+        // Tiles of other tilesets can have lower GIDs, they are skipped before subtracting
+        if tileset_of(gid).map(|tileset| tileset.first_gid) != Some(tileset.first_gid) {
+            skipped += 1;
+            continue;
+        }
+        let tile = (gid & !GID_FLAGS) - tileset.first_gid;
+        if tile >= atlas.width() as u32 * atlas.height() as u32 {
+            skipped += 1;
+            continue;
+        }
+        map.data[index] = TVec2::new(
+            (tile % atlas.width() as u32) as i16,
+            (tile / atlas.width() as u32) as i16,
+        );
+    }
+    if flipped > 0 {
+        warnings.push(format!(
+            "{} tiles are flipped or rotated, imported them unflipped",
+            flipped
+        ));
+    }
+    if skipped > 0 {
+        warnings.push(format!(
+            "{} tiles are not from tileset {:?}, left them empty",
+            skipped, tileset.image
+        ));
+    }
+
+    // * Objects
+    let uuid = Uuid::new_v4();
+    map.export_id = free_export_id(assets.maps.values().map(|map| map.export_id));
+    let maps = assets
+        .maps
+        .iter()
+        .map(|(uuid, map)| (*uuid, map.path.as_path()))
+        .chain([(uuid, map_path)])
+        .collect::<Vec<_>>();
+    for group in layers(root, "objectgroup") {
+        for node in group.children().filter(|node| node.has_tag_name("object")) {
+            map.objects.insert(
+                Uuid::new_v4(),
+                import_object(assets, node, base, &maps, &mut warnings)?,
+            );
+        }
+    }
+    for object in map.objects.values_mut() {
+        for (component, instance) in object.components.iter_mut() {
+            assets.components[component].fix_instance(
+                instance,
+                assets.atlases.keys().copied(),
+                assets.maps.keys().copied().chain([uuid]),
+            );
+        }
+    }
+
+    map.save()?;
+    assets.maps.insert(uuid, map);
+    assets.uuids.insert(map_path.to_path_buf(), uuid);
+    Ok((uuid, warnings))
+}
+
+// * --------------------------------------------------------------------------------- TILESET -------------------------------------------------------------------------------- * //
+struct Tileset {
+    first_gid: u32,
+    tile_size: U16Vec2,
+    margin: u16,
+    spacing: u16,
+    image: PathBuf,
+}
+
+/// Embedded tileset or the TSX file it points to
+fn load_tileset(node: Node, base: &Path) -> Result<Tileset> {
+    let first_gid = attribute(node, "firstgid")?;
+    if let Some(source) = node.attribute("source") {
+        let path = base.join(source);
+        let text = std::fs::read_to_string(&path)
+            .context(format!("Failed to read tileset {:?}!", path))?;
+        let document =
+            Document::parse(&text).context(format!("Failed to parse tileset {:?}!", path))?;
+        tileset(
+            document.root_element(),
+            path.parent().unwrap_or(Path::new("")),
+            first_gid,
+        )
+        .context(format!("Can't import tileset {:?}!", path))
+    } else {
+        tileset(node, base, first_gid).context(format!(
+            "Can't import tileset {:?}!",
+            node.attribute("name").unwrap_or_default()
+        ))
+    }
+}
+
+fn tileset(node: Node, base: &Path, first_gid: u32) -> Result<Tileset> {
+    let image = node
+        .children()
+        .find(|node| node.has_tag_name("image"))
+        .context("Tileset has no image, image collections are not supported!")?;
+    let tile_size = U16Vec2::new(
+        attribute(node, "tilewidth")?,
+        attribute(node, "tileheight")?,
+    );
+    if tile_size.x == 0 || tile_size.y == 0 {
+        bail!("Tileset has a zero tile size!");
+    }
+    Ok(Tileset {
+        first_gid,
+        tile_size,
+        margin: attribute_or(node, "margin", 0)?,
+        spacing: attribute_or(node, "spacing", 0)?,
+        image: base.join(
+            image
+                .attribute("source")
+                .context("Tileset image has no source!")?,
+        ),
+    })
+}
+
+// * --------------------------------------------------------------------------------- LAYERS --------------------------------------------------------------------------------- * //
+/// Layers with the tag, including ones inside of groups, from bottom to top
+fn layers<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Vec<Node<'a, 'input>> {
+    node.children()
+        .flat_map(|child| {
+            if child.has_tag_name("group") {
+                layers(child, tag)
+            } else if child.has_tag_name(tag) {
+                vec![child]
+            } else {
+                Vec::new()
+            }
+        })
+        .collect()
+}
+
+fn layer_data(layer: Node, size: U16Vec2) -> Result<Vec<u32>> {
+    let name = layer.attribute("name").unwrap_or_default();
+    let data = layer
+        .children()
+        .find(|node| node.has_tag_name("data"))
+        .context(format!("Layer {:?} has no data!", name))?;
+    let gids = match data.attribute("encoding") {
+        None => data
+            .children()
+            .filter(|node| node.has_tag_name("tile"))
+            .map(|tile| attribute_or(tile, "gid", 0))
+            .collect::<Result<Vec<_>>>()?,
+        Some("csv") => data
+            .text()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| gid.parse().context(format!("Invalid tile {:?}!", gid)))
+            .collect::<Result<Vec<_>>>()?,
+        Some(encoding) => bail!(
+            "Layer {:?} uses {} encoding, only CSV and XML are supported!",
+            name,
+            encoding
+        ),
+    };
+    if gids.len() != size.x as usize * size.y as usize {
+        bail!(
+            "Layer {:?} has {} tiles, expected {}!",
+            name,
+            gids.len(),
+            size.x as usize * size.y as usize
+        );
+    }
+    Ok(gids)
+}
+
+// * --------------------------------------------------------------------------------- OBJECTS -------------------------------------------------------------------------------- * //
+/// Properties named `Component.property` become component values, a `Component` bool adds the component
+fn import_object(
+    assets: &Assets,
+    node: Node,
+    base: &Path,
+    maps: &[(Uuid, &Path)],
+    warnings: &mut Vec<String>,
+) -> Result<Object> {
+    let id = node.attribute("id").unwrap_or("?");
+    let x: f32 = attribute_or(node, "x", 0.0)?;
+    let mut y: f32 = attribute_or(node, "y", 0.0)?;
+    if node.has_attribute("gid") {
+        // Tile objects are positioned by their bottom left corner
+        y -= attribute_or(node, "height", 0.0)?;
+    }
+    let mut object = Object::new(TVec2::new(x.round() as i32, y.round() as i32), &[]);
+
+    for property in node
+        .children()
+        .filter(|node| node.has_tag_name("properties"))
+        .flat_map(|node| node.children())
+        .filter(|node| node.has_tag_name("property"))
+    {
+        let name = property.attribute("name").unwrap_or_default();
+        let value = property
+            .attribute("value")
+            .or_else(|| property.text())
+            .unwrap_or_default();
+        if name == "always_on_top" {
+            object.always_on_top = value == "true";
+            continue;
+        }
+
+        let (component_name, property_name) = name.rsplit_once('.').unwrap_or((name, ""));
+        let Some((uuid, component)) = find_component(assets, component_name) else {
+            warnings.push(format!(
+                "Object {}: no component matches property {:?}",
+                id, name
+            ));
+            continue;
+        };
+        if property_name.is_empty() {
+            if value == "true" {
+                object.components.entry(*uuid).or_default();
+            }
+            continue;
+        }
+        let Some(property_type) = component.properties.get(property_name) else {
+            warnings.push(format!(
+                "Object {}: component {} has no property {:?}",
+                id, component_name, property_name
+            ));
+            continue;
+        };
+
+        let resolved = match property_type {
+            Property::Atlas => find_asset(
+                assets,
+                &base.join(value),
+                assets
+                    .atlases
+                    .iter()
+                    .map(|(uuid, atlas)| (*uuid, atlas.path.as_path())),
+            ),
+            Property::Map => find_asset(assets, &base.join(value), maps.iter().copied()),
+            _ => {
+                let fixed = property_type.fix_value(std::iter::empty(), std::iter::empty(), value);
+                if fixed != value
+                    && !(matches!(property_type, Property::F32) && value.parse::<f32>().is_ok())
+                {
+                    warnings.push(format!(
+                        "Object {}: {:?} is not a valid {:?} for {}, used {:?}",
+                        id, value, property_type, name, fixed
+                    ));
+                }
+                Some(fixed)
+            }
+        };
+        if let Some(value) = resolved {
+            object
+                .components
+                .entry(*uuid)
+                .or_default()
+                .insert(property_name.to_owned(), value);
+        } else {
+            warnings.push(format!(
+                "Object {}: {:?} of {} is not in the project, left it unset",
+                id, value, name
+            ));
+        }
+    }
+    Ok(object)
+}
+
+fn find_component<'a>(assets: &'a Assets, name: &str) -> Option<(&'a Uuid, &'a Component)> {
+    assets
+        .components
+        .iter()
+        .find(|(_, component)| asset_name(assets, &component.path) == name)
+        .or_else(|| {
+            assets.components.iter().find(|(_, component)| {
+                component.path.file_stem().and_then(|stem| stem.to_str()) == Some(name)
+            })
+        })
+}
+
+/// Asset that the file points to, such as `levels/forest.tmx` for the map `levels/forest.map`
+fn find_asset<'a>(
+    assets: &Assets,
+    file: &Path,
+    candidates: impl Iterator<Item = (Uuid, &'a Path)>,
+) -> Option<String> {
+    let file = file.with_extension("");
+    candidates
+        .map(|(uuid, path)| (uuid, asset_name(assets, path)))
+        .filter(|(_, name)| !name.is_empty() && file.ends_with(name))
+        .max_by_key(|(_, name)| name.len())
+        .map(|(uuid, _)| uuid.to_string())
+}
+
+// * --------------------------------------------------------------------------------- HELPERS -------------------------------------------------------------------------------- * //
+fn attribute<T: std::str::FromStr>(node: Node, name: &str) -> Result<T> {
+    let value =
+        node.attribute(name)
+            .context(format!("<{}> has no {}!", node.tag_name().name(), name))?;
+    value.parse().ok().context(format!(
+        "Invalid {} {:?} in <{}>!",
+        name,
+        value,
+        node.tag_name().name()
+    ))
+}
+
+fn attribute_or<T: std::str::FromStr>(node: Node, name: &str, default: T) -> Result<T> {
+    if node.has_attribute(name) {
+        attribute(node, name)
+    } else {
+        Ok(default)
+    }
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::temp_dir::temp_dir;
+    use std::collections::HashMap;
+
+    /// Project with a 2x1 tiles atlas and a door component
+    fn project(dir: &Path) -> (Assets, Uuid, Uuid) {
+        let mut assets = Assets::new(dir);
+        let image = image::RgbaImage::from_fn(16, 8, |x, _| image::Rgba([x as u8, 0, 0, 255]));
+        image.save(dir.join("tiles.png")).unwrap();
+        let atlas = Uuid::new_v4();
+        assets.atlases.insert(
+            atlas,
+            Atlas::new(&dir.join("tiles.png"), image, U16Vec2::new(8, 8)),
+        );
+
+        let door = Uuid::new_v4();
+        let mut component = Component::new(&dir.join("door.cmp"));
+        component.export_id = 2;
+        component
+            .properties
+            .insert("target".to_owned(), Property::Map);
+        component
+            .properties
+            .insert("count".to_owned(), Property::U8);
+        assets.components.insert(door, component);
+        (assets, atlas, door)
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = temp_dir();
+        let (mut assets, atlas, door) = project(&dir);
+        let level = Uuid::new_v4();
+        let mut map = Map::new(&dir.join("level.map"), U16Vec2::new(3, 2), atlas);
+        map.set_tile(TVec2::new(0, 0), TVec2::new(1, 0));
+        map.set_tile(TVec2::new(2, 1), TVec2::new(0, 0));
+        let mut object = Object::new(
+            TVec2::new(5, -7),
+            &[
+                (
+                    door,
+                    [
+                        ("target".to_owned(), level.to_string()),
+                        ("count".to_owned(), "7".to_owned()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                (SERIALIZE_UUID, HashMap::new()),
+            ],
+        );
+        object.always_on_top = true;
+        map.objects.insert(Uuid::new_v4(), object);
+        assets.maps.insert(level, map);
+
+        crate::export::tiled::export(&assets, &dir.join("tiled")).unwrap();
+        let (imported, warnings) = import(
+            &mut assets,
+            &dir.join("tiled/level.tmx"),
+            &dir.join("imported.map"),
+        )
+        .unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(assets.uuids[&dir.join("imported.map")], imported);
+
+        let (original, imported) = (&assets.maps[&level], &assets.maps[&imported]);
+        assert_eq!(imported.atlas, atlas);
+        assert_eq!(imported.size, original.size);
+        assert_eq!(imported.data, original.data);
+        let (original, imported) = (
+            original.objects.values().next().unwrap(),
+            imported.objects.values().next().unwrap(),
+        );
+        assert_eq!(imported.pos, original.pos);
+        assert!(imported.always_on_top);
+        assert_eq!(imported.components, original.components);
+    }
+
+    #[test]
+    fn reports_what_it_skips() {
+        let (dir, outside) = (temp_dir(), temp_dir());
+        let (mut assets, _, door) = project(&dir);
+        image::RgbaImage::new(16, 16)
+            .save(outside.join("other.png"))
+            .unwrap();
+        std::fs::write(
+            outside.join("legacy.tmx"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="2" height="2" tilewidth="8" tileheight="8" infinite="0">
+ <tileset firstgid="1" name="other" tilewidth="8" tileheight="8" tilecount="4" columns="2">
+  <image source="other.png" width="16" height="16"/>
+ </tileset>
+ <layer id="1" name="Ground" width="2" height="2">
+  <data encoding="csv">1,2,3,4</data>
+ </layer>
+ <group id="2" name="Top">
+  <layer id="3" name="Detail" width="2" height="2">
+   <data><tile/><tile gid="2147483649"/><tile/><tile gid="9"/></data>
+  </layer>
+ </group>
+ <objectgroup id="4" name="Objects">
+  <object id="7" gid="1" x="4" y="24" width="8" height="8">
+   <properties>
+    <property name="door.count" type="int" value="300"/>
+    <property name="door.target" type="file" value="missing.tmx"/>
+    <property name="door.speed" type="float" value="1"/>
+    <property name="health" type="int" value="3"/>
+   </properties>
+  </object>
+ </objectgroup>
+</map>"#,
+        )
+        .unwrap();
+
+        let (uuid, warnings) = import(
+            &mut assets,
+            &outside.join("legacy.tmx"),
+            &dir.join("legacy.map"),
+        )
+        .unwrap();
+        assert_eq!(warnings.len(), 7, "{:?}", warnings);
+        assert!(dir.join("other.png").exists());
+
+        let map = &assets.maps[&uuid];
+        assert_eq!(assets.atlases[&map.atlas].tile_size, U16Vec2::new(8, 8));
+        assert_eq!(
+            map.data,
+            vec![
+                TVec2::new(0, 0),
+                TVec2::new(0, 0),
+                TVec2::new(0, 1),
+                TVec2::new(-1, -1)
+            ]
+        );
+        let object = map.objects.values().next().unwrap();
+        assert_eq!(object.pos, I32Vec2::new(4, 16));
+        assert_eq!(object.components[&door]["count"], "0");
+        // Unresolved references are left unset for validation to report, not given some other map
+        assert!(warnings.contains(
+            &"Object 7: \"missing.tmx\" of door.target is not in the project, left it unset"
+                .to_owned()
+        ));
+        assert_eq!(object.components[&door]["target"], Uuid::nil().to_string());
+
+        assert!(import(
+            &mut assets,
+            &outside.join("legacy.tmx"),
+            &dir.join("legacy.map")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn skips_tiles_of_other_tilesets() {
+        let dir = temp_dir();
+        let (mut assets, atlas, _) = project(&dir);
+        image::RgbaImage::new(16, 16)
+            .save(dir.join("other.png"))
+            .unwrap();
+        let tmx = |tile_width| {
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="2" height="2" tilewidth="8" tileheight="8" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="8" tileheight="8" tilecount="2" columns="2">
+  <image source="tiles.png" width="16" height="8"/>
+ </tileset>
+ <tileset firstgid="3" name="other" tilewidth="{}" tileheight="8" tilecount="4" columns="2">
+  <image source="other.png" width="16" height="16"/>
+ </tileset>
+ <layer id="1" name="Ground" width="2" height="2">
+  <data encoding="csv">3,1,4,0</data>
+ </layer>
+</map>"#,
+                tile_width
+            )
+        };
+
+        // The first tile picks the tileset with the higher first GID, the tile with a lower GID is left empty
+        std::fs::write(dir.join("mixed.tmx"), tmx(8)).unwrap();
+        let (uuid, warnings) =
+            import(&mut assets, &dir.join("mixed.tmx"), &dir.join("mixed.map")).unwrap();
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        let map = &assets.maps[&uuid];
+        assert_ne!(map.atlas, atlas);
+        assert_eq!(
+            map.data,
+            vec![
+                TVec2::new(0, 0),
+                TVec2::new(-1, -1),
+                TVec2::new(1, 0),
+                TVec2::new(-1, -1)
+            ]
+        );
+
+        std::fs::write(dir.join("flat.tmx"), tmx(0)).unwrap();
+        assert!(import(&mut assets, &dir.join("flat.tmx"), &dir.join("flat.map")).is_err());
+    }
+}
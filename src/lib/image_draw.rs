@@ -4,6 +4,7 @@ pub trait DrawTarget {
     fn size(&self) -> U32Vec2;
     fn fill_rect(&mut self, pos: I32Vec2, size: I32Vec2, color: image::Rgba<u8>);
     fn draw_rect(&mut self, pos: I32Vec2, size: I32Vec2, color: image::Rgba<u8>, thickness: u32);
+    fn draw_line(&mut self, from: I32Vec2, to: I32Vec2, color: image::Rgba<u8>);
     fn draw_image(&mut self, image: &image::RgbaImage, pos: I32Vec2, size: Option<I32Vec2>);
     fn draw_subimage(
         &mut self,
@@ -48,6 +49,29 @@ impl DrawTarget for image::RgbaImage {
         );
     }
 
+    fn draw_line(&mut self, from: I32Vec2, to: I32Vec2, color: image::Rgba<u8>) {
+        // Bresenham
+        let delta = I32Vec2::new((to.x - from.x).abs(), -(to.y - from.y).abs());
+        let step = I32Vec2::new((to.x - from.x).signum(), (to.y - from.y).signum());
+        let mut pos = from;
+        let mut error = delta.x + delta.y;
+        loop {
+            self.fill_rect(pos, I32Vec2::new(1, 1), color);
+            if pos == to {
+                break;
+            }
+            let error2 = error * 2;
+            if error2 >= delta.y {
+                error += delta.y;
+                pos.x += step.x;
+            }
+            if error2 <= delta.x {
+                error += delta.x;
+                pos.y += step.y;
+            }
+        }
+    }
+
     fn draw_image(&mut self, image: &image::RgbaImage, pos: I32Vec2, size: Option<I32Vec2>) {
         self.draw_subimage(
             image,
@@ -6,6 +6,35 @@ mod lib {
     pub mod image_draw;
     pub mod math;
     pub mod more_ui;
+    #[cfg(test)]
+    pub mod temp_dir;
+}
+
+mod cli;
+mod export {
+    pub mod codegen;
+    pub mod compression;
+    pub mod header;
+    pub mod layout;
+    pub mod ldtk;
+    pub mod pixel_format;
+    pub mod quantize;
+    pub mod reader;
+    pub mod render;
+    pub mod settings;
+    pub mod size;
+    #[cfg(test)]
+    pub mod test_project;
+    pub mod tiled;
+    pub mod tiles;
+    pub mod validate;
+    pub mod watch;
+}
+mod import {
+    pub mod aseprite;
+    pub mod atlas;
+    pub mod ldtk;
+    pub mod tiled;
 }
 
 mod project;
@@ -14,6 +43,8 @@ mod view {
     pub mod content_view;
     pub mod editor_view;
     pub mod inspector_view;
+    pub mod report_view;
+    pub mod settings_view;
 }
 
 use project::*;
@@ -21,6 +52,10 @@ use project::*;
 struct Application {
     assets: Option<Assets>,
     status: RichText,
+    /// Re-exports the project from disk when its files change
+    watcher: Option<export::watch::Watcher>,
+    /// Reloads atlas images when they are edited outside of the editor
+    image_watcher: Option<export::watch::Watcher>,
 
     #[cfg(feature = "egui_file")]
     open_dialog: Option<egui_file::FileDialog>,
@@ -31,6 +66,8 @@ impl Application {
         Self {
             assets: None,
             status: RichText::new("Ready"),
+            watcher: None,
+            image_watcher: None,
             #[cfg(feature = "egui_file")]
             open_dialog: None,
         }
@@ -64,6 +101,9 @@ impl Application {
             let mut new_assets = Assets::load(&assets.path)?;
             new_assets.content_viewer_path = assets.content_viewer_path.clone();
             new_assets.atlas_selected = assets.atlas_selected.clone();
+            if let Some(view) = &mut new_assets.atlas_selected {
+                view.preview_cache = None;
+            }
             new_assets.map_selected = assets.map_selected.clone();
             new_assets.object_selected = assets.object_selected;
             new_assets.component_selected = assets.component_selected.clone();
@@ -100,6 +140,42 @@ impl eframe::App for Application {
                         if ui.button("Export (Ctrl+E)").clicked() {
                             try_or_status!(assets.export());
                         }
+                        let mut watching = self.watcher.is_some();
+                        if ui.checkbox(&mut watching, "Watch and re-export").changed() {
+                            self.watcher =
+                                watching.then(|| export::watch::Watcher::new(&assets.path));
+                            self.status = RichText::new(if watching {
+                                "Watching the project, saved changes are exported automatically"
+                            } else {
+                                "Stopped watching the project"
+                            });
+                        }
+                        if ui.button("Size Report").clicked() {
+                            assets.size_report_open = true;
+                            ui.close_menu();
+                        }
+                        if ui.button("Validate").clicked() {
+                            assets.export_problems = crate::export::validate::validate(assets);
+                            if assets.export_problems.is_empty() {
+                                self.status = RichText::new("No problems found");
+                            }
+                        }
+                        if ui.button("Export Tiled").clicked() {
+                            try_or_status!(crate::export::tiled::export(
+                                assets,
+                                &assets.path.join("tiled")
+                            ));
+                        }
+                        if ui.button("Export LDtk").clicked() {
+                            try_or_status!(crate::export::ldtk::export(
+                                assets,
+                                &assets.path.join(crate::export::ldtk::PROJECT_FILE)
+                            ));
+                        }
+                        if ui.button("Export Settings").clicked() {
+                            assets.export_settings_open = true;
+                            ui.close_menu();
+                        }
                         if ui.button("Reload (F5)").clicked() {
                             try_or_status!(self.reload_project());
                         }
@@ -107,6 +183,79 @@ impl eframe::App for Application {
                 });
             })
         });
+        // Atlas images edited outside of the editor
+        if let Some(assets) = &mut self.assets {
+            let watcher = self.image_watcher.get_or_insert_with(|| {
+                export::watch::Watcher::with_extensions(&assets.path, &ATLAS_EXTENSIONS)
+            });
+            if watcher.path != assets.path {
+                *watcher = export::watch::Watcher::with_extensions(&assets.path, &ATLAS_EXTENSIONS);
+            }
+            // Without atlases there is nothing to reload, so no need to keep waking up
+            if !assets.atlases.is_empty() {
+                ctx.request_repaint_after(export::watch::POLL_INTERVAL);
+            }
+            let changed = watcher.poll();
+            let (reloaded, warnings, errors) = export::watch::reload_atlases(assets, &changed);
+            if !reloaded.is_empty() {
+                if let Some(view) = &mut assets.atlas_selected {
+                    if reloaded.contains(&view.atlas) {
+                        view.preview_cache = None;
+                    }
+                }
+                let paths = reloaded
+                    .iter()
+                    .map(|uuid| assets.atlases[uuid].path.clone())
+                    .collect::<Vec<_>>();
+                let reloaded = export::watch::describe(&assets.path, &paths);
+                self.status = if warnings.is_empty() {
+                    RichText::new(format!("Reloaded {}", reloaded))
+                } else {
+                    RichText::new(format!(
+                        "Reloaded {}, {} warnings in the import report",
+                        reloaded,
+                        warnings.len()
+                    ))
+                    .color(Color32::YELLOW)
+                };
+                assets.import_warnings.extend(warnings);
+            }
+            for err in &errors {
+                println!("{:?}", err);
+            }
+            if let Some(err) = errors.first() {
+                self.status = RichText::new(format!("{:#}", err)).color(Color32::RED);
+            }
+        }
+
+        // Watch mode
+        if let (Some(watcher), Some(assets)) = (&mut self.watcher, &mut self.assets) {
+            if watcher.path != assets.path {
+                *watcher = export::watch::Watcher::new(&assets.path);
+            }
+            ctx.request_repaint_after(export::watch::POLL_INTERVAL);
+            let changed = watcher.poll();
+            if !changed.is_empty() {
+                let changed = export::watch::describe(&assets.path, &changed);
+                let (problems, result) = export::watch::reexport(&assets.path, None);
+                assets.export_problems = problems;
+                match result {
+                    Ok(output) => {
+                        self.status =
+                            RichText::new(format!("{} changed, exported to {:?}", changed, output))
+                    }
+                    Err(err) => {
+                        self.status = RichText::new(format!(
+                            "{} changed, but the export failed: {:#}",
+                            changed, err
+                        ))
+                        .color(Color32::RED);
+                        println!("{:?}", err);
+                    }
+                }
+            }
+        }
+
         // Status bar
         TopBottomPanel::bottom("status_bar").show(ctx, |ui| ui.label(self.status.clone()));
 
@@ -130,6 +279,8 @@ impl eframe::App for Application {
 
                 try_or_status!(view::editor_view::show(ui, assets, &mut self.status));
             });
+            view::settings_view::show(ctx, assets);
+            view::report_view::show(ctx, assets);
 
             // Keys
             if ctx.input_mut(|input| {
@@ -183,6 +334,16 @@ impl eframe::App for Application {
 
 fn main() -> Result<(), eframe::Error> {
     env_logger::init();
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if !args.is_empty() {
+        if let Err(err) = cli::run(&args) {
+            eprintln!("Error: {:?}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     eframe::run_native(
         "OreMapper",
         Default::default(),
@@ -4,19 +4,22 @@ pub use crate::lib::math::*;
 pub use crate::lib::more_ui::*;
 
 pub use anyhow::{bail, Context, Result};
-pub use byteorder::{LittleEndian, WriteBytesExt};
 pub use serde::{Deserialize, Serialize};
 pub use std::path::{Path, PathBuf};
 pub use uuid::Uuid;
 
+pub use crate::export::settings::ExportSettings;
 pub use crate::view::{
-    atlas_view::{Atlas, AtlasView},
+    atlas_view::{
+        Animation, Atlas, AtlasView, Collision, CollisionShape, ANIMATION_FPS, ATLAS_EXTENSIONS,
+    },
     editor_view::{Map, MapView},
     inspector_view::{Component, ComponentView, Property},
     inspector_view::{ATLAS_RENDERER_UUID, SERIALIZE_UUID},
 };
 
-use std::collections::HashMap;
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
 
 // * --------------------------------------------------------------------------------- ASSETS --------------------------------------------------------------------------------- * //
 #[derive(Default)]
@@ -43,26 +46,100 @@ pub struct Assets {
     pub components: HashMap<Uuid, Component>,
 
     pub atlas_indices: HashMap<Uuid, u16>,
+    /// Exported tile index for every tile of an atlas, filled when tiles are merged or stripped
+    pub tile_indices: HashMap<Uuid, Vec<u16>>,
+    pub export_settings: ExportSettings,
+    pub export_settings_open: bool,
+    /// What the last import had to skip or change, shown until closed
+    pub import_warnings: Vec<String>,
+    /// Problems found by the last export or validation, shown until closed
+    pub export_problems: Vec<crate::export::validate::Problem>,
+    /// What the last export is made of
+    pub export_sizes: crate::export::size::SizeReport,
+    pub size_report_open: bool,
 }
 
 impl Assets {
-    pub fn load(path: &Path) -> Result<Self> {
-        let mut uuids = HashMap::new();
-        let mut atlases = HashMap::new();
-        let mut maps = HashMap::new();
+    pub fn new(path: &Path) -> Self {
         let mut components = HashMap::new();
+        components.insert(
+            ATLAS_RENDERER_UUID,
+            Component {
+                path: "/\nbuiltin/0 - AtlasRenderer".into(),
+                export_id: 0,
+                properties: [("Atlas".to_owned(), Property::Atlas)]
+                    .into_iter()
+                    .collect(),
+            },
+        );
+        components.insert(
+            SERIALIZE_UUID,
+            Component {
+                path: "/\nbuiltin/1 - Serialize".into(),
+                export_id: 1,
+                properties: indexmap::IndexMap::new(),
+            },
+        );
+
+        Self {
+            path: path.to_path_buf(),
+            content_viewer_path: path.to_path_buf(),
+            new_map: None,
+            new_component_name: None,
+
+            atlas_selected: None,
+            map_selected: None,
+            object_selected: None,
+            component_selected: None,
+
+            uuids: HashMap::new(),
+            atlases: HashMap::new(),
+            maps: HashMap::new(),
+            components,
+
+            atlas_indices: HashMap::new(),
+            tile_indices: HashMap::new(),
+            export_settings: ExportSettings::default(),
+            export_settings_open: false,
+            import_warnings: Vec::new(),
+            export_problems: Vec::new(),
+            export_sizes: Default::default(),
+            size_report_open: false,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut assets = Self::new(path);
+        assets.export_settings = ExportSettings::load(path)?;
 
         macro_rules! load_uuids {
             ($target: ident, $type: ty, $file: literal, $load_error: literal, $deserialize_error: literal) => {
                 if path.join("atlases.json").exists() {
-                    for (uuid, path) in serde_json::from_str::<HashMap<Uuid, PathBuf>>(
+                    let entries = serde_json::from_str::<HashMap<Uuid, RegistryEntry>>(
                         &std::fs::read_to_string(path.join($file))
                             .context(format!($load_error, path.join($file)))?,
                     )
-                    .context($deserialize_error)?
+                    .context($deserialize_error)?;
+
+                    // Assets without an ID get free ones in path order, same as older exports
+                    let mut used_ids = assets
+                        .$target
+                        .values()
+                        .map(|asset| asset.export_id)
+                        .chain(entries.values().filter_map(RegistryEntry::export_id))
+                        .collect::<HashSet<_>>();
+                    for (uuid, entry) in entries
+                        .into_iter()
+                        .sorted_by(|a, b| a.1.path().cmp(b.1.path()))
                     {
-                        $target.insert(uuid, <$type>::load(&path)?);
-                        uuids.insert(path, uuid);
+                        let mut asset = <$type>::load(entry.path())?;
+                        asset.export_id = entry.export_id().unwrap_or_else(|| {
+                            let id = free_export_id(used_ids.iter().copied());
+                            used_ids.insert(id);
+                            id
+                        });
+                        assets.$target.insert(uuid, asset);
+                        assets.uuids.insert(entry.path().to_path_buf(), uuid);
                     }
                 }
             };
@@ -90,41 +167,7 @@ impl Assets {
             "Failed to deserialize component list!"
         );
 
-        components.insert(
-            ATLAS_RENDERER_UUID,
-            Component {
-                path: "/\nbuiltin/0 - AtlasRenderer".into(),
-                properties: [("Atlas".to_owned(), Property::Atlas)]
-                    .into_iter()
-                    .collect(),
-            },
-        );
-        components.insert(
-            SERIALIZE_UUID,
-            Component {
-                path: "/\nbuiltin/1 - Serialize".into(),
-                properties: indexmap::IndexMap::new(),
-            },
-        );
-
-        Ok(Self {
-            path: path.to_path_buf(),
-            content_viewer_path: path.to_path_buf(),
-            new_map: None,
-            new_component_name: None,
-
-            atlas_selected: None,
-            map_selected: None,
-            object_selected: None,
-            component_selected: None,
-
-            uuids,
-            atlases,
-            maps,
-            components,
-
-            atlas_indices: HashMap::new(),
-        })
+        Ok(assets)
     }
 
     pub fn save(&self) -> Result<()> {
@@ -136,7 +179,12 @@ impl Assets {
                         &self
                             .$target
                             .iter()
-                            .map(|(uuid, $name)| (uuid, $name.path.clone()))
+                            .map(|(uuid, $name)| {
+                                (
+                                    uuid,
+                                    RegistryEntry::new($name.path.clone(), $name.export_id),
+                                )
+                            })
                             .collect::<HashMap<_, _>>(),
                     )
                     .context($serialize_error)?,
@@ -169,7 +217,10 @@ impl Assets {
                     .iter()
                     .filter_map(|(uuid, component)| {
                         if !component.path.starts_with("/\nbuiltin/") {
-                            Some((uuid, component.path.clone()))
+                            Some((
+                                uuid,
+                                RegistryEntry::new(component.path.clone(), component.export_id),
+                            ))
                         } else {
                             None
                         }
@@ -180,6 +231,8 @@ impl Assets {
         )
         .context("Failed to save component list!")?;
 
+        self.export_settings.save(&self.path)?;
+
         // * All
         for (_, atlas) in self.atlases.iter() {
             atlas.save()?;
@@ -194,12 +247,173 @@ impl Assets {
     }
 
     pub fn export(&mut self) -> Result<()> {
-        let mut file = std::fs::File::create(self.path.join("data.dat"))
-            .context("Failed to create data file.")?;
-        crate::view::atlas_view::export(self, &mut file).context("Failed to serialize atlases!")?;
-        crate::view::editor_view::export(self, &mut file).context("Failed to serialize maps!")?;
+        let path = self.path.join("data.dat");
+        self.export_to(&path)
+    }
+
+    /// Nothing is written unless the whole export succeeds, so a failed export keeps the last good files
+    pub fn export_to(&mut self, path: &Path) -> Result<()> {
+        let mut data = Vec::new();
+        self.export_data(&mut data)?;
+        let settings = &self.export_settings;
+        let header = (settings.generate_c_header)
+            .then(|| crate::export::codegen::c_header(self))
+            .transpose()?;
+        let module = (settings.generate_rust_module)
+            .then(|| crate::export::codegen::rust_module(self))
+            .transpose()?;
+
+        replace_file(path, &data).context(format!("Failed to write data file {:?}!", path))?;
+        if let Some(header) = header {
+            let path = path.with_extension("h");
+            replace_file(&path, header.as_bytes())
+                .context(format!("Failed to write C header {:?}!", path))?;
+        }
+        if let Some(module) = module {
+            let path = path.with_extension("rs");
+            replace_file(&path, module.as_bytes())
+                .context(format!("Failed to write Rust module {:?}!", path))?;
+        }
         Ok(())
     }
+
+    pub fn export_data<W: std::io::Write>(&mut self, file: &mut W) -> Result<()> {
+        use crate::export::header::*;
+        use crate::export::layout::DataWriter;
+
+        self.export_problems = crate::export::validate::validate(self);
+        crate::export::validate::check(&self.export_problems)?;
+        self.export_sizes = Default::default();
+        let mut atlases = DataWriter::new(&self.export_settings);
+        crate::view::atlas_view::export(self, &mut atlases)
+            .context("Failed to serialize atlases!")?;
+        let mut maps = DataWriter::new(&self.export_settings);
+        crate::view::editor_view::export(self, &mut maps).context("Failed to serialize maps!")?;
+
+        let sections = [
+            Section::new(ATLASES_SECTION, atlases.data)
+                .compressed(self.export_settings.atlas_compression),
+            Section::new(MAPS_SECTION, maps.data).compressed(self.export_settings.map_compression),
+        ];
+        let mut data = Vec::new();
+        write(&mut data, &sections, &self.export_settings).context("Failed to write data file!")?;
+
+        // The budget can only be checked once the size is known
+        self.export_sizes.finish(&sections, data.len());
+        self.export_problems
+            .extend(crate::export::size::check_budget(
+                &self.export_settings,
+                &self.path,
+                data.len(),
+            ));
+        crate::export::validate::check(&self.export_problems)?;
+        file.write_all(&data).context("Failed to write data file!")
+    }
+
+    pub fn map_indices(&self) -> HashMap<Uuid, u16> {
+        self.maps
+            .iter()
+            .map(|(uuid, map)| (*uuid, map.export_id))
+            .collect()
+    }
+
+    pub fn component_indices(&self) -> HashMap<Uuid, u16> {
+        self.components
+            .iter()
+            .map(|(uuid, component)| (*uuid, component.export_id))
+            .collect()
+    }
+
+    pub fn check_export_ids(&self) -> Result<()> {
+        check_export_ids(
+            "Atlases",
+            self.atlases
+                .values()
+                .map(|atlas| (atlas.export_id, &atlas.path)),
+        )?;
+        check_export_ids(
+            "Maps",
+            self.maps.values().map(|map| (map.export_id, &map.path)),
+        )?;
+        check_export_ids(
+            "Components",
+            self.components
+                .values()
+                .map(|component| (component.export_id, &component.path)),
+        )
+    }
+}
+
+/// Smallest export ID that is not used yet
+/// Writes next to the file and renames over it, so it is never left half written
+fn replace_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)
+}
+
+pub fn free_export_id(used: impl Iterator<Item = u16>) -> u16 {
+    let used = used.collect::<HashSet<_>>();
+    (0..MAX_EXPORT_ID)
+        .find(|id| !used.contains(id))
+        .unwrap_or(MAX_EXPORT_ID)
+}
+
+/// 0xFFFF is left free, it marks missing tiles and references in data.dat
+pub const MAX_EXPORT_ID: u16 = 0xFFFE;
+
+fn check_export_ids<'a>(
+    kind: &str,
+    assets: impl Iterator<Item = (u16, &'a PathBuf)>,
+) -> Result<()> {
+    let mut used = HashMap::new();
+    for (id, path) in assets {
+        if id > MAX_EXPORT_ID {
+            bail!("{} can't use export ID {}! File: {:?}!", kind, id, path);
+        }
+        if let Some(other) = used.insert(id, path) {
+            bail!(
+                "{} {:?} and {:?} have the same export ID {}!",
+                kind,
+                other,
+                path,
+                id
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Value of atlases.json, maps.json and components.json
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RegistryEntry {
+    Asset {
+        path: PathBuf,
+        export_id: u16,
+    },
+    /// Projects saved before export IDs only stored the path
+    Path(PathBuf),
+}
+
+impl RegistryEntry {
+    fn new(path: PathBuf, export_id: u16) -> Self {
+        Self::Asset { path, export_id }
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Self::Asset { path, .. } | Self::Path(path) => path,
+        }
+    }
+
+    fn export_id(&self) -> Option<u16> {
+        match self {
+            Self::Asset { export_id, .. } => Some(*export_id),
+            Self::Path(_) => None,
+        }
+    }
 }
 
 // * --------------------------------------------------------------------------------- VECTORS -------------------------------------------------------------------------------- * //
@@ -255,3 +469,120 @@ impl_vector_cast_for_primitive!(u64);
 impl_vector_cast_for_primitive!(u128);
 impl_vector_cast_for_primitive!(f32);
 impl_vector_cast_for_primitive!(f64);
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::reader::*;
+    use crate::export::test_project::*;
+    use crate::lib::temp_dir::temp_dir;
+
+    #[test]
+    fn export_ids() {
+        let mut assets = test_project();
+        for map in assets.maps.values_mut() {
+            if map.export_id == 1 {
+                map.export_id = 3;
+            }
+        }
+        let file = export_and_read(&mut assets);
+        assert_eq!(file.maps.len(), 4);
+        for gap in &file.maps[1..3] {
+            assert_eq!(gap.size, U16Vec2::zeros());
+            assert_eq!(gap.atlas, 0xFFFF);
+            assert!(gap.objects.is_empty());
+        }
+        assert_eq!(file.maps[3].tiles, vec![0xFFFF]);
+        assert_eq!(
+            file.maps[0].objects[0].components[0].values[0],
+            ("target".to_owned(), Value::Map(3))
+        );
+
+        for map in assets.maps.values_mut() {
+            map.export_id = 0;
+        }
+        let mut data = Vec::new();
+        assert!(assets.export_data(&mut data).is_err());
+    }
+
+    #[test]
+    fn failed_export_keeps_files() {
+        let dir = temp_dir();
+        let path = dir.join("data.dat");
+        let mut assets = test_project();
+        assets.export_settings.generate_c_header = true;
+        assets.export_to(&path).unwrap();
+        let (data, header) = (std::fs::read(&path).unwrap(), path.with_extension("h"));
+        assert_eq!(data, export(&mut assets));
+
+        // Two maps with the same export ID fail validation
+        for map in assets.maps.values_mut() {
+            map.export_id = 0;
+        }
+        std::fs::remove_file(&header).unwrap();
+        assert!(assets.export_to(&path).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        assert!(!header.exists());
+        assert_eq!(std::fs::read_dir(&*dir).unwrap().count(), 1);
+    }
+
+    /// Run with UPDATE_GOLDEN=1 to rewrite the golden files after an intended format change
+    #[test]
+    fn golden_files() {
+        let mut assets = golden_project();
+        let data = export(&mut assets);
+        // Another copy iterates its HashMaps in a different order
+        assert_eq!(export(&mut golden_project()), data);
+
+        let level = &read(&data, &component_schemas(&assets)).unwrap().maps[0];
+        assert_eq!(
+            level
+                .objects
+                .iter()
+                .map(|object| (
+                    object.pos,
+                    object
+                        .components
+                        .iter()
+                        .map(|component| component.index)
+                        .collect::<Vec<_>>()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (I32Vec2::new(5, -7), vec![2]),
+                (I32Vec2::new(8, 0), vec![0, 1, 2]),
+                (I32Vec2::new(0, 4), vec![0, 2]),
+                (I32Vec2::new(0, 4), vec![0, 2]),
+            ]
+        );
+
+        let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        for (name, output) in [
+            ("data.dat", data),
+            (
+                "data.h",
+                crate::export::codegen::c_header(&assets)
+                    .unwrap()
+                    .into_bytes(),
+            ),
+            (
+                "data.rs",
+                crate::export::codegen::rust_module(&assets)
+                    .unwrap()
+                    .into_bytes(),
+            ),
+        ] {
+            let path = golden.join(name);
+            if std::env::var_os("UPDATE_GOLDEN").is_some() {
+                std::fs::create_dir_all(&golden).unwrap();
+                std::fs::write(&path, &output).unwrap();
+            }
+            assert!(
+                std::fs::read(&path).unwrap() == output,
+                "Export differs from {:?}, run with UPDATE_GOLDEN=1 if the change is intended",
+                path
+            );
+        }
+    }
+}
@@ -1,22 +1,124 @@
+use crate::export::layout::DataWriter;
+use crate::export::pixel_format::*;
+use crate::export::quantize::*;
 use crate::project::*;
+use indexmap::IndexMap;
 use itertools::Itertools;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 // * ---------------------------------------------------------------------------------- ATLAS --------------------------------------------------------------------------------- * //
+/// Game frames per second the editor plays animations at
+pub const ANIMATION_FPS: f64 = 60.0;
+/// Images the content view opens as atlases
+pub const ATLAS_EXTENSIONS: [&str; 8] =
+    ["png", "bmp", "gif", "tga", "qoi", "webp", "ase", "aseprite"];
+
+/// Decodes an atlas image, Aseprite files are flattened with their frames side by side
+pub fn load_image(path: &Path) -> Result<image::RgbaImage> {
+    if crate::import::aseprite::is_aseprite(path) {
+        Ok(crate::import::aseprite::load(path)?.image)
+    } else {
+        Ok(image::open(path)
+            .context(format!("Failed to load image {:?}!", path))?
+            .to_rgba8())
+    }
+}
+
+/// Tile that cycles through frames while the game runs
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Animation {
+    /// Tile index maps use, row by row
+    pub tile: u16,
+    /// Tile indices shown one after another, usually starting with `tile`
+    pub frames: Vec<u16>,
+    /// Game frames every animation frame is shown for
+    pub frame_time: u16,
+}
+
+/// Collision shape of a tile, in pixels from the tile's top left corner
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub enum CollisionShape {
+    Rect {
+        pos: U16Vec2,
+        size: U16Vec2,
+    },
+    /// Corners in order, at least 3
+    Polygon(Vec<U16Vec2>),
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Collision {
+    pub shape: CollisionShape,
+    /// Only blocks from above, like a platform that can be jumped onto from below
+    #[serde(default)]
+    pub one_way: bool,
+}
+
+impl Collision {
+    /// Outline of the shape, scaled from tile pixels, solid shapes in red and one-way ones in blue
+    pub fn draw(&self, to: &mut image::RgbaImage, pos: I32Vec2, scale: F32Vec2) {
+        let color = if self.one_way {
+            image::Rgba([0, 128, 255, 255])
+        } else {
+            image::Rgba([255, 0, 0, 255])
+        };
+        let point = |point: &U16Vec2| pos + point.casted::<f32>().component_mul(&scale).casted();
+        match &self.shape {
+            CollisionShape::Rect { pos: corner, size } => {
+                let start = point(corner);
+                let end = point(&corner.zip_map(size, u16::saturating_add));
+                to.draw_rect(start, end - start, color, 1)
+            }
+            CollisionShape::Polygon(points) => {
+                for (from, to_point) in points.iter().circular_tuple_windows() {
+                    to.draw_line(point(from), point(to_point), color);
+                }
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Atlas {
     #[serde(skip)]
     pub path: PathBuf,
     #[serde(skip)]
     pub image: image::RgbaImage,
+    #[serde(skip)]
+    pub export_id: u16,
     #[serde(default)]
     pub tile_size: U16Vec2,
+    /// Pixels before the first tile row and column
+    #[serde(default)]
+    pub margin: u16,
+    /// Pixels between neighbouring tiles
+    #[serde(default)]
+    pub spacing: u16,
+    /// Overrides project's pixel format
+    #[serde(default)]
+    pub pixel_format: Option<PixelFormat>,
+    /// Overrides project's transparency
+    #[serde(default)]
+    pub transparency: Option<Transparency>,
+    /// Number of colors the atlas is reduced to on export, including the color key
+    #[serde(default)]
+    pub color_limit: Option<u16>,
+    #[serde(default)]
+    pub animations: Vec<Animation>,
+    /// Properties every tile has, like a component's
+    #[serde(default)]
+    pub tile_properties: IndexMap<String, Property>,
+    /// Property values by tile index, missing values are exported as the type's default
+    #[serde(default)]
+    pub tile_values: BTreeMap<u16, HashMap<String, String>>,
+    /// Collision shapes by tile index
+    #[serde(default)]
+    pub collisions: BTreeMap<u16, Vec<Collision>>,
 }
 
 impl Atlas {
     pub fn load(path: &Path) -> Result<Self> {
-        let image = image::open(path.to_str().unwrap())
-            .context("Failed to load IMAGE!")?
-            .to_rgba8();
+        let image = load_image(path)?;
 
         let atl_path = path.with_extension("atl");
         if atl_path.exists() {
@@ -31,11 +133,25 @@ impl Atlas {
             })
         } else {
             let tile_size = TVec2::new(image.width(), image.height()).casted();
-            Ok(Self {
-                path: path.to_path_buf(),
-                image,
-                tile_size,
-            })
+            Ok(Self::new(path, image, tile_size))
+        }
+    }
+
+    pub fn new(path: &Path, image: image::RgbaImage, tile_size: U16Vec2) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            image,
+            export_id: 0,
+            tile_size,
+            margin: 0,
+            spacing: 0,
+            pixel_format: None,
+            transparency: None,
+            color_limit: None,
+            animations: Vec::new(),
+            tile_properties: IndexMap::new(),
+            tile_values: BTreeMap::new(),
+            collisions: BTreeMap::new(),
         }
     }
 
@@ -49,12 +165,73 @@ impl Atlas {
         Ok(())
     }
 
+    /// Whole tiles in a row, the last tile needs no spacing after it
     pub fn width(&self) -> u16 {
-        (self.image.width() / self.tile_size.x as u32) as u16
+        ((self.image.width().saturating_sub(self.margin as u32) + self.spacing as u32)
+            / (self.tile_size.x as u32 + self.spacing as u32)) as u16
     }
 
+    /// Whole tiles in a column, the last tile needs no spacing after it
     pub fn height(&self) -> u16 {
-        (self.image.height() / self.tile_size.y as u32) as u16
+        ((self.image.height().saturating_sub(self.margin as u32) + self.spacing as u32)
+            / (self.tile_size.y as u32 + self.spacing as u32)) as u16
+    }
+
+    /// Whether a map tile is in the image. Empty tiles are not, neither are tiles of a bigger image it used to have.
+    pub fn contains_tile(&self, tile: I16Vec2) -> bool {
+        tile >= TVec2::zeros() && (tile.x as u16) < self.width() && (tile.y as u16) < self.height()
+    }
+
+    /// Pixel position of the tile's top left corner in the image
+    pub fn tile_pos(&self, tile: U32Vec2) -> U32Vec2 {
+        tile.component_mul(&(self.tile_size.casted() + U32Vec2::repeat(self.spacing as u32)))
+            + U32Vec2::repeat(self.margin as u32)
+    }
+
+    /// Tile under the pixel of the image, the margin belongs to the first tiles and spacing to the tile before it
+    pub fn tile_at(&self, pixel: U32Vec2) -> U32Vec2 {
+        let step = self.tile_size.casted() + U32Vec2::repeat(self.spacing as u32);
+        let last =
+            U32Vec2::new(self.width() as u32, self.height() as u32).map(|size| size.max(1) - 1);
+        pixel
+            .map(|pixel| pixel.saturating_sub(self.margin as u32))
+            .component_div(&step)
+            .zip_map(&last, |tile, last| tile.min(last))
+    }
+
+    /// Tile indices of the selection, row by row
+    pub fn selected_tiles(&self, view: &AtlasView) -> Vec<u16> {
+        let width = self.width() as u32;
+        (0..view.selection_size.y)
+            .flat_map(|y| (0..view.selection_size.x).map(move |x| U32Vec2::new(x, y)))
+            .map(|tile| {
+                let tile = view.selection_pos + tile;
+                (tile.x + tile.y * width) as u16
+            })
+            .collect()
+    }
+
+    /// Tile shown `frame` game frames after the start, tiles without an animation stay the same
+    pub fn animated_tile(&self, tile: U32Vec2, frame: u64) -> U32Vec2 {
+        let width = self.width().max(1) as u32;
+        let index = tile.x + tile.y * width;
+        match self
+            .animations
+            .iter()
+            .find(|animation| animation.tile as u32 == index)
+        {
+            Some(animation) if !animation.frames.is_empty() && animation.frame_time > 0 => {
+                let frame = animation.frames
+                    [(frame / animation.frame_time as u64) as usize % animation.frames.len()]
+                    as u32;
+                // Frames outside of the atlas are reported by validation, the tile stays still instead
+                if frame >= width * self.height() as u32 {
+                    return tile;
+                }
+                U32Vec2::new(frame % width, frame / width)
+            }
+            _ => tile,
+        }
     }
 
     pub fn draw_tile(&self, to: &mut image::RgbaImage, pos: I32Vec2, tile: U32Vec2, size: I32Vec2) {
@@ -62,25 +239,78 @@ impl Atlas {
             &self.image,
             pos.casted(),
             size,
-            tile.component_mul(&self.tile_size.casted()),
+            self.tile_pos(tile),
             self.tile_size.casted(),
         );
     }
 }
 
+/// Everything the export preview depends on besides the pixels
+type PreviewKey = (
+    U16Vec2,
+    (u16, u16),
+    PixelFormat,
+    Transparency,
+    Option<u16>,
+    [[u8; 3]; 4],
+    (u32, u32),
+);
+
 #[derive(Clone)]
 pub struct AtlasView {
     pub atlas: Uuid,
+    pub camera: I32Vec2,
+    /// Screen pixels per image pixel, 0 fits the whole atlas into the panel the next time it is shown
+    pub scale: f32,
+    pub hover_tile: Option<U32Vec2>,
+    /// Outline every tile
+    pub show_grid: bool,
     pub selection_pos: U32Vec2,
     pub selection_size: U32Vec2,
+    /// Show the atlas as it is exported, with the tiles changed by color reduction marked
+    pub preview: bool,
+    /// Quantized atlas, cleared when the image changes
+    pub preview_cache: Option<(PreviewKey, Quantized)>,
+    /// Tile property being added in the inspector
+    pub adding: Option<(String, Property)>,
+    /// The collision editor draws polygons instead of rectangles
+    pub polygon_tool: bool,
+    /// New collision shapes are one-way
+    pub one_way: bool,
+    /// Corners of the shape being drawn: start and current corner of a rectangle, or the polygon so far
+    pub drawing: Vec<U16Vec2>,
 }
 
 impl AtlasView {
+    /// Keeps the selection inside of the atlas, after its tile size, margin, spacing or image changed
+    pub fn clamp_selection(&mut self, atlas: &Atlas) {
+        let tiles =
+            U32Vec2::new(atlas.width() as u32, atlas.height() as u32).map(|tiles| tiles.max(1));
+        self.selection_pos = self
+            .selection_pos
+            .zip_map(&tiles, |pos, tiles| pos.min(tiles - 1));
+        self.selection_size =
+            self.selection_size
+                .zip_zip_map(&self.selection_pos, &tiles, |size, pos, tiles| {
+                    size.clamp(1, tiles - pos)
+                });
+    }
+
     pub fn new(atlas: Uuid) -> Self {
         Self {
             atlas,
+            camera: TVec2::zeros(),
+            scale: 0.0,
+            hover_tile: None,
+            show_grid: true,
             selection_pos: U32Vec2::zeros(),
             selection_size: U32Vec2::new(1, 1),
+            preview: false,
+            preview_cache: None,
+            adding: None,
+            polygon_tool: false,
+            one_way: false,
+            drawing: Vec::new(),
         }
     }
 }
@@ -88,54 +318,300 @@ impl AtlasView {
 // * ---------------------------------------------------------------------------------- SHOW ---------------------------------------------------------------------------------- * //
 pub fn show(ui: &mut Ui, assets: &mut Assets) {
     if let Some(view) = assets.atlas_selected.as_mut() {
+        let taken_ids = assets
+            .atlases
+            .iter()
+            .filter(|(uuid, _)| **uuid != view.atlas)
+            .map(|(_, atlas)| atlas.export_id)
+            .collect();
         if let Some(atlas) = assets.atlases.get_mut(&view.atlas) {
             ui.horizontal(|ui| {
                 ui.add(egui::DragValue::new(&mut atlas.tile_size.x).clamp_range(1..=65535));
                 ui.add(egui::DragValue::new(&mut atlas.tile_size.y).clamp_range(1..=65535));
+                ui.label("Margin");
+                ui.add(egui::DragValue::new(&mut atlas.margin));
+                ui.label("Spacing");
+                ui.add(egui::DragValue::new(&mut atlas.spacing));
+                ui.separator();
+
+                crate::view::settings_view::export_id_edit(ui, &mut atlas.export_id, &taken_ids);
+                ui.separator();
+
+                let mut custom = atlas.pixel_format.is_some();
+                ui.checkbox(&mut custom, "Pixel format");
+                if custom {
+                    let pixel_format = atlas
+                        .pixel_format
+                        .get_or_insert(assets.export_settings.pixel_format);
+                    crate::view::settings_view::pixel_format_select(
+                        ui,
+                        pixel_format,
+                        "atlas_pixel_format",
+                    );
+                } else {
+                    atlas.pixel_format = None;
+                }
+
+                let mut custom = atlas.transparency.is_some();
+                ui.checkbox(&mut custom, "Transparency");
+                if custom {
+                    let transparency = atlas
+                        .transparency
+                        .get_or_insert(assets.export_settings.transparency);
+                    crate::view::settings_view::transparency_select(
+                        ui,
+                        transparency,
+                        "atlas_transparency",
+                    );
+                } else {
+                    atlas.transparency = None;
+                }
+                ui.separator();
+
+                let mut limited = atlas.color_limit.is_some();
+                ui.checkbox(&mut limited, "Color limit");
+                if limited {
+                    let limit = atlas.color_limit.get_or_insert(16);
+                    ui.add(egui::DragValue::new(limit).clamp_range(2..=256));
+                } else {
+                    atlas.color_limit = None;
+                }
+                ui.checkbox(&mut view.preview, "Preview export");
             });
+            view.clamp_selection(atlas);
 
-            let scale = (ui.available_size().x / atlas.image.width() as f32)
-                .min(ui.available_size().y / atlas.image.height() as f32);
-            let tile_size = atlas.tile_size.casted() * scale;
+            ui.collapsing(format!("Animations ({})", atlas.animations.len()), |ui| {
+                if ui
+                    .button("Animate selection")
+                    .on_hover_text(
+                        "Selected tiles become the frames, the first one is the animated tile",
+                    )
+                    .clicked()
+                {
+                    let frames = atlas.selected_tiles(view);
+                    atlas
+                        .animations
+                        .retain(|animation| animation.tile != frames[0]);
+                    atlas.animations.push(Animation {
+                        tile: frames[0],
+                        frames,
+                        frame_time: 8,
+                    });
+                }
+                let mut removed = None;
+                for (index, animation) in atlas.animations.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "Tile {}: {}",
+                            animation.tile,
+                            animation.frames.iter().join(", ")
+                        ));
+                        ui.add(
+                            egui::DragValue::new(&mut animation.frame_time)
+                                .clamp_range(1..=65535)
+                                .suffix(" frames each"),
+                        );
+                        if ui.button("Remove").clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                }
+                if let Some(index) = removed {
+                    atlas.animations.remove(index);
+                }
+            });
 
-            let mut image = EguiImage::new(
-                (atlas.image.width() as f32 * scale) as u32,
-                (atlas.image.height() as f32 * scale) as u32,
+            let shapes = atlas.collisions.values().map(Vec::len).sum::<usize>();
+            ui.collapsing(format!("Collision ({} shapes)", shapes), |ui| {
+                collision_editor(ui, atlas, view);
+            });
+
+            let settings = &assets.export_settings;
+            let pixel_format = atlas.pixel_format.unwrap_or(settings.pixel_format);
+            let key = (
+                atlas.tile_size,
+                (atlas.margin, atlas.spacing),
+                pixel_format,
+                atlas.transparency.unwrap_or(settings.transparency),
+                atlas.color_limit,
+                settings.palette,
+                atlas.image.dimensions(),
             );
+            let preview = if view.preview && pixel_format.check(key.0, key.3).is_ok() {
+                if view.preview_cache.as_ref().map(|(cached, _)| cached) != Some(&key) {
+                    view.preview_cache = Some((key, quantize_atlas(atlas, settings)));
+                }
+                view.preview_cache.as_ref().map(|(_, quantized)| quantized)
+            } else {
+                None
+            };
+            if let Some(quantized) = preview {
+                ui.label(match quantized.limit {
+                    Some(limit) => format!(
+                        "{} colors, limit {}, {} tiles changed",
+                        quantized.colors,
+                        limit,
+                        quantized
+                            .changed_tiles
+                            .iter()
+                            .filter(|changed| **changed)
+                            .count()
+                    ),
+                    None => format!("{} colors", quantized.colors),
+                });
+            }
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut view.show_grid, "Grid");
+                if ui.button("Fit").clicked() {
+                    view.scale = 0.0;
+                }
+                ui.label(format!("Zoom: {:.0}%", view.scale * 100.0));
+            });
+
+            let mut image = EguiImage::new(ui.available_size().x as _, ui.available_size().y as _);
+            if view.scale <= 0.0 {
+                view.scale = (ui.available_size().x / atlas.image.width().max(1) as f32)
+                    .min(ui.available_size().y / atlas.image.height().max(1) as f32);
+                view.camera = TVec2::zeros();
+            }
+            let (scale, camera) = (view.scale, view.camera);
+            // Rectangle on screen from the first tile to the last one
+            let tiles_rect = |first: U32Vec2, last: U32Vec2| {
+                let pos = (atlas.tile_pos(first).casted::<f32>() * scale).casted::<i32>();
+                let end = ((atlas.tile_pos(last) + atlas.tile_size.casted()).casted::<f32>()
+                    * scale)
+                    .casted::<i32>();
+                (pos - camera, end - pos)
+            };
 
             image.draw(|canvas| {
                 canvas.draw_image(
-                    &atlas.image,
-                    I32Vec2::zeros(),
-                    Some(I32Vec2::new(canvas.width() as _, canvas.height() as _)),
+                    preview.map_or(&atlas.image, |quantized| &quantized.image),
+                    -camera,
+                    Some(
+                        (U32Vec2::new(atlas.image.width(), atlas.image.height()).casted::<f32>()
+                            * scale)
+                            .casted(),
+                    ),
                 );
-                canvas.draw_rect(
-                    view.selection_pos
-                        .casted()
-                        .component_mul(&tile_size)
-                        .casted(),
-                    view.selection_size
-                        .casted()
-                        .component_mul(&tile_size)
-                        .casted(),
-                    image::Rgba([255, 0, 0, 255]),
-                    3,
+                // Only the tiles on screen, and only while the lines don't cover the tiles
+                if view.show_grid && atlas.tile_size.casted::<f32>().min() * scale >= 4.0 {
+                    let first =
+                        atlas.tile_at((camera.casted::<f32>() / scale).map(|pixel| pixel as u32));
+                    let last = atlas.tile_at(
+                        ((camera + canvas.size().casted()).casted::<f32>() / scale)
+                            .map(|pixel| pixel as u32),
+                    );
+                    for y in first.y..=last.y {
+                        for x in first.x..=last.x {
+                            let (pos, size) = tiles_rect(U32Vec2::new(x, y), U32Vec2::new(x, y));
+                            canvas.draw_rect(pos, size, image::Rgba([128, 128, 128, 255]), 1);
+                        }
+                    }
+                }
+                if let Some(quantized) = preview {
+                    for (index, _) in quantized
+                        .changed_tiles
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, changed)| **changed)
+                    {
+                        let tile = U32Vec2::new(
+                            index as u32 % atlas.width() as u32,
+                            index as u32 / atlas.width() as u32,
+                        );
+                        let (pos, size) = tiles_rect(tile, tile);
+                        canvas.draw_rect(pos, size, image::Rgba([255, 200, 0, 255]), 1);
+                    }
+                }
+                if let Some(tile) = view.hover_tile {
+                    let (pos, size) = tiles_rect(tile, tile);
+                    canvas.draw_rect(pos, size, image::Rgba([255, 255, 255, 255]), 1);
+                }
+                let (pos, size) = tiles_rect(
+                    view.selection_pos,
+                    view.selection_pos + view.selection_size - U32Vec2::repeat(1),
                 );
+                canvas.draw_rect(pos, size, image::Rgba([255, 0, 0, 255]), 3);
             });
-            let response = image.ui(ui);
+
+            // Focusable, so the arrow keys only move the selection after clicking on the atlas
+            let response = image.ui(ui).interact(Sense::click_and_drag());
+            if response.is_pointer_button_down_on() {
+                response.request_focus();
+            }
+            view.hover_tile = None;
             if let Some(pos) = response.hover_pos() {
                 let pos = pos - response.rect.min;
-                let tile_pos = min2(&max(&pos.casted(), 0), &response.rect.max.casted())
-                    .casted()
-                    .component_div(&tile_size)
-                    .casted();
-                if ui.input(|input| input.pointer.button_pressed(PointerButton::Primary)) {
-                    view.selection_pos = tile_pos;
+                let pixel = (pos.casted() + view.camera).casted::<f32>() / view.scale;
+                let image_size = U32Vec2::new(atlas.image.width(), atlas.image.height());
+                if pixel >= F32Vec2::zeros() && pixel < image_size.casted() {
+                    let tile_pos = atlas.tile_at(pixel.map(|pixel| pixel as u32));
+                    view.hover_tile = Some(tile_pos);
+                    if ui.input(|input| input.pointer.button_pressed(PointerButton::Primary)) {
+                        view.selection_pos = tile_pos;
+                    }
+                    if ui.input(|input| input.pointer.button_down(PointerButton::Primary)) {
+                        view.selection_size = tile_pos - view.selection_pos + 1.casted();
+                    }
+                }
+
+                // * Panning and zooming
+                if ui.input(|input| input.pointer.button_down(PointerButton::Middle)) {
+                    view.camera -= ui.input(|input| input.pointer.delta().casted());
                 }
-                if ui.input(|input| input.pointer.button_down(PointerButton::Primary)) {
-                    view.selection_size = tile_pos - view.selection_pos + 1.casted();
+                let wheel = ui.input(|input| input.scroll_delta.y) / 50.0;
+                if wheel != 0.0 {
+                    let zoom_factor = 1.7f32;
+                    let zoom_delta = zoom_factor.powf(wheel);
+                    view.scale *= zoom_delta;
+                    view.camera = ((view.camera + pos.casted()).casted() * zoom_delta).casted()
+                        - pos.casted();
                 }
             }
+
+            if response.has_focus() {
+                // * Keyboard navigation: arrows move the selection, shift+arrows resize it
+                let (step, grow) = ui.input_mut(|input| {
+                    let mut arrows = |modifiers| {
+                        let mut pressed = |key| input.consume_key(modifiers, key) as i32;
+                        I32Vec2::new(
+                            pressed(Key::ArrowRight) - pressed(Key::ArrowLeft),
+                            pressed(Key::ArrowDown) - pressed(Key::ArrowUp),
+                        )
+                    };
+                    (arrows(Modifiers::NONE), arrows(Modifiers::SHIFT))
+                });
+                if step != I32Vec2::zeros() || grow != I32Vec2::zeros() {
+                    let tiles = I32Vec2::new(atlas.width() as i32, atlas.height() as i32)
+                        .map(|tiles| tiles.max(1));
+                    let size = (view.selection_size.casted::<i32>() + grow)
+                        .zip_map(&tiles, |size, tiles| size.clamp(1, tiles));
+                    let pos = (view.selection_pos.casted::<i32>() + step)
+                        .zip_map(&(tiles - size), |pos, last| pos.clamp(0, last));
+                    view.selection_pos = pos.casted();
+                    view.selection_size = size.casted();
+
+                    // Scroll the selection into view
+                    let (pos, size) = tiles_rect(
+                        view.selection_pos,
+                        view.selection_pos + view.selection_size - U32Vec2::repeat(1),
+                    );
+                    let (start, end) = (pos + view.camera, pos + size + view.camera);
+                    let screen =
+                        I32Vec2::new(response.rect.width() as _, response.rect.height() as _);
+                    view.camera = min2(&max2(&view.camera, &(end - screen)), &start);
+                }
+            }
+            if let Some(tile) = view.hover_tile {
+                response.on_hover_text_at_pointer(format!(
+                    "Tile {} ({}, {})",
+                    tile.x + tile.y * atlas.width() as u32,
+                    tile.x,
+                    tile.y
+                ));
+            }
         } else {
             assets.atlas_selected = None;
         }
@@ -144,45 +620,586 @@ pub fn show(ui: &mut Ui, assets: &mut Assets) {
     }
 }
 
-pub fn export<W: std::io::Write>(assets: &mut Assets, file: &mut W) -> Result<()> {
-    file.write_u16::<LittleEndian>(assets.atlases.len() as _)?;
-    assets.atlas_indices.clear();
-    for (index, (uuid, atlas)) in assets
-        .atlases
-        .iter()
-        .sorted_by_key(|x| &x.1.path)
+/// Shapes of the first selected tile, drawn on a zoomed copy of it
+fn collision_editor(ui: &mut Ui, atlas: &mut Atlas, view: &mut AtlasView) {
+    if atlas.width() == 0 || atlas.height() == 0 {
+        ui.label("The image is smaller than a tile");
+        return;
+    }
+    let tile = atlas.selected_tiles(view)[0];
+    ui.horizontal(|ui| {
+        ui.label(format!("Tile {}", tile));
+        ui.radio_value(&mut view.polygon_tool, false, "Rectangle");
+        ui.radio_value(&mut view.polygon_tool, true, "Polygon");
+        ui.checkbox(&mut view.one_way, "One-way");
+    });
+    ui.label(if view.polygon_tool {
+        "Click the corners, then the first one again to close the polygon. Right click cancels."
+    } else {
+        "Drag to draw a rectangle. Right click cancels."
+    });
+
+    let zoom = (ui.available_width() / atlas.tile_size.x as f32)
+        .min(16.0)
+        .floor()
+        .max(1.0);
+    let mut image = EguiImage::new(
+        (atlas.tile_size.x as f32 * zoom) as u32,
+        (atlas.tile_size.y as f32 * zoom) as u32,
+    );
+    let width = atlas.width().max(1) as u32;
+    image.draw(|canvas| {
+        canvas.fill_rect(
+            I32Vec2::zeros(),
+            canvas.size().casted(),
+            image::Rgba([64, 64, 64, 255]),
+        );
+        atlas.draw_tile(
+            canvas,
+            I32Vec2::zeros(),
+            U32Vec2::new(tile as u32 % width, tile as u32 / width),
+            canvas.size().casted(),
+        );
+        for collision in atlas.collisions.get(&tile).into_iter().flatten() {
+            collision.draw(canvas, I32Vec2::zeros(), F32Vec2::repeat(zoom));
+        }
+        let point = |point: &U16Vec2| (point.casted::<f32>() * zoom).casted::<i32>();
+        let color = image::Rgba([255, 255, 0, 255]);
+        match view.drawing.as_slice() {
+            [start, end] if !view.polygon_tool => {
+                let (min, max) = (start.inf(end), start.sup(end));
+                canvas.draw_rect(point(&min), point(&max) - point(&min), color, 1);
+            }
+            points => {
+                for (from, to) in points.iter().tuple_windows() {
+                    canvas.draw_line(point(from), point(to), color);
+                }
+            }
+        }
+    });
+    let response = image.ui(ui);
+
+    if ui.input(|input| input.pointer.button_pressed(PointerButton::Secondary)) {
+        view.drawing.clear();
+    }
+    if let Some(pos) = response.hover_pos() {
+        // Tile pixels, snapped to the closest pixel corner
+        let pos = pos - response.rect.min;
+        let corner = U16Vec2::new(
+            ((pos.x / zoom).round() as u16).min(atlas.tile_size.x),
+            ((pos.y / zoom).round() as u16).min(atlas.tile_size.y),
+        );
+        if ui.input(|input| input.pointer.button_pressed(PointerButton::Primary)) {
+            if !view.polygon_tool {
+                view.drawing = vec![corner, corner];
+            } else if view.drawing.len() >= 3 && view.drawing[0] == corner {
+                atlas.collisions.entry(tile).or_default().push(Collision {
+                    shape: CollisionShape::Polygon(std::mem::take(&mut view.drawing)),
+                    one_way: view.one_way,
+                });
+            } else if view.drawing.last() != Some(&corner) {
+                view.drawing.push(corner);
+            }
+        } else if !view.polygon_tool && view.drawing.len() == 2 {
+            view.drawing[1] = corner;
+        }
+    }
+    if !view.polygon_tool && ui.input(|input| input.pointer.button_released(PointerButton::Primary))
+    {
+        if let [start, end] = std::mem::take(&mut view.drawing)[..] {
+            let (pos, size) = (start.inf(&end), (start.sup(&end) - start.inf(&end)));
+            if size.x > 0 && size.y > 0 {
+                atlas.collisions.entry(tile).or_default().push(Collision {
+                    shape: CollisionShape::Rect { pos, size },
+                    one_way: view.one_way,
+                });
+            }
+        }
+    }
+
+    let mut removed = None;
+    for (index, collision) in atlas
+        .collisions
+        .get_mut(&tile)
+        .into_iter()
+        .flatten()
         .enumerate()
     {
-        assets.atlas_indices.insert(*uuid, index as u16);
-        file.write_u16::<LittleEndian>(atlas.tile_size.x)?;
-        file.write_u16::<LittleEndian>(atlas.tile_size.y)?;
-        file.write_u16::<LittleEndian>(
-            (atlas.image.width() / atlas.tile_size.x as u32 * atlas.image.height()
-                / atlas.tile_size.y as u32) as _,
-        )?;
-
-        // Export the image itself
+        ui.horizontal(|ui| {
+            ui.label(match &collision.shape {
+                CollisionShape::Rect { pos, size } => {
+                    format!("Rectangle at ({}, {}), {}x{}", pos.x, pos.y, size.x, size.y)
+                }
+                CollisionShape::Polygon(points) => format!("Polygon with {} corners", points.len()),
+            });
+            ui.checkbox(&mut collision.one_way, "One-way");
+            if ui.button("Remove").clicked() {
+                removed = Some(index);
+            }
+        });
+    }
+    if let Some(index) = removed {
+        let shapes = atlas.collisions.get_mut(&tile).unwrap();
+        shapes.remove(index);
+        if shapes.is_empty() {
+            atlas.collisions.remove(&tile);
+        }
+    }
+}
+
+pub fn export(assets: &mut Assets, file: &mut DataWriter) -> Result<()> {
+    let settings = assets.export_settings.clone();
+    // Atlas properties of tiles may point to atlases that come later
+    let atlas_ids = assets
+        .atlases
+        .iter()
+        .map(|(uuid, atlas)| (*uuid, atlas.export_id))
+        .collect::<HashMap<_, _>>();
+    let map_indices = assets.map_indices();
+    let used_tiles = if settings.strip_unused_tiles {
+        Some(crate::export::tiles::used_tiles(assets))
+    } else {
+        None
+    };
+
+    // Atlases are stored at their export ID, unused IDs get empty records
+    let atlases = assets
+        .atlases
+        .iter()
+        .sorted_by_key(|x| x.1.export_id)
+        .collect::<Vec<_>>();
+    file.u16(atlases.last().map_or(0, |x| x.1.export_id + 1));
+    assets.atlas_indices.clear();
+    assets.tile_indices.clear();
+    let mut next_id = 0;
+    for (uuid, atlas) in atlases {
+        for _ in next_id..atlas.export_id {
+            write_atlas_record(file, AtlasRecord::empty());
+        }
+        next_id = atlas.export_id + 1;
+
+        assets.atlas_indices.insert(*uuid, atlas.export_id);
+        let pixel_format = atlas.pixel_format.unwrap_or(settings.pixel_format);
+        let transparency = atlas.transparency.unwrap_or(settings.transparency);
+        pixel_format
+            .check(atlas.tile_size, transparency)
+            .context(format!("Can't export atlas {:?}!", atlas.path))?;
+
+        // Reduce colors first, so every tile indexes the same palette
+        let (image, palette) = if pixel_format.is_indexed() || atlas.color_limit.is_some() {
+            let quantized = crate::export::quantize::quantize_atlas(atlas, &settings);
+            let palette = if pixel_format.is_indexed() {
+                quantized.palette
+            } else {
+                settings.shades()
+            };
+            (std::borrow::Cow::Owned(quantized.image), palette)
+        } else {
+            (std::borrow::Cow::Borrowed(&atlas.image), settings.shades())
+        };
+
+        // Encode tiles, merging and stripping them if needed.
+        // Tiles that look the same but have different property values or collision shapes are kept apart,
+        // animated tiles are never merged, or a still copy of them would animate too.
+        let animated = atlas
+            .animations
+            .iter()
+            .map(|animation| animation.tile)
+            .collect::<HashSet<_>>();
+        let mut tiles = Vec::new();
+        let mut tile_indices = Vec::new();
+        let mut records = Vec::new();
+        let mut collisions = Vec::new();
+        let mut known_tiles = HashMap::new();
         for y in 0..atlas.height() {
             for x in 0..atlas.width() {
-                for pixel_y in 0..atlas.tile_size.y {
-                    for pixel_x in 0..atlas.tile_size.x {
-                        let pixel = atlas.image.get_pixel(
-                            x as u32 * atlas.tile_size.x as u32 + pixel_x as u32,
-                            y as u32 * atlas.tile_size.y as u32 + pixel_y as u32,
-                        );
-                        file.write_u16::<byteorder::BigEndian>(if pixel[3] < 128 {
-                            0xF81F
-                        } else {
-                            let r = (pixel[0] >> 3) as u16;
-                            let g = (pixel[1] >> 2) as u16;
-                            let b = (pixel[2] >> 3) as u16;
-                            r << 11 | g << 5 | b
-                        })
-                        .context("Failed to write atlas RGB pixel to exported file!")?;
+                if let Some(used_tiles) = &used_tiles {
+                    if !used_tiles[uuid][tile_indices.len()] {
+                        tile_indices.push(0xFFFF);
+                        continue;
                     }
                 }
+
+                let mut tile = DataWriter::new(&settings);
+                pixel_format.write_tile(
+                    &mut tile,
+                    &image,
+                    atlas.tile_pos(TVec2::new(x, y).casted()),
+                    atlas.tile_size,
+                    transparency,
+                    &palette,
+                );
+                let tile = tile.data;
+
+                let mut record = DataWriter::new(&settings);
+                let values = atlas.tile_values.get(&(tile_indices.len() as u16));
+                for (name, property_type) in &atlas.tile_properties {
+                    let value = property_type.fix_value(
+                        assets.atlases.keys().copied(),
+                        assets.maps.keys().copied(),
+                        values
+                            .and_then(|values| values.get(name))
+                            .map_or("", String::as_str),
+                    );
+                    property_type.write_value(&mut record, &value, &atlas_ids, &map_indices)?;
+                }
+                let record = record.data;
+
+                let mut shapes = DataWriter::new(&settings);
+                if let Some(tile_collisions) = atlas.collisions.get(&(tile_indices.len() as u16)) {
+                    write_collisions(&mut shapes, tile_collisions);
+                }
+                let shapes = shapes.data;
+
+                if settings.deduplicate_tiles && !animated.contains(&(tile_indices.len() as u16)) {
+                    let key = (tile.clone(), record.clone(), shapes.clone());
+                    if let Some(&index) = known_tiles.get(&key) {
+                        tile_indices.push(index);
+                        continue;
+                    }
+                    known_tiles.insert(key, tiles.len() as u16);
+                }
+                tile_indices.push(tiles.len() as u16);
+                tiles.push(tile);
+                records.push(record);
+                collisions.push(shapes);
             }
         }
+        // Animations of stripped tiles are dropped
+        let animations = atlas
+            .animations
+            .iter()
+            .filter_map(|animation| {
+                let exported = |tile: u16| {
+                    tile_indices
+                        .get(tile as usize)
+                        .copied()
+                        .filter(|tile| *tile != 0xFFFF)
+                };
+                Some((
+                    exported(animation.tile)?,
+                    animation.frame_time,
+                    animation
+                        .frames
+                        .iter()
+                        .map(|frame| exported(*frame))
+                        .collect::<Option<Vec<_>>>()?,
+                ))
+            })
+            .collect::<Vec<_>>();
+        if used_tiles.is_some() || settings.deduplicate_tiles {
+            assets.tile_indices.insert(*uuid, tile_indices);
+        }
+
+        if u16::try_from(tiles.len()).is_err() {
+            bail!("Atlas {:?} has too many tiles!", atlas.path);
+        }
+
+        let start = file.data.len();
+        write_atlas_record(
+            file,
+            AtlasRecord {
+                tile_size: atlas.tile_size,
+                pixel_format,
+                transparency,
+                palette,
+                tiles,
+                animations,
+                property_types: atlas.tile_properties.values().collect(),
+                records,
+                collisions,
+            },
+        );
+        assets
+            .export_sizes
+            .atlases
+            .insert(*uuid, file.data.len() - start);
     }
     Ok(())
 }
+
+/// Parts of an atlas record, already encoded where they depend on the settings
+struct AtlasRecord<'a> {
+    tile_size: U16Vec2,
+    pixel_format: PixelFormat,
+    transparency: Transparency,
+    palette: Vec<image::Rgba<u8>>,
+    tiles: Vec<Vec<u8>>,
+    /// Tile, frame time and frames
+    animations: Vec<(u16, u16, Vec<u16>)>,
+    property_types: Vec<&'a Property>,
+    /// Property values of every tile
+    records: Vec<Vec<u8>>,
+    /// Collision shapes of every tile
+    collisions: Vec<Vec<u8>>,
+}
+
+impl AtlasRecord<'_> {
+    /// Record of an unused export ID
+    fn empty() -> Self {
+        Self {
+            tile_size: U16Vec2::zeros(),
+            pixel_format: PixelFormat::Rgb565,
+            transparency: Transparency::None,
+            palette: Vec::new(),
+            tiles: Vec::new(),
+            animations: Vec::new(),
+            property_types: Vec::new(),
+            records: Vec::new(),
+            collisions: Vec::new(),
+        }
+    }
+}
+
+fn write_atlas_record(file: &mut DataWriter, record: AtlasRecord) {
+    file.align_record();
+    file.u16(record.tile_size.x);
+    file.u16(record.tile_size.y);
+    file.u16(record.tiles.len() as _);
+    file.u8(record.pixel_format as _);
+    file.u8(record.transparency.id());
+    file.u32(record.transparency.key());
+    if record.pixel_format.is_indexed() {
+        file.u16(record.palette.len() as _);
+        for color in &record.palette {
+            file.bytes(&color.0);
+        }
+    }
+    for tile in record.tiles {
+        file.bytes(&tile);
+    }
+    file.u16(record.animations.len() as _);
+    for (tile, frame_time, frames) in record.animations {
+        file.u16(tile);
+        file.u16(frame_time);
+        file.u16(frames.len() as _);
+        for frame in frames {
+            file.u16(frame);
+        }
+    }
+
+    // Tile property types, then the offset of every tile's record from the first one, then the records
+    file.u16(record.property_types.len() as _);
+    for property_type in &record.property_types {
+        file.u8(property_type.id());
+    }
+    if !record.property_types.is_empty() {
+        let mut table = DataWriter {
+            data: Vec::new(),
+            byte_order: file.byte_order,
+            alignment: file.alignment,
+        };
+        for values in record.records {
+            table.align_record();
+            file.u32(table.data.len() as _);
+            table.bytes(&values);
+        }
+        file.align_record();
+        file.bytes(&table.data);
+    }
+
+    // Collision shapes of the tiles that have any, by exported tile index
+    file.u16(
+        record
+            .collisions
+            .iter()
+            .filter(|shapes| !shapes.is_empty())
+            .count() as _,
+    );
+    for (tile, shapes) in record.collisions.iter().enumerate() {
+        if !shapes.is_empty() {
+            file.u16(tile as _);
+            file.bytes(shapes);
+        }
+    }
+}
+
+/// Shape count, then every shape: kind and flags, then its corners as bytes
+fn write_collisions(file: &mut DataWriter, collisions: &[Collision]) {
+    file.u8(collisions.len() as _);
+    for collision in collisions {
+        let one_way = if collision.one_way { 0x80 } else { 0 };
+        match &collision.shape {
+            CollisionShape::Rect { pos, size } => {
+                file.u8(one_way);
+                for value in [pos.x, pos.y, size.x, size.y] {
+                    file.u8(value as _);
+                }
+            }
+            CollisionShape::Polygon(points) => {
+                file.u8(one_way | 1);
+                file.u8(points.len() as _);
+                for point in points {
+                    file.u8(point.x as _);
+                    file.u8(point.y as _);
+                }
+            }
+        }
+    }
+}
+
+// * ---------------------------------------------------------------------------------- TESTS --------------------------------------------------------------------------------- * //
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::layout::Alignment;
+    use crate::export::reader::*;
+    use crate::export::test_project::*;
+
+    #[test]
+    fn atlas_margin_and_spacing() {
+        let mut assets = test_project();
+        // 3x1 tiles of 2x2 with a green margin of 1 and green spacing of 1 between them
+        let atlas = assets.atlases.get_mut(&ATLAS).unwrap();
+        atlas.image = image::RgbaImage::from_fn(10, 4, |x, y| match (x, y) {
+            (1..=2, 1..=2) => image::Rgba([255, 0, 0, 255]),
+            (4..=5, 1..=2) => image::Rgba([0, 0, 255, 255]),
+            (7..=8, 1..=2) => image::Rgba([0, 0, 0, 0]),
+            _ => image::Rgba([0, 255, 0, 255]),
+        });
+        atlas.tile_size = U16Vec2::new(2, 2);
+        atlas.margin = 1;
+        atlas.spacing = 1;
+        assert_eq!((atlas.width(), atlas.height()), (3, 1));
+        assert_eq!(atlas.tile_pos(U32Vec2::new(2, 0)), U32Vec2::new(7, 1));
+        assert_eq!(atlas.tile_at(U32Vec2::new(3, 0)), U32Vec2::new(0, 0));
+        assert_eq!(atlas.tile_at(U32Vec2::new(4, 2)), U32Vec2::new(1, 0));
+        assert_eq!(atlas.tile_at(U32Vec2::new(9, 3)), U32Vec2::new(2, 0));
+
+        let file = export_and_read(&mut assets);
+        assert_eq!(
+            file.atlases[0].tiles,
+            vec![RED.repeat(4), BLUE.repeat(4), TRANSPARENT.repeat(4)]
+        );
+        // The margin after the last tile is expected, anything more is not exported
+        let image_warnings = |assets: &Assets| {
+            crate::export::validate::validate(assets)
+                .into_iter()
+                .filter(|problem| problem.message.starts_with("Image is"))
+                .count()
+        };
+        assert_eq!(image_warnings(&assets), 0);
+        let atlas = assets.atlases.get_mut(&ATLAS).unwrap();
+        atlas.image = image::imageops::crop_imm(&atlas.image, 0, 0, 9, 4).to_image();
+        assert_eq!(image_warnings(&assets), 0);
+        let atlas = assets.atlases.get_mut(&ATLAS).unwrap();
+        atlas.margin = 0;
+        assert_eq!(atlas.width(), 3);
+        assert_eq!(image_warnings(&assets), 1);
+    }
+
+    #[test]
+    fn tile_properties() {
+        let mut assets = test_project();
+        // Red, blue, red, red, the last red tile hurts
+        let atlas = assets.atlases.get_mut(&ATLAS).unwrap();
+        atlas.image = image::RgbaImage::from_fn(16, 4, |x, _| match x / 4 {
+            1 => image::Rgba([0, 0, 255, 255]),
+            _ => image::Rgba([255, 0, 0, 255]),
+        });
+        atlas
+            .tile_properties
+            .insert("solid".to_owned(), Property::U8);
+        atlas
+            .tile_properties
+            .insert("sound".to_owned(), Property::String);
+        atlas
+            .tile_properties
+            .insert("damage".to_owned(), Property::I16);
+        let values = |values: &[(&str, &str)]| {
+            values
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        };
+        atlas
+            .tile_values
+            .insert(0, values(&[("solid", "1"), ("sound", "step")]));
+        atlas
+            .tile_values
+            .insert(2, values(&[("solid", "1"), ("sound", "step")]));
+        atlas.tile_values.insert(3, values(&[("damage", "-3")]));
+
+        let file = export_and_read(&mut assets);
+        let atlas = &file.atlases[0];
+        assert_eq!(atlas.tiles.len(), 4);
+        assert_eq!(
+            atlas.tile_values[1],
+            vec![Value::U8(0), Value::String(String::new()), Value::I16(0)]
+        );
+
+        // The red tiles with the same values merge, the one that hurts stays apart
+        assets.export_settings.deduplicate_tiles = true;
+        assets.export_settings.alignment = Alignment::Align4;
+        let file = export_and_read(&mut assets);
+        let atlas = &file.atlases[0];
+        assert_eq!(
+            atlas.tiles,
+            vec![RED.repeat(16), BLUE.repeat(16), RED.repeat(16)]
+        );
+        assert_eq!(
+            atlas.tile_values,
+            vec![
+                vec![
+                    Value::U8(1),
+                    Value::String("step".to_owned()),
+                    Value::I16(0)
+                ],
+                vec![Value::U8(0), Value::String(String::new()), Value::I16(0)],
+                vec![Value::U8(0), Value::String(String::new()), Value::I16(-3)],
+            ]
+        );
+        assert_eq!(file.maps[1].objects.len(), 1);
+    }
+
+    #[test]
+    fn collision_shapes() {
+        let mut assets = test_project();
+        // Red, blue, red: a half block and a one-way slope on the red tiles
+        let atlas = assets.atlases.get_mut(&ATLAS).unwrap();
+        atlas.image = image::RgbaImage::from_fn(12, 4, |x, _| match x / 4 {
+            1 => image::Rgba([0, 0, 255, 255]),
+            _ => image::Rgba([255, 0, 0, 255]),
+        });
+        let half_block = Collision {
+            shape: CollisionShape::Rect {
+                pos: U16Vec2::new(0, 2),
+                size: U16Vec2::new(4, 2),
+            },
+            one_way: false,
+        };
+        let slope = Collision {
+            shape: CollisionShape::Polygon(vec![
+                U16Vec2::new(0, 4),
+                U16Vec2::new(4, 0),
+                U16Vec2::new(4, 4),
+            ]),
+            one_way: true,
+        };
+        atlas.collisions.insert(0, vec![half_block.clone()]);
+        atlas
+            .collisions
+            .insert(2, vec![slope.clone(), half_block.clone()]);
+
+        // The red tiles have different shapes, so they are not merged
+        assets.export_settings.deduplicate_tiles = true;
+        assets.export_settings.alignment = Alignment::Align4;
+        let file = export_and_read(&mut assets);
+        assert_eq!(file.atlases[0].tiles.len(), 3);
+        assert_eq!(
+            file.atlases[0].collisions,
+            vec![
+                (0, vec![half_block.clone()]),
+                (2, vec![slope, half_block.clone()])
+            ]
+        );
+        assert_eq!(file.maps[1].objects.len(), 1);
+
+        let atlas = assets.atlases.get_mut(&ATLAS).unwrap();
+        atlas.collisions.insert(2, vec![half_block.clone()]);
+        let file = export_and_read(&mut assets);
+        assert_eq!(file.atlases[0].tiles.len(), 2);
+        assert_eq!(file.atlases[0].collisions, vec![(0, vec![half_block])]);
+        assert_eq!(file.maps[0].tiles, vec![0, 1, 0xFFFF, 0xFFFF, 0xFFFF, 0]);
+    }
+}
@@ -21,7 +21,23 @@ pub fn show(ui: &mut Ui, assets: &mut Assets) -> Result<()> {
                         }
                     }
 
-                    if ui.button(file.file_name().to_str().unwrap()).clicked() {
+                    // Tiled maps and LDtk projects are only imported on request, a click opens what they became
+                    let importable = path
+                        .extension()
+                        .is_some_and(|extension| extension == "tmx" || extension == "ldtk");
+                    let mut button = ui.button(file.file_name().to_str().unwrap());
+                    let mut import = false;
+                    if importable {
+                        button = button.on_hover_text("Right click to import");
+                        button = button.context_menu(|ui| {
+                            if ui.button("Import").clicked() {
+                                import = true;
+                                ui.close_menu();
+                            }
+                        });
+                    }
+
+                    if button.clicked() {
                         if file
                             .file_type()
                             .context("[PROBABLY A BUG] Failed to get file type!")?
@@ -30,13 +46,29 @@ pub fn show(ui: &mut Ui, assets: &mut Assets) -> Result<()> {
                             assets.content_viewer_path.push(file.file_name());
                         }
                         if let Some(extension) = path.extension() {
-                            if extension == "png" {
+                            if ATLAS_EXTENSIONS.iter().any(|atlas| extension == *atlas) {
                                 // Atlas
                                 let uuid = if let Some(uuid) = assets.uuids.get(&path) {
                                     Ok(*uuid)
+                                } else if crate::import::aseprite::is_aseprite(&path)
+                                    && !path.with_extension("atl").exists()
+                                {
+                                    // New Aseprite file, tile size and animations come from its grid and tags
+                                    crate::import::aseprite::import(assets, &path)
+                                        .map(|(uuid, warnings)| {
+                                            assets.import_warnings = warnings;
+                                            uuid
+                                        })
+                                        .context(format!(
+                                            "Failed to import Aseprite file {:?}!",
+                                            path
+                                        ))
                                 } else {
-                                    Atlas::load(&path).map(|atlas| {
+                                    Atlas::load(&path).map(|mut atlas| {
                                         let uuid = Uuid::new_v4();
+                                        atlas.export_id = free_export_id(
+                                            assets.atlases.values().map(|atlas| atlas.export_id),
+                                        );
                                         assets.atlases.insert(uuid, atlas);
                                         assets.uuids.insert(path.clone(), uuid);
                                         uuid
@@ -49,8 +81,11 @@ pub fn show(ui: &mut Ui, assets: &mut Assets) -> Result<()> {
                                 let uuid = if let Some(uuid) = assets.uuids.get(&path) {
                                     Ok(*uuid)
                                 } else {
-                                    Map::load(&path).map(|map| {
+                                    Map::load(&path).map(|mut map| {
                                         let uuid = Uuid::new_v4();
+                                        map.export_id = free_export_id(
+                                            assets.maps.values().map(|map| map.export_id),
+                                        );
                                         assets.maps.insert(uuid, map);
                                         assets.uuids.insert(path.clone(), uuid);
                                         uuid
@@ -64,8 +99,14 @@ pub fn show(ui: &mut Ui, assets: &mut Assets) -> Result<()> {
                                 let uuid = if let Some(uuid) = assets.uuids.get(&path) {
                                     Ok(*uuid)
                                 } else {
-                                    Component::load(&path).map(|component| {
+                                    Component::load(&path).map(|mut component| {
                                         let uuid = Uuid::new_v4();
+                                        component.export_id = free_export_id(
+                                            assets
+                                                .components
+                                                .values()
+                                                .map(|component| component.export_id),
+                                        );
                                         assets.components.insert(uuid, component);
                                         assets.uuids.insert(path.clone(), uuid);
                                         uuid
@@ -74,9 +115,45 @@ pub fn show(ui: &mut Ui, assets: &mut Assets) -> Result<()> {
 
                                 assets.component_selected = Some(ComponentView::new(uuid));
                                 assets.object_selected = None;
+                            } else if extension == "tmx" {
+                                // Tiled map, once imported
+                                if let Some(uuid) = assets.uuids.get(&path.with_extension("map")) {
+                                    assets.map_selected = Some(MapView::new(*uuid));
+                                    assets.object_selected = None;
+                                }
                             }
                         }
                     }
+
+                    if import && path.extension().is_some_and(|extension| extension == "tmx") {
+                        // Tiled map
+                        let (uuid, warnings) = crate::import::tiled::import(
+                            assets,
+                            &path,
+                            &path.with_extension("map"),
+                        )
+                        .context(format!("Failed to import Tiled map {:?}!", path))?;
+                        assets.import_warnings = warnings;
+
+                        assets.map_selected = Some(MapView::new(uuid));
+                        assets.object_selected = None;
+                    } else if import {
+                        // LDtk project, except for the export of this one, which would overwrite its own maps
+                        if path == assets.path.join(crate::export::ldtk::PROJECT_FILE) {
+                            bail!(
+                                "{:?} is written by Export LDtk, importing it would overwrite the maps it was exported from!",
+                                path
+                            );
+                        }
+                        let (maps, warnings) = crate::import::ldtk::import(assets, &path)
+                            .context(format!("Failed to import LDtk project {:?}!", path))?;
+                        assets.import_warnings = warnings;
+
+                        if let Some(uuid) = maps.first() {
+                            assets.map_selected = Some(MapView::new(*uuid));
+                            assets.object_selected = None;
+                        }
+                    }
                 }
 
                 // Create new map/component
@@ -137,7 +214,10 @@ pub fn show(ui: &mut Ui, assets: &mut Assets) -> Result<()> {
                                     let path = assets
                                         .content_viewer_path
                                         .join(format!("{}.map", new_map.name));
-                                    let map = Map::new(&path, new_map.size, new_map.atlas);
+                                    let mut map = Map::new(&path, new_map.size, new_map.atlas);
+                                    map.export_id = free_export_id(
+                                        assets.maps.values().map(|map| map.export_id),
+                                    );
                                     map.save()?;
                                     assets.maps.insert(uuid, map);
                                     assets.uuids.insert(path, uuid);
@@ -162,7 +242,13 @@ pub fn show(ui: &mut Ui, assets: &mut Assets) -> Result<()> {
                                     let path = assets
                                         .content_viewer_path
                                         .join(format!("{}.cmp", new_component_name));
-                                    let component = Component::new(&path);
+                                    let mut component = Component::new(&path);
+                                    component.export_id = free_export_id(
+                                        assets
+                                            .components
+                                            .values()
+                                            .map(|component| component.export_id),
+                                    );
                                     component.save()?;
                                     assets.components.insert(uuid, component);
                                     assets.uuids.insert(path, uuid);
@@ -1,3 +1,4 @@
+use crate::export::layout::DataWriter;
 use crate::project::*;
 use itertools::Itertools;
 use std::collections::HashMap;
@@ -24,15 +25,17 @@ impl Object {
 
     pub fn altas<'a>(&mut self, atlases: &'a HashMap<Uuid, Atlas>) -> Result<Option<&'a Atlas>> {
         if let Some(atlas) = self.components.get(&ATLAS_RENDERER_UUID) {
-            if let Some(atlas) = atlases.get(
-                &Uuid::parse_str(
-                    atlas
-                        .get("Atlas")
-                        .context("[PROBABLY A BUG] Atlas component without atlas!")?,
-                )
-                .context("[PROBABLY A BUG] Atlas UUID in atlas renderer is invalid!")?,
-            ) {
+            let uuid = Uuid::parse_str(
+                atlas
+                    .get("Atlas")
+                    .context("[PROBABLY A BUG] Atlas component without atlas!")?,
+            )
+            .context("[PROBABLY A BUG] Atlas UUID in atlas renderer is invalid!")?;
+            if let Some(atlas) = atlases.get(&uuid) {
                 Ok(Some(atlas))
+            } else if uuid.is_nil() {
+                // Not picked yet
+                Ok(None)
             } else {
                 self.components.remove(&ATLAS_RENDERER_UUID);
                 Ok(None)
@@ -47,6 +50,8 @@ impl Object {
 pub struct Map {
     #[serde(skip)]
     pub path: PathBuf,
+    #[serde(skip)]
+    pub export_id: u16,
     #[serde(default)]
     pub size: U16Vec2,
     #[serde(default)]
@@ -61,6 +66,7 @@ impl Map {
     pub fn new(path: &Path, size: U16Vec2, atlas: Uuid) -> Self {
         Self {
             path: path.to_path_buf(),
+            export_id: 0,
             size,
             data: vec![TVec2::new(-1, -1); size.x as usize * size.y as usize],
             atlas,
@@ -118,6 +124,8 @@ pub struct MapView {
     pub scale: f32,
     pub hover_tile: Option<U32Vec2>,
     pub mode: EditingMode,
+    /// Draw the collision shapes of the tiles over the map
+    pub show_collisions: bool,
 }
 
 impl MapView {
@@ -128,6 +136,7 @@ impl MapView {
             scale: 1.0,
             hover_tile: None,
             mode: EditingMode::None,
+            show_collisions: false,
         }
     }
 }
@@ -135,6 +144,12 @@ impl MapView {
 // * ---------------------------------------------------------------------------------- SHOW ---------------------------------------------------------------------------------- * //
 pub fn show(ui: &mut Ui, assets: &mut Assets, status: &mut RichText) -> Result<()> {
     if let Some(view) = assets.map_selected.as_mut() {
+        let taken_ids = assets
+            .maps
+            .iter()
+            .filter(|(uuid, _)| **uuid != view.map)
+            .map(|(_, map)| map.export_id)
+            .collect();
         let map = assets
             .maps
             .get_mut(&view.map)
@@ -159,6 +174,11 @@ pub fn show(ui: &mut Ui, assets: &mut Assets, status: &mut RichText) -> Result<(
                 }
                 map.data = new_data;
             }
+            ui.separator();
+
+            crate::view::settings_view::export_id_edit(ui, &mut map.export_id, &taken_ids);
+            ui.separator();
+            ui.checkbox(&mut view.show_collisions, "Show collisions");
         });
 
         let mut image = EguiImage::new(ui.available_size().x as _, ui.available_size().y as _);
@@ -179,6 +199,13 @@ pub fn show(ui: &mut Ui, assets: &mut Assets, status: &mut RichText) -> Result<(
             TVec2::new(1, 1)
         };
 
+        // Animations play while the editor is open
+        let frame = (ui.input(|input| input.time) * ANIMATION_FPS) as u64;
+        if !atlas.animations.is_empty() {
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs_f64(1.0 / ANIMATION_FPS));
+        }
+
         image.draw(|canvas| -> Result<()> {
             let camera_tile =
                 &max(&view.camera.casted().component_div(&tile_size), 0.0).casted::<i32>();
@@ -191,17 +218,37 @@ pub fn show(ui: &mut Ui, assets: &mut Assets, status: &mut RichText) -> Result<(
             for y in camera_tile.y..br_tile.y {
                 for x in camera_tile.x..br_tile.x {
                     let tile = map.get_tile(TVec2::new(x, y).casted());
-                    if tile < TVec2::zeros() {
+                    // Tiles outside of the atlas are reported by validation, or after a reload that shrank the image
+                    if !atlas.contains_tile(tile) {
                         continue;
                     }
                     atlas.draw_tile(
                         canvas,
                         TVec2::new(x, y).casted().component_mul(&tile_size).casted() - view.camera,
-                        tile.casted(),
+                        atlas.animated_tile(tile.casted(), frame),
                         ceil(&tile_size).casted(),
                     );
                 }
             }
+            if view.show_collisions {
+                for y in camera_tile.y..br_tile.y {
+                    for x in camera_tile.x..br_tile.x {
+                        let tile = map.get_tile(TVec2::new(x, y).casted());
+                        if !atlas.contains_tile(tile) {
+                            continue;
+                        }
+                        let index = (tile.x as i32 + tile.y as i32 * atlas.width() as i32) as u16;
+                        for collision in atlas.collisions.get(&index).into_iter().flatten() {
+                            collision.draw(
+                                canvas,
+                                TVec2::new(x, y).casted().component_mul(&tile_size).casted()
+                                    - view.camera,
+                                F32Vec2::repeat(view.scale),
+                            );
+                        }
+                    }
+                }
+            }
 
             let mut draw_object = |uuid: &Uuid, object: &mut Object| -> Result<()> {
                 if let Some(atlas) = object.altas(&assets.atlases)? {
@@ -209,13 +256,7 @@ pub fn show(ui: &mut Ui, assets: &mut Assets, status: &mut RichText) -> Result<(
                         (object.pos.casted() * view.scale).casted() - view.camera,
                         (atlas.tile_size.casted() * view.scale).casted(),
                     );
-                    canvas.draw_subimage(
-                        &atlas.image,
-                        pos,
-                        size,
-                        TVec2::zeros(),
-                        atlas.tile_size.casted(),
-                    );
+                    atlas.draw_tile(canvas, pos, TVec2::zeros(), size);
                     if assets.object_selected == Some(*uuid) {
                         canvas.draw_rect(pos, size, image::Rgba([255, 0, 0, 255]), 3);
                     }
@@ -407,59 +448,71 @@ pub fn show(ui: &mut Ui, assets: &mut Assets, status: &mut RichText) -> Result<(
     Ok(())
 }
 
-pub fn export<W: std::io::Write>(assets: &mut Assets, file: &mut W) -> Result<()> {
-    file.write_u16::<LittleEndian>(assets.maps.len() as _)
-        .context("Failed to export map count!")?;
-
+pub fn export(assets: &mut Assets, file: &mut DataWriter) -> Result<()> {
     let valid_maps = assets.maps.keys().copied().collect::<Vec<_>>();
-    let mut component_indices = HashMap::new();
-    let mut map_indices = HashMap::new();
+    let component_indices = assets.component_indices();
+    let map_indices = assets.map_indices();
 
-    // * Fill component indices
-    for (index, (uuid, _)) in assets
-        .components
-        .iter_mut()
-        .sorted_by_key(|x| x.1.path.clone())
-        .enumerate()
-    {
-        component_indices.insert(*uuid, index);
-    }
-
-    // * Fill map indices
-    for (index, (uuid, _)) in assets
+    // Maps are stored at their export ID, unused IDs get empty records
+    let maps = assets
         .maps
         .iter_mut()
-        .sorted_by_key(|x| x.1.path.clone())
-        .enumerate()
-    {
-        map_indices.insert(*uuid, index);
-    }
+        .sorted_by_key(|x| x.1.export_id)
+        .collect::<Vec<_>>();
+    file.u16(maps.last().map_or(0, |x| x.1.export_id + 1));
 
     // * Export maps
-    for (_uuid, map) in assets.maps.iter_mut().sorted_by_key(|x| x.1.path.clone()) {
-        file.write_u16::<LittleEndian>(map.size.x)?;
-        file.write_u16::<LittleEndian>(map.size.y)?;
-        file.write_u16::<LittleEndian>(assets.atlas_indices[&map.atlas])?;
+    let mut next_id = 0;
+    for (map_uuid, map) in maps {
+        for _ in next_id..map.export_id {
+            write_map_header(file, U16Vec2::zeros(), 0xFFFF);
+            file.u16(0);
+        }
+        next_id = map.export_id + 1;
+
+        let start = file.data.len();
+        write_map_header(file, map.size, assets.atlas_indices[&map.atlas]);
         for y in 0..map.size.y {
             for x in 0..map.size.x {
                 let tile = map.get_tile(TVec2::new(x, y).casted());
-                file.write_u16::<LittleEndian>(if tile < TVec2::zeros() {
+                file.u16(if tile < TVec2::zeros() {
                     0xFFFF
                 } else {
-                    (tile.x + tile.y * assets.atlases[&map.atlas].width() as i16) as _
-                })?;
+                    let index =
+                        (tile.x + tile.y * assets.atlases[&map.atlas].width() as i16) as u16;
+                    if let Some(tile_indices) = assets.tile_indices.get(&map.atlas) {
+                        tile_indices.get(index as usize).copied().unwrap_or(0xFFFF)
+                    } else {
+                        index
+                    }
+                });
             }
         }
 
-        // * Export objects
-        file.write_u16::<LittleEndian>(map.objects.len() as _)?;
-        for object in map.objects.values_mut() {
-            file.write_i32::<LittleEndian>(object.pos.x)?;
-            file.write_i32::<LittleEndian>(object.pos.y)?;
-            file.write_u8(if object.always_on_top { 1 } else { 0 })?;
-
-            file.write_u16::<LittleEndian>(object.components.len() as _)?;
-            for (uuid, properties) in &mut object.components {
+        assets
+            .export_sizes
+            .map_tiles
+            .insert(*map_uuid, file.data.len() - start);
+
+        // * Export objects, row by row, so the same project always exports the same bytes
+        let start = file.data.len();
+        file.u16(map.objects.len() as _);
+        for (_, object) in map
+            .objects
+            .iter_mut()
+            .sorted_by_key(|(uuid, object)| (object.pos.y, object.pos.x, **uuid))
+        {
+            file.align_record();
+            file.i32(object.pos.x);
+            file.i32(object.pos.y);
+            file.u8(if object.always_on_top { 1 } else { 0 });
+
+            file.u16(object.components.len() as _);
+            for (uuid, properties) in object
+                .components
+                .iter_mut()
+                .sorted_by_key(|(uuid, _)| component_indices.get(uuid).copied())
+            {
                 let component = assets
                     .components
                     .get(uuid)
@@ -471,63 +524,32 @@ pub fn export<W: std::io::Write>(assets: &mut Assets, file: &mut W) -> Result<()
                 );
 
                 // * Export component
-                file.write_u16::<LittleEndian>(*component_indices.get(uuid).context(
+                let component_start = file.data.len();
+                file.align_record();
+                file.u16(*component_indices.get(uuid).context(
                     "[PROBABLY A BUG] Failed to get component index while exporting map!",
-                )? as _)?;
+                )? as _);
                 for (name, property_type) in &component.properties {
                     let value = properties.get(name).context("[PROBABLY A BUG] Failed to get object's component property while exporting map!")?;
-                    let context = format!(
-                        "[PROBABLY A BUG] Failed to parse numeric property {}!",
-                        value
-                    );
-
-                    // * Export property value
-                    match property_type {
-                        Property::I8 => {
-                            file.write_i8(value.parse().context(context)?)?;
-                        }
-                        Property::U8 => {
-                            file.write_u8(value.parse().context(context)?)?;
-                        }
-                        Property::I16 => {
-                            file.write_i16::<LittleEndian>(value.parse().context(context)?)?;
-                        }
-                        Property::U16 => {
-                            file.write_u16::<LittleEndian>(value.parse().context(context)?)?;
-                        }
-                        Property::I32 => {
-                            file.write_i32::<LittleEndian>(value.parse().context(context)?)?;
-                        }
-                        Property::U32 => {
-                            file.write_u32::<LittleEndian>(value.parse().context(context)?)?;
-                        }
-                        Property::F32 => {
-                            file.write_f32::<LittleEndian>(value.parse().context(context)?)?;
-                        }
-                        Property::String => {
-                            file.write_u16::<LittleEndian>(value.as_bytes().len() as _)?;
-                            file.write_all(value.as_bytes())?;
-                        }
-                        Property::Atlas => {
-                            file.write_u16::<LittleEndian>(
-                                assets.atlas_indices[&Uuid::parse_str(value).context(format!(
-                                    "[PROBABLY A BUG] Failed to parse atlas property {}!",
-                                    value
-                                ))?],
-                            )?;
-                        }
-                        Property::Map => {
-                            file.write_u16::<LittleEndian>(
-                                map_indices[&Uuid::parse_str(value).context(format!(
-                                    "[PROBABLY A BUG] Failed to parse map property {}!",
-                                    value
-                                ))?] as _,
-                            )?;
-                        }
-                    }
+                    property_type.write_value(file, value, &assets.atlas_indices, &map_indices)?;
                 }
+                *assets.export_sizes.components.entry(*uuid).or_default() +=
+                    file.data.len() - component_start;
             }
         }
+        assets
+            .export_sizes
+            .map_objects
+            .insert(*map_uuid, file.data.len() - start);
     }
     Ok(())
 }
+
+/// Size and atlas, followed by the tiles and then the objects.
+/// An unused export ID gets a 0x0 map with atlas 0xFFFF and no objects.
+fn write_map_header(file: &mut DataWriter, size: U16Vec2, atlas: u16) {
+    file.align_record();
+    file.u16(size.x);
+    file.u16(size.y);
+    file.u16(atlas);
+}
@@ -1,3 +1,4 @@
+use crate::export::layout::DataWriter;
 use crate::project::*;
 use indexmap::IndexMap;
 use itertools::Itertools;
@@ -11,6 +12,8 @@ pub const SERIALIZE_UUID: Uuid = uuid::uuid!("8d4db834-82c3-4c27-b49c-7c32c638e1
 pub struct Component {
     #[serde(skip)]
     pub path: PathBuf,
+    #[serde(skip)]
+    pub export_id: u16,
     pub properties: IndexMap<String, Property>,
 }
 
@@ -18,6 +21,7 @@ impl Component {
     pub fn new(path: &Path) -> Self {
         Self {
             path: path.to_path_buf(),
+            export_id: 0,
             properties: IndexMap::new(),
         }
     }
@@ -91,29 +95,28 @@ impl ComponentView {
 pub fn show(ui: &mut Ui, assets: &mut Assets) -> Result<()> {
     // * Component
     if let Some(view) = &mut assets.component_selected {
+        let taken_ids = assets
+            .components
+            .iter()
+            .filter(|(uuid, _)| **uuid != view.component)
+            .map(|(_, component)| component.export_id)
+            .collect();
         if let Some(component) = assets.components.get_mut(&view.component) {
-            fn property_type_select(
-                ui: &mut Ui,
-                property_type: &mut Property,
-                id: impl std::hash::Hash,
-            ) {
-                ComboBox::from_id_source(id)
-                    .selected_text(format!("{:?}", property_type))
-                    .show_ui(ui, |ui| {
-                        for type_variant in Property::VALUES {
-                            ui.selectable_value(
-                                property_type,
-                                type_variant.clone(),
-                                format!("{:?}", type_variant),
-                            );
-                        }
-                    });
-            }
-
             ui.label(format!(
                 "Component: {}",
                 component.path.file_stem().unwrap().to_str().unwrap()
             ));
+            if component.path.starts_with("/\nbuiltin/") {
+                ui.label(format!("ID {}", component.export_id));
+            } else {
+                ui.horizontal(|ui| {
+                    crate::view::settings_view::export_id_edit(
+                        ui,
+                        &mut component.export_id,
+                        &taken_ids,
+                    );
+                });
+            }
             ui.separator();
 
             let mut remove = None;
@@ -177,6 +180,111 @@ pub fn show(ui: &mut Ui, assets: &mut Assets) -> Result<()> {
         }
     }
 
+    // * Tile
+    if let Some(view) = &mut assets.atlas_selected {
+        let atlas_names = assets
+            .atlases
+            .iter()
+            .map(|(uuid, atlas)| {
+                (
+                    *uuid,
+                    atlas.path.file_stem().unwrap().to_str().unwrap().to_owned(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let map_names = assets
+            .maps
+            .iter()
+            .map(|(uuid, map)| {
+                (
+                    *uuid,
+                    map.path.file_stem().unwrap().to_str().unwrap().to_owned(),
+                )
+            })
+            .collect::<Vec<_>>();
+        if let Some(atlas) = assets.atlases.get_mut(&view.atlas) {
+            let tiles = atlas.selected_tiles(view);
+            if tiles.len() == 1 {
+                ui.label(format!("Tile {}", tiles[0]));
+            } else {
+                ui.label(format!("Tiles {} and {} more", tiles[0], tiles.len() - 1));
+            }
+
+            let mut remove = None;
+            for (name, property_type) in atlas.tile_properties.iter_mut() {
+                ui.horizontal(|ui| {
+                    ui.label(name);
+                    property_type_select(ui, property_type, ("tile_property", name));
+                    if ui.button("Remove").clicked() {
+                        remove = Some(name.clone());
+                    }
+                });
+            }
+            if let Some(name) = remove {
+                atlas.tile_properties.shift_remove(&name);
+                for values in atlas.tile_values.values_mut() {
+                    values.remove(&name);
+                }
+                atlas.tile_values.retain(|_, values| !values.is_empty());
+            }
+            if view.adding.is_some() {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut view.adding.as_mut().unwrap().0);
+                    property_type_select(
+                        ui,
+                        &mut view.adding.as_mut().unwrap().1,
+                        "New Tile Property",
+                    );
+                });
+                if let Some(accepted) = ok_cancel(ui) {
+                    if accepted {
+                        let (name, property_type) = view.adding.clone().unwrap();
+                        atlas.tile_properties.insert(name, property_type);
+                    }
+                    view.adding = None;
+                }
+            } else if ui.button("Add Tile Property").clicked() {
+                view.adding = Some(("".to_owned(), Property::U8));
+            }
+
+            // Values of the first selected tile, changes apply to the whole selection
+            for (name, property_type) in atlas.tile_properties.iter() {
+                let old_value = property_type.fix_value(
+                    atlas_names.iter().map(|(uuid, _)| *uuid),
+                    map_names.iter().map(|(uuid, _)| *uuid),
+                    atlas
+                        .tile_values
+                        .get(&tiles[0])
+                        .and_then(|values| values.get(name))
+                        .map_or("", String::as_str),
+                );
+                let mut value = old_value.clone();
+                ui.horizontal(|ui| -> Result<()> {
+                    ui.label(name);
+                    property_value_input(
+                        ui,
+                        atlas_names.iter().cloned(),
+                        map_names.iter().cloned(),
+                        ("tile_value", name),
+                        property_type,
+                        &mut value,
+                    )
+                })
+                .inner?;
+                if value != old_value {
+                    for tile in &tiles {
+                        atlas
+                            .tile_values
+                            .entry(*tile)
+                            .or_default()
+                            .insert(name.clone(), value.clone());
+                    }
+                }
+            }
+            ui.separator();
+        }
+    }
+
     // * Object
     if let Some(object) = assets.object_selected {
         let map_names = assets
@@ -328,21 +436,74 @@ impl Property {
         Self::Map,
     ];
 
+    /// Type byte in the data file
+    pub fn id(&self) -> u8 {
+        Self::VALUES.iter().position(|value| value == self).unwrap() as _
+    }
+
+    /// Writes a value fixed by `fix_value`, atlases and maps as their export IDs, 0xFFFF if nil
+    pub fn write_value(
+        &self,
+        file: &mut DataWriter,
+        value: &str,
+        atlas_indices: &HashMap<Uuid, u16>,
+        map_indices: &HashMap<Uuid, u16>,
+    ) -> Result<()> {
+        let context = format!(
+            "[PROBABLY A BUG] Failed to parse numeric property {}!",
+            value
+        );
+        match self {
+            Property::I8 => file.i8(value.parse().context(context)?),
+            Property::U8 => file.u8(value.parse().context(context)?),
+            Property::I16 => file.i16(value.parse().context(context)?),
+            Property::U16 => file.u16(value.parse().context(context)?),
+            Property::I32 => file.i32(value.parse().context(context)?),
+            Property::U32 => file.u32(value.parse().context(context)?),
+            Property::F32 => file.f32(value.parse().context(context)?),
+            Property::String => {
+                file.u16(value.len() as _);
+                file.bytes(value.as_bytes());
+            }
+            Property::Atlas => {
+                file.u16(match Uuid::parse_str(value) {
+                    Ok(uuid) if uuid.is_nil() => 0xFFFF,
+                    uuid => *uuid
+                        .ok()
+                        .and_then(|uuid| atlas_indices.get(&uuid))
+                        .context(format!(
+                            "Atlas property points to {}, which is not in the project!",
+                            value
+                        ))?,
+                });
+            }
+            Property::Map => {
+                file.u16(match Uuid::parse_str(value) {
+                    Ok(uuid) if uuid.is_nil() => 0xFFFF,
+                    uuid => *uuid
+                        .ok()
+                        .and_then(|uuid| map_indices.get(&uuid))
+                        .context(format!(
+                            "Map property points to {}, which is not in the project!",
+                            value
+                        ))?,
+                });
+            }
+        }
+        Ok(())
+    }
+
     pub fn fix_value(
         &self,
         valid_atalses: impl Iterator<Item = Uuid>,
         valid_maps: impl Iterator<Item = Uuid>,
         value: &str,
     ) -> String {
+        /// A missing or dangling UUID becomes nil, which is exported as 0xFFFF. Validation reports nil.
         fn fix_uuid(mut source: impl Iterator<Item = Uuid>, value: &str) -> String {
-            if let Ok(uuid) = Uuid::parse_str(value) {
-                if !source.contains(&uuid) {
-                    source.next().unwrap().to_string()
-                } else {
-                    value.to_owned()
-                }
-            } else {
-                source.next().unwrap().to_string()
+            match Uuid::parse_str(value) {
+                Ok(uuid) if source.contains(&uuid) => value.to_owned(),
+                _ => Uuid::nil().to_string(),
             }
         }
 
@@ -361,17 +522,38 @@ impl Property {
     }
 }
 
+pub fn property_type_select(ui: &mut Ui, property_type: &mut Property, id: impl std::hash::Hash) {
+    ComboBox::from_id_source(id)
+        .selected_text(format!("{:?}", property_type))
+        .show_ui(ui, |ui| {
+            for type_variant in Property::VALUES {
+                ui.selectable_value(
+                    property_type,
+                    type_variant.clone(),
+                    format!("{:?}", type_variant),
+                );
+            }
+        });
+}
+
 pub fn pick_uuid(
     ui: &mut Ui,
     names: impl Iterator<Item = (Uuid, String)> + Clone,
     id: impl std::hash::Hash,
     value: &mut Uuid,
 ) -> Result<()> {
-    ComboBox::from_id_source(id)
-        .selected_text(names.clone().find(|(uuid, _)| uuid == value).context(
+    let selected =
+        if value.is_nil() {
+            "None".to_owned()
+        } else {
+            names.clone().find(|(uuid, _)| uuid == value).context(
             "[PROBABLY A BUG] Atlas/Map from property was not found! Perhaps it was deleted?",
-        )?.1)
+        )?.1
+        };
+    ComboBox::from_id_source(id)
+        .selected_text(selected)
         .show_ui(ui, |ui| {
+            ui.selectable_value(value, Uuid::nil(), "None");
             for (uuid, name) in names.into_iter().sorted_by_key(|(_, name)| name.clone()) {
                 ui.selectable_value(value, uuid, name);
             }
@@ -379,7 +561,7 @@ pub fn pick_uuid(
     Ok(())
 }
 
-fn property_value_input(
+pub fn property_value_input(
     ui: &mut Ui,
     atlas_names: impl Iterator<Item = (Uuid, String)> + Clone,
     map_names: impl Iterator<Item = (Uuid, String)> + Clone,
@@ -0,0 +1,78 @@
+use crate::export::validate::Severity;
+use crate::project::*;
+
+// * ---------------------------------------------------------------------------------- SHOW ---------------------------------------------------------------------------------- * //
+pub fn show(ctx: &egui::Context, assets: &mut Assets) {
+    let mut open = !assets.import_warnings.is_empty();
+    Window::new("Import Report")
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ScrollArea::vertical().show(ui, |ui| {
+                for warning in &assets.import_warnings {
+                    ui.label(warning);
+                }
+            });
+        });
+    if !open {
+        assets.import_warnings.clear();
+    }
+
+    let mut open = !assets.export_problems.is_empty();
+    Window::new("Export Report")
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ScrollArea::vertical().show(ui, |ui| {
+                for problem in &assets.export_problems {
+                    ui.horizontal(|ui| {
+                        let color = match problem.severity {
+                            Severity::Warning => Color32::YELLOW,
+                            Severity::Error => Color32::RED,
+                        };
+                        ui.label(RichText::new(format!("{:?}", problem.severity)).color(color));
+                        ui.label(problem.asset.to_string_lossy());
+                        ui.label(&problem.message);
+                    });
+                }
+            });
+        });
+    if !open {
+        assets.export_problems.clear();
+    }
+
+    let mut open = assets.size_report_open;
+    Window::new("Size Report").open(&mut open).show(ctx, |ui| {
+        let sizes = &assets.export_sizes;
+        if sizes.total == 0 {
+            ui.label("Export the project to see what data.dat is made of");
+            return;
+        }
+        ScrollArea::vertical().show(ui, |ui| {
+            ui.label(format!("data.dat: {} bytes", sizes.total));
+            Grid::new("size_report_sections").show(ui, |ui| {
+                ui.label("Header");
+                ui.label(format!("{} bytes", sizes.header));
+                ui.end_row();
+                for (tag, stored, length) in &sizes.sections {
+                    ui.label(format!("Section {}", String::from_utf8_lossy(tag)));
+                    ui.label(format!("{} bytes", stored));
+                    if stored != length {
+                        ui.label(format!("{} before compression", length));
+                    }
+                    ui.end_row();
+                }
+            });
+            for (group, entries) in sizes.groups(assets) {
+                ui.collapsing(format!("{} ({} entries)", group, entries.len()), |ui| {
+                    Grid::new(group).show(ui, |ui| {
+                        for (name, size) in entries {
+                            ui.label(name);
+                            ui.label(format!("{} bytes", size));
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+        });
+    });
+    assets.size_report_open = open;
+}
@@ -0,0 +1,142 @@
+use crate::export::compression::Compression;
+use crate::export::layout::*;
+use crate::export::pixel_format::*;
+use crate::project::*;
+use std::collections::HashSet;
+
+// * ---------------------------------------------------------------------------------- SHOW ---------------------------------------------------------------------------------- * //
+pub fn show(ctx: &egui::Context, assets: &mut Assets) {
+    let settings = &mut assets.export_settings;
+    Window::new("Export Settings")
+        .open(&mut assets.export_settings_open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            Grid::new("export_settings").show(ui, |ui| {
+                ui.label("Pixel format");
+                pixel_format_select(ui, &mut settings.pixel_format, "export_pixel_format");
+                ui.end_row();
+
+                ui.label("Transparency");
+                transparency_select(ui, &mut settings.transparency, "export_transparency");
+                ui.end_row();
+
+                ui.label("2bpp palette");
+                ui.horizontal(|ui| {
+                    for shade in settings.palette.iter_mut() {
+                        ui.color_edit_button_srgb(shade);
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Tiles");
+                ui.vertical(|ui| {
+                    ui.checkbox(&mut settings.deduplicate_tiles, "Merge duplicates");
+                    ui.checkbox(&mut settings.strip_unused_tiles, "Strip unused");
+                });
+                ui.end_row();
+
+                ui.label("Atlas compression");
+                compression_select(ui, &mut settings.atlas_compression, "atlas_compression");
+                ui.end_row();
+
+                ui.label("Map compression");
+                compression_select(ui, &mut settings.map_compression, "map_compression");
+                ui.end_row();
+
+                ui.label("Byte order");
+                ComboBox::from_id_source("byte_order")
+                    .selected_text(format!("{:?}", settings.byte_order))
+                    .show_ui(ui, |ui| {
+                        for variant in ByteOrder::VALUES {
+                            ui.selectable_value(
+                                &mut settings.byte_order,
+                                variant,
+                                format!("{:?}", variant),
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Alignment");
+                ComboBox::from_id_source("alignment")
+                    .selected_text(format!("{:?}", settings.alignment))
+                    .show_ui(ui, |ui| {
+                        for variant in Alignment::VALUES {
+                            ui.selectable_value(
+                                &mut settings.alignment,
+                                variant,
+                                format!("{:?}", variant),
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Size budget");
+                ui.horizontal(|ui| {
+                    ui.label("Warn above");
+                    ui.add(egui::DragValue::new(&mut settings.size_warning).suffix(" B"));
+                    ui.label("Fail above");
+                    ui.add(egui::DragValue::new(&mut settings.size_limit).suffix(" B"));
+                })
+                .response
+                .on_hover_text("data.dat size in bytes, 0 for no budget");
+                ui.end_row();
+
+                ui.label("Generate");
+                ui.vertical(|ui| {
+                    ui.checkbox(&mut settings.generate_c_header, "C header (.h)");
+                    ui.checkbox(&mut settings.generate_rust_module, "Rust module (.rs)");
+                });
+                ui.end_row();
+            });
+        });
+}
+
+pub fn pixel_format_select(ui: &mut Ui, pixel_format: &mut PixelFormat, id: impl std::hash::Hash) {
+    ComboBox::from_id_source(id)
+        .selected_text(format!("{:?}", pixel_format))
+        .show_ui(ui, |ui| {
+            for variant in PixelFormat::VALUES {
+                ui.selectable_value(pixel_format, variant, format!("{:?}", variant));
+            }
+        });
+}
+
+pub fn transparency_select(ui: &mut Ui, transparency: &mut Transparency, id: impl std::hash::Hash) {
+    ui.horizontal(|ui| {
+        ComboBox::from_id_source(id)
+            .selected_text(match transparency {
+                Transparency::None => "None",
+                Transparency::ColorKey(_) => "Color key",
+                Transparency::Alpha => "Alpha",
+            })
+            .show_ui(ui, |ui| {
+                let key = transparency.key();
+                ui.selectable_value(transparency, Transparency::None, "None");
+                ui.selectable_value(transparency, Transparency::ColorKey(key), "Color key");
+                ui.selectable_value(transparency, Transparency::Alpha, "Alpha");
+            });
+        if let Transparency::ColorKey(key) = transparency {
+            ui.add(egui::DragValue::new(key).hexadecimal(4, false, true));
+        }
+    });
+}
+
+pub fn compression_select(ui: &mut Ui, compression: &mut Compression, id: impl std::hash::Hash) {
+    ComboBox::from_id_source(id)
+        .selected_text(format!("{:?}", compression))
+        .show_ui(ui, |ui| {
+            for variant in Compression::VALUES {
+                ui.selectable_value(compression, variant, format!("{:?}", variant));
+            }
+        });
+}
+
+/// Export ID field, warns if another asset of the same kind already uses the ID
+pub fn export_id_edit(ui: &mut Ui, export_id: &mut u16, taken: &HashSet<u16>) {
+    ui.label("ID");
+    ui.add(egui::DragValue::new(export_id).clamp_range(0..=MAX_EXPORT_ID));
+    if taken.contains(export_id) {
+        ui.colored_label(Color32::RED, "Already used!");
+    }
+}
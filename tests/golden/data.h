@@ -0,0 +1,103 @@
+/* Generated by OreMapper from the project, do not edit! */
+#ifndef ORE_DATA_H
+#define ORE_DATA_H
+
+#include <stdint.h>
+#include <string.h>
+
+#define ORE_FORMAT_VERSION 8
+#define ORE_ALIGNMENT 1
+
+/* Collision shape header: kind in the low bits, one-way flag in the top bit */
+#define ORE_SHAPE_RECT 0
+#define ORE_SHAPE_POLYGON 1
+#define ORE_SHAPE_ONE_WAY 0x80
+
+#define ORE_ATLAS_TILES 0
+#define ORE_ATLAS_COUNT 1
+
+#define ORE_MAP_LEVEL 0
+#define ORE_MAP_SHOP 1
+#define ORE_MAP_COUNT 2
+
+#define ORE_COMPONENT_ATLAS_RENDERER 0
+#define ORE_COMPONENT_SERIALIZE 1
+#define ORE_COMPONENT_DOOR 2
+#define ORE_COMPONENT_COUNT 3
+
+typedef struct {
+    uint16_t length;
+    const uint8_t *data;
+} OreString;
+
+/* Values are aligned in memory as long as data.dat is loaded at a multiple of ORE_ALIGNMENT */
+static inline const uint8_t *ore_align(const uint8_t *data, uintptr_t alignment) {
+    return data + (alignment - (uintptr_t)data % alignment) % alignment;
+}
+
+static inline uint16_t ore_read_u16(const uint8_t *data) {
+    return (uint16_t)(data[0] | data[1] << 8);
+}
+
+static inline uint32_t ore_read_u32(const uint8_t *data) {
+    return (uint32_t)data[0] | (uint32_t)data[1] << 8 | (uint32_t)data[2] << 16 | (uint32_t)data[3] << 24;
+}
+
+static inline float ore_read_f32(const uint8_t *data) {
+    uint32_t bits = ore_read_u32(data);
+    float value;
+    memcpy(&value, &bits, sizeof(value));
+    return value;
+}
+
+typedef struct {
+    uint16_t atlas; /* ORE_ATLAS_* */
+} OreAtlasRenderer;
+
+/* Reads component values, returns pointer past them */
+static inline const uint8_t *ore_read_atlas_renderer(const uint8_t *data, OreAtlasRenderer *out) {
+    out->atlas = ore_read_u16(data);
+    data += 2;
+    return data;
+}
+
+typedef struct {
+    uint16_t target; /* ORE_MAP_* */
+    uint16_t sprite; /* ORE_ATLAS_* */
+    int8_t key;
+    uint8_t count;
+    int16_t dx;
+    uint16_t dy;
+    int32_t id;
+    uint32_t flags;
+    float speed;
+    OreString name;
+} OreDoor;
+
+/* Reads component values, returns pointer past them */
+static inline const uint8_t *ore_read_door(const uint8_t *data, OreDoor *out) {
+    out->target = ore_read_u16(data);
+    data += 2;
+    out->sprite = ore_read_u16(data);
+    data += 2;
+    out->key = (int8_t)data[0];
+    data += 1;
+    out->count = data[0];
+    data += 1;
+    out->dx = (int16_t)ore_read_u16(data);
+    data += 2;
+    out->dy = ore_read_u16(data);
+    data += 2;
+    out->id = (int32_t)ore_read_u32(data);
+    data += 4;
+    out->flags = ore_read_u32(data);
+    data += 4;
+    out->speed = ore_read_f32(data);
+    data += 4;
+    out->name.length = ore_read_u16(data);
+    out->name.data = data + 2;
+    data += 2 + out->name.length;
+    return data;
+}
+
+#endif
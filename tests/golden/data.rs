@@ -0,0 +1,110 @@
+// Generated by OreMapper from the project, do not edit!
+
+pub const FORMAT_VERSION: u16 = 8;
+pub const ALIGNMENT: usize = 1;
+
+/// Collision shape header: kind in the low bits, one-way flag in the top bit
+pub const SHAPE_RECT: u8 = 0;
+pub const SHAPE_POLYGON: u8 = 1;
+pub const SHAPE_ONE_WAY: u8 = 0x80;
+
+pub const ATLAS_TILES: u16 = 0;
+pub const ATLAS_COUNT: u16 = 1;
+
+pub const MAP_LEVEL: u16 = 0;
+pub const MAP_SHOP: u16 = 1;
+pub const MAP_COUNT: u16 = 2;
+
+pub const COMPONENT_ATLAS_RENDERER: u16 = 0;
+pub const COMPONENT_SERIALIZE: u16 = 1;
+pub const COMPONENT_DOOR: u16 = 2;
+pub const COMPONENT_COUNT: u16 = 3;
+
+#[allow(dead_code)]
+fn take<const N: usize>(data: &mut &[u8]) -> Option<[u8; N]> {
+    let (bytes, rest) = data.split_first_chunk::<N>()?;
+    *data = rest;
+    Some(*bytes)
+}
+
+/// Values are aligned in memory as long as data.dat is loaded at a multiple of ALIGNMENT
+#[allow(dead_code)]
+fn align(data: &mut &[u8], alignment: usize) -> Option<()> {
+    let padding = (alignment - data.as_ptr() as usize % alignment) % alignment;
+    *data = data.get(padding..)?;
+    Some(())
+}
+
+#[allow(dead_code)]
+fn take_str<'a>(data: &mut &'a [u8]) -> Option<&'a str> {
+    let length = u16::from_le_bytes(take(data)?) as usize;
+    if data.len() < length {
+        return None;
+    }
+    let (bytes, rest) = data.split_at(length);
+    *data = rest;
+    core::str::from_utf8(bytes).ok()
+}
+
+pub struct AtlasRenderer {
+    /// ATLAS_*
+    pub atlas: u16,
+}
+
+impl AtlasRenderer {
+    pub const INDEX: u16 = 0;
+
+    /// Reads component values and advances data past them
+    pub fn read(data: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            atlas: u16::from_le_bytes(take(data)?),
+        })
+    }
+}
+
+pub struct Serialize;
+
+impl Serialize {
+    pub const INDEX: u16 = 1;
+
+    /// Reads component values and advances data past them
+    pub fn read(data: &mut &[u8]) -> Option<Self> {
+        let _ = data;
+        Some(Self)
+    }
+}
+
+pub struct Door<'a> {
+    /// MAP_*
+    pub target: u16,
+    /// ATLAS_*
+    pub sprite: u16,
+    pub key: i8,
+    pub count: u8,
+    pub dx: i16,
+    pub dy: u16,
+    pub id: i32,
+    pub flags: u32,
+    pub speed: f32,
+    pub name: &'a str,
+}
+
+impl<'a> Door<'a> {
+    pub const INDEX: u16 = 2;
+
+    /// Reads component values and advances data past them
+    pub fn read(data: &mut &'a [u8]) -> Option<Self> {
+        Some(Self {
+            target: u16::from_le_bytes(take(data)?),
+            sprite: u16::from_le_bytes(take(data)?),
+            key: i8::from_le_bytes(take(data)?),
+            count: u8::from_le_bytes(take(data)?),
+            dx: i16::from_le_bytes(take(data)?),
+            dy: u16::from_le_bytes(take(data)?),
+            id: i32::from_le_bytes(take(data)?),
+            flags: u32::from_le_bytes(take(data)?),
+            speed: f32::from_le_bytes(take(data)?),
+            name: take_str(data)?,
+        })
+    }
+}